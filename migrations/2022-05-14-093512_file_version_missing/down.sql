@@ -0,0 +1,2 @@
+ALTER TABLE file_version
+DROP COLUMN missing;
@@ -0,0 +1,2 @@
+ALTER TABLE file_version
+ADD COLUMN missing BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS rejected_file (
+    id SERIAL PRIMARY KEY,
+    full_path TEXT NOT NULL UNIQUE,
+    reason INTEGER NOT NULL,
+    matched_rule TEXT,
+    rejected_at TIMESTAMP NOT NULL DEFAULT NOW()
+)
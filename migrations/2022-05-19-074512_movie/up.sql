@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS movie (
+    generic_uid     INTEGER REFERENCES generic (generic_uid) PRIMARY KEY,
+    movie_title     TEXT NOT NULL,
+    movie_year      INTEGER NOT NULL
+)
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS sidecar (
+    id              SERIAL PRIMARY KEY,
+    generic_uid     INTEGER REFERENCES generic (generic_uid) NOT NULL,
+    full_path       TEXT NOT NULL UNIQUE,
+    kind            INTEGER NOT NULL,
+    language        TEXT
+)
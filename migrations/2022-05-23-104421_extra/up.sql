@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS extra (
+    generic_uid         INTEGER REFERENCES generic (generic_uid) PRIMARY KEY,
+    show_uid            INTEGER REFERENCES show (show_uid),
+    movie_generic_uid   INTEGER REFERENCES movie (generic_uid)
+)
@@ -0,0 +1,7 @@
+ALTER TABLE episode
+DROP COLUMN release_source;
+UPDATE show
+SET show_title = show_title || ' (' || show_year || ')'
+WHERE show_year IS NOT NULL;
+ALTER TABLE show
+DROP COLUMN show_year;
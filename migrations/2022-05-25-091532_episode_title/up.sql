@@ -0,0 +1,8 @@
+ALTER TABLE show
+ADD COLUMN show_year INTEGER;
+UPDATE show
+SET show_year = CAST(substring(show_title from '\(((?:18|19|20)\d{2})\)\s*$') AS INTEGER),
+    show_title = regexp_replace(show_title, '[\s._-]*\((?:18|19|20)\d{2}\)\s*$', '')
+WHERE show_title ~ '\((18|19|20)\d{2}\)\s*$';
+ALTER TABLE episode
+ADD COLUMN release_source TEXT;
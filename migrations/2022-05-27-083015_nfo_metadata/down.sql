@@ -0,0 +1,5 @@
+ALTER TABLE episode
+DROP COLUMN air_date,
+DROP COLUMN plot;
+ALTER TABLE show
+DROP COLUMN plot;
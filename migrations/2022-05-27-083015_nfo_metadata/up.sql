@@ -0,0 +1,5 @@
+ALTER TABLE show
+ADD COLUMN plot TEXT;
+ALTER TABLE episode
+ADD COLUMN air_date TEXT,
+ADD COLUMN plot TEXT;
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS hash_run (
+    id SERIAL PRIMARY KEY,
+    total_files INTEGER NOT NULL,
+    total_bytes BIGINT NOT NULL,
+    hashed_files INTEGER NOT NULL DEFAULT 0,
+    hashed_bytes BIGINT NOT NULL DEFAULT 0,
+    finished BOOLEAN NOT NULL DEFAULT FALSE,
+    started_at TIMESTAMP NOT NULL DEFAULT NOW()
+)
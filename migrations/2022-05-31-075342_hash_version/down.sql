@@ -0,0 +1,4 @@
+ALTER TABLE hash_run
+DROP COLUMN rehash_outdated;
+ALTER TABLE file_version
+DROP COLUMN hash_version;
@@ -0,0 +1,6 @@
+ALTER TABLE file_version
+ADD COLUMN hash_version INTEGER NOT NULL DEFAULT 2;
+UPDATE file_version SET hash_version = 1
+WHERE file_hash IS NOT NULL OR fast_file_hash IS NOT NULL;
+ALTER TABLE hash_run
+ADD COLUMN rehash_outdated BOOLEAN NOT NULL DEFAULT FALSE;
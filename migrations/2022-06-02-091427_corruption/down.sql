@@ -0,0 +1,4 @@
+DROP TABLE corruption;
+ALTER TABLE file_version
+DROP COLUMN corrupted,
+DROP COLUMN verified_at;
@@ -0,0 +1,12 @@
+ALTER TABLE file_version
+ADD COLUMN corrupted BOOLEAN NOT NULL DEFAULT FALSE,
+ADD COLUMN verified_at TIMESTAMP;
+CREATE TABLE IF NOT EXISTS corruption (
+    id              SERIAL PRIMARY KEY,
+    file_version_id INTEGER REFERENCES file_version (id) ON DELETE CASCADE NOT NULL,
+    generic_uid     INTEGER NOT NULL,
+    full_path       TEXT NOT NULL,
+    expected_hash   VARCHAR NOT NULL,
+    actual_hash     VARCHAR NOT NULL,
+    detected_at     TIMESTAMP NOT NULL DEFAULT NOW()
+);
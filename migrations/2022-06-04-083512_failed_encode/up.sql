@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS failed_encode (
+    id              SERIAL PRIMARY KEY,
+    generic_uid     INTEGER NOT NULL,
+    source_path     TEXT NOT NULL,
+    target_path     TEXT NOT NULL,
+    quarantine_path TEXT,
+    reason          TEXT NOT NULL,
+    failed_at       TIMESTAMP NOT NULL DEFAULT NOW()
+);
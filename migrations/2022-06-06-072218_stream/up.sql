@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS stream (
+    id              SERIAL PRIMARY KEY,
+    file_version_id INTEGER REFERENCES file_version (id) ON DELETE CASCADE NOT NULL,
+    stream_index    INTEGER NOT NULL,
+    stream_type     INTEGER NOT NULL,
+    codec           TEXT NOT NULL,
+    profile         TEXT,
+    level           TEXT,
+    bit_depth       INTEGER,
+    bitrate         BIGINT,
+    channels        INTEGER,
+    language        TEXT,
+    is_default      BOOLEAN NOT NULL DEFAULT FALSE,
+    is_forced       BOOLEAN NOT NULL DEFAULT FALSE,
+    UNIQUE (file_version_id, stream_index)
+);
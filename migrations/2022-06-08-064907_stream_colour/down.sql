@@ -0,0 +1,6 @@
+ALTER TABLE stream
+DROP COLUMN transfer_characteristics,
+DROP COLUMN colour_primaries,
+DROP COLUMN matrix_coefficients,
+DROP COLUMN mastering_display,
+DROP COLUMN content_light_level;
@@ -0,0 +1,10 @@
+ALTER TABLE stream
+ADD COLUMN transfer_characteristics TEXT,
+ADD COLUMN colour_primaries TEXT,
+ADD COLUMN matrix_coefficients TEXT,
+ADD COLUMN mastering_display TEXT,
+ADD COLUMN content_light_level TEXT;
+
+-- Only video streams have colour data. File versions without a video stream are profiled again,
+-- which reads the new colour columns, and their audio and subtitle streams are kept until then
+DELETE FROM stream WHERE stream_type = 1;
@@ -0,0 +1 @@
+DROP TABLE completed_encode;
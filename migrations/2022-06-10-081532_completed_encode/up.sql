@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS completed_encode (
+    id             SERIAL PRIMARY KEY,
+    generic_uid    INTEGER NOT NULL,
+    encode_profile TEXT NOT NULL,
+    source_size    BIGINT NOT NULL,
+    output_size    BIGINT NOT NULL,
+    finished_at    TIMESTAMP NOT NULL DEFAULT NOW()
+);
@@ -16,6 +16,7 @@ use {
         encode::Encode,
         file_manager::FileManager,
         scheduler::{Scheduler, Task},
+        watcher::FileWatcher,
         worker::Worker,
         worker_manager::WorkerManager,
         ws::run_web,
@@ -85,6 +86,7 @@ async fn main() -> Result<(), IoError> {
         worker_mananger_workers,
         worker_mananger_transcode_queue.clone(),
         600,
+        server_config.clone(),
     )));
     let file_manager: Arc<Mutex<FileManager>> =
         Arc::new(Mutex::new(FileManager::new(server_config.clone())));
@@ -117,6 +119,16 @@ async fn main() -> Result<(), IoError> {
         inner_worker_manager
     });
 
+    //Watch the tracked directories so new files are imported as they arrive
+    let stop_watcher = Arc::new(AtomicBool::new(false));
+    let mut watcher_handle = None;
+    if server_config.read().unwrap().watch_tracked_directories {
+        watcher_handle = Some(
+            FileWatcher::new(server_config.clone(), file_manager.clone(), tasks.clone())
+                .start_watching(stop_watcher.clone()),
+        );
+    }
+
     let port = server_config.read().unwrap().port;
 
     if !preferences.disable_input {
@@ -133,10 +145,14 @@ async fn main() -> Result<(), IoError> {
 
     stop_scheduler.store(true, Ordering::Relaxed);
     stop_worker_mananger_polling.store(true, Ordering::Relaxed);
+    stop_watcher.store(true, Ordering::Relaxed);
 
     //manual shutdown tasks or other manipulation
     let _scheduler = scheduler_handle.join().unwrap();
     let _worker_manager = worker_manager_polling_handle.join().unwrap();
+    if let Some(watcher_handle) = watcher_handle {
+        watcher_handle.join().unwrap();
+    }
 
     Ok(())
 }
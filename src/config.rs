@@ -1,10 +1,19 @@
 //!Set of functions and structures to make is easier to handle the config file
 //!and command line arguments
 use {
-    crate::{ensure_path_exists, file_manager::TrackedDirectories, pathbuf_to_string},
+    crate::{
+        device::{get_builtin_devices, DeviceProfile},
+        encode::EncodeProfile,
+        ensure_path_exists,
+        file_manager::{LibraryType, RootDirectory, TrackedDirectories},
+        ignore::IgnoreRules,
+        parser::EpisodeParseRule,
+        path_mapping::{PathMapper, PathMapping, WorkerPathMappings},
+        pathbuf_to_string,
+        prober::ProberBackend,
+    },
     argparse::{ArgumentParser, Store, StoreOption, StoreTrue},
     directories::BaseDirs,
-    fancy_regex::Regex,
     serde::{Deserialize, Serialize},
     std::{env, fmt, fs, path::Path, path::PathBuf},
     tracing::{error, warn},
@@ -17,10 +26,81 @@ use {
 pub struct ServerConfig {
     pub port: u16,
     pub allowed_extensions: Vec<String>,
+    //Gitignore-style globs
     pub ignored_paths: Vec<String>,
+    //Regexes matched against the full path
+    #[serde(default)]
+    pub ignored_regexes: Vec<String>,
     #[serde(skip)]
-    pub ignored_paths_regex: Vec<Regex>,
+    pub ignore_rules: IgnoreRules,
+    #[serde(default = "default_watch_tracked_directories")]
+    pub watch_tracked_directories: bool,
+    //Seconds a new file's size has to stay the same before it is imported
+    #[serde(default = "default_watcher_settle_time")]
+    pub watcher_settle_time: u64,
+    //Profiles that also encode specials, extras and trailers when encoding everything
+    #[serde(default)]
+    pub bulk_encode_extras_profiles: Vec<EncodeProfile>,
+    //Prefixes swapped when sending paths to workers that mount the library somewhere else
+    #[serde(default)]
+    pub path_mappings: Vec<PathMapping>,
+    //Per worker mappings, tried before path_mappings
+    #[serde(default)]
+    pub worker_path_mappings: Vec<WorkerPathMappings>,
+    //Files hashed at the same time across all devices
+    #[serde(default = "default_hash_threads")]
+    pub hash_threads: usize,
+    //Files hashed at the same time from any one device, 1 suits spinning disks
+    #[serde(default = "default_hash_threads_per_device")]
+    pub hash_threads_per_device: usize,
+    //Days between verifying each file against its hash to find corruption, 0 disables it
+    #[serde(default = "default_verify_interval_days")]
+    pub verify_interval_days: u64,
+    //Megabytes per second files are read at while verifying, 0 doesn't limit it
+    #[serde(default = "default_verify_rate")]
+    pub verify_rate: u64,
+    //Seconds the length of an encode can differ from its source before it's rejected
+    #[serde(default = "default_encode_duration_tolerance")]
+    pub encode_duration_tolerance: f64,
+    //Tool used to read the width, height, frame rate and length of files, ffprobe or mediainfo
+    #[serde(default)]
+    pub media_prober: ProberBackend,
+    //Devices checked for direct play on top of the built in ones, a device with the same name as
+    //a built in one replaces it
+    #[serde(default)]
+    pub devices: Vec<DeviceProfile>,
     pub tracked_directories: TrackedDirectories,
+    //Tried in order, the first rule to match a file name is used
+    #[serde(default = "EpisodeParseRule::default_rules")]
+    pub episode_parse_rules: Vec<EpisodeParseRule>,
+}
+
+fn default_watch_tracked_directories() -> bool {
+    true
+}
+
+fn default_watcher_settle_time() -> u64 {
+    10
+}
+
+fn default_hash_threads() -> usize {
+    4
+}
+
+fn default_hash_threads_per_device() -> usize {
+    1
+}
+
+fn default_verify_interval_days() -> u64 {
+    30
+}
+
+fn default_verify_rate() -> u64 {
+    25
+}
+
+fn default_encode_duration_tolerance() -> f64 {
+    2.0
 }
 
 impl ServerConfig {
@@ -29,8 +109,9 @@ impl ServerConfig {
         let ignored_paths = vec![String::from(".recycle_bin")];
         let mut tracked_directories = TrackedDirectories::default();
         //TODO: Remove hardcoding
-        tracked_directories.add_root_directory(PathBuf::from(
-            r"C:\Users\Alexi Peck\Desktop\tlm\test_files".to_string(),
+        tracked_directories.add_root_directory(RootDirectory::new(
+            PathBuf::from(r"C:\Users\Alexi Peck\Desktop\tlm\test_files".to_string()),
+            LibraryType::Mixed,
         ));
         //TODO: Have a better method for getting a temp network share directory from the user
         tracked_directories
@@ -39,8 +120,22 @@ impl ServerConfig {
             port: 8888,
             allowed_extensions,
             ignored_paths,
-            ignored_paths_regex: Vec::new(),
+            ignored_regexes: Vec::new(),
+            ignore_rules: IgnoreRules::default(),
             tracked_directories,
+            watch_tracked_directories: default_watch_tracked_directories(),
+            watcher_settle_time: default_watcher_settle_time(),
+            bulk_encode_extras_profiles: Vec::new(),
+            path_mappings: Vec::new(),
+            worker_path_mappings: Vec::new(),
+            hash_threads: default_hash_threads(),
+            hash_threads_per_device: default_hash_threads_per_device(),
+            verify_interval_days: default_verify_interval_days(),
+            verify_rate: default_verify_rate(),
+            encode_duration_tolerance: default_encode_duration_tolerance(),
+            media_prober: ProberBackend::default(),
+            devices: Vec::new(),
+            episode_parse_rules: EpisodeParseRule::default_rules(),
         }
     }
 
@@ -93,14 +188,43 @@ impl ServerConfig {
             config.port = preferences.port.unwrap();
         }
 
-        for ignored_path in &config.ignored_paths {
-            config
-                .ignored_paths_regex
-                .push(Regex::new(&format!("(?i){}", regex::escape(ignored_path))).unwrap())
+        config.ignore_rules = IgnoreRules::new(&config.ignored_paths, &config.ignored_regexes);
+
+        for root_directory in config.tracked_directories.get_root_directories_mut() {
+            root_directory.compile();
+        }
+
+        for episode_parse_rule in config.episode_parse_rules.iter_mut() {
+            episode_parse_rule.compile();
         }
 
         config
     }
+
+    pub fn get_path_mapper(&self, worker_uid: Option<i32>) -> PathMapper {
+        PathMapper::new(&self.path_mappings, &self.worker_path_mappings, worker_uid)
+    }
+
+    ///The built in devices followed by the ones from the config
+    pub fn get_devices(&self) -> Vec<DeviceProfile> {
+        let mut devices: Vec<DeviceProfile> = get_builtin_devices()
+            .into_iter()
+            .filter(|builtin| {
+                !self
+                    .devices
+                    .iter()
+                    .any(|device| device.name == builtin.name)
+            })
+            .collect();
+        devices.extend(self.devices.iter().cloned());
+        devices
+    }
+
+    pub fn get_device(&self, name: &str) -> Option<DeviceProfile> {
+        self.get_devices()
+            .into_iter()
+            .find(|device| device.name == name)
+    }
 }
 
 #[derive(Deserialize, Serialize, Clone)]
@@ -1,19 +1,37 @@
 use {
     crate::{
-        designation::Designation, generic::FileVersion, generic::Generic, model::WorkerModel,
-        model::*, schema::episode as episode_table, schema::episode::dsl::episode as episode_db,
-        schema::file_version as file_version_table,
+        designation::Designation, extra::Extra, generic::FileVersion, generic::Generic,
+        hashing::HashProgress, model::WorkerModel, model::*, movie::Movie, pathbuf_to_string,
+        schema::completed_encode as completed_encode_table, schema::corruption as corruption_table,
+        schema::episode as episode_table, schema::episode::dsl::episode as episode_db,
+        schema::extra as extra_table, schema::extra::dsl::extra as extra_db,
+        schema::failed_encode as failed_encode_table, schema::file_version as file_version_table,
         schema::file_version::dsl::file_version as file_version_data,
         schema::generic as generic_table, schema::generic::designation,
-        schema::generic::dsl::generic as generic_data, schema::show as show_table,
-        schema::show::dsl::show as show_db, schema::worker as worker_table,
-        schema::worker::dsl::worker as worker_data, show::Episode, show::Show, worker::Worker,
+        schema::generic::dsl::generic as generic_data, schema::hash_run as hash_run_table,
+        schema::movie as movie_table, schema::movie::dsl::movie as movie_db,
+        schema::rejected_file as rejected_file_table,
+        schema::rejected_file::dsl::rejected_file as rejected_file_data,
+        schema::show as show_table, schema::show::dsl::show as show_db,
+        schema::sidecar as sidecar_table, schema::sidecar::dsl::sidecar as sidecar_db,
+        schema::stream as stream_table, schema::worker as worker_table,
+        schema::worker::dsl::worker as worker_data, show::Episode, show::Show, sidecar::Sidecar,
+        stream::Stream, worker::Worker,
     },
-    diesel::{pg::PgConnection, prelude::*},
-    std::{collections::VecDeque, env},
-    tracing::{debug, error},
+    diesel::{
+        pg::{upsert::excluded, PgConnection},
+        prelude::*,
+    },
+    std::{
+        collections::{HashMap, VecDeque},
+        env,
+        path::Path,
+    },
+    tracing::{debug, error, warn},
 };
 
+const REJECTED_FILES_CHUNK_SIZE: usize = 5000;
+
 ///Sets up a connection to the database via DATABASE_URL environment variable
 pub fn establish_connection() -> PgConnection {
     let database_url = env::var("DATABASE_URL").unwrap_or_else(|err| {
@@ -36,6 +54,102 @@ pub fn update_file_version(file_version: &FileVersion, connection: &PgConnection
     }
 }
 
+///Only updates the hashes so that other changes made to the FileVersion while it was being hashed
+///aren't overwritten
+pub fn update_file_version_hashes(connection: &PgConnection, file_version: &FileVersion) {
+    if let Err(err) = diesel::update(file_version_table::table.find(file_version.id))
+        .set((
+            file_version_table::file_hash.eq(&file_version.hash),
+            file_version_table::fast_file_hash.eq(&file_version.fast_hash),
+            file_version_table::hash_version.eq(file_version.hash_version),
+        ))
+        .execute(connection)
+    {
+        error!("Failed to update file_version hashes. Err: {}", err);
+        panic!();
+    }
+}
+
+///Stores when the FileVersion was last verified and whether it matched its hash
+pub fn update_file_version_verification(connection: &PgConnection, file_version: &FileVersion) {
+    if let Err(err) = diesel::update(file_version_table::table.find(file_version.id))
+        .set((
+            file_version_table::corrupted.eq(file_version.corrupted),
+            file_version_table::verified_at.eq(file_version.verified_at),
+        ))
+        .execute(connection)
+    {
+        error!("Failed to update file_version verification. Err: {}", err);
+        panic!();
+    }
+}
+
+pub fn create_corruption(connection: &PgConnection, new_corruption: NewCorruption) {
+    if let Err(err) = diesel::insert_into(corruption_table::table)
+        .values(&new_corruption)
+        .execute(connection)
+    {
+        error!("Error saving corruption. Err: {}", err);
+        panic!();
+    }
+}
+
+///Every corruption found, most recently detected first
+pub fn get_all_corruptions() -> Vec<CorruptionModel> {
+    corruption_table::table
+        .order(corruption_table::detected_at.desc())
+        .load::<CorruptionModel>(&establish_connection())
+        .unwrap_or_else(|err| {
+            error!("Error loading corruptions. Err: {}", err);
+            panic!();
+        })
+}
+
+pub fn create_failed_encode(connection: &PgConnection, new_failed_encode: NewFailedEncode) {
+    if let Err(err) = diesel::insert_into(failed_encode_table::table)
+        .values(&new_failed_encode)
+        .execute(connection)
+    {
+        error!("Error saving failed encode. Err: {}", err);
+        panic!();
+    }
+}
+
+///Every encode that failed, most recent first
+pub fn get_all_failed_encodes() -> Vec<FailedEncodeModel> {
+    failed_encode_table::table
+        .order(failed_encode_table::failed_at.desc())
+        .load::<FailedEncodeModel>(&establish_connection())
+        .unwrap_or_else(|err| {
+            error!("Error loading failed encodes. Err: {}", err);
+            panic!();
+        })
+}
+
+pub fn create_completed_encode(
+    connection: &PgConnection,
+    new_completed_encode: NewCompletedEncode,
+) {
+    if let Err(err) = diesel::insert_into(completed_encode_table::table)
+        .values(&new_completed_encode)
+        .execute(connection)
+    {
+        error!("Error saving completed encode. Err: {}", err);
+        panic!();
+    }
+}
+
+///Every encode that was added to the library with the given profile
+pub fn get_completed_encodes(encode_profile: &str) -> Vec<CompletedEncodeModel> {
+    completed_encode_table::table
+        .filter(completed_encode_table::encode_profile.eq(encode_profile))
+        .load::<CompletedEncodeModel>(&establish_connection())
+        .unwrap_or_else(|err| {
+            error!("Error loading completed encodes. Err: {}", err);
+            panic!();
+        })
+}
+
 ///Inserts generic data into the database
 pub fn create_generics(conn: &PgConnection, new_generics: Vec<NewGeneric>) -> Vec<GenericModel> {
     diesel::insert_into(generic_table::table)
@@ -65,10 +179,67 @@ pub fn create_file_version(new_file_version: NewFileVersion) -> FileVersionModel
     create_file_versions(&establish_connection(), vec![new_file_version])[0].to_owned()
 }
 
-///Inserts show data into the database
-pub fn create_show(connection: &PgConnection, show_title: String) -> ShowModel {
-    let new_show = NewShow { show_title };
+///Inserts movie data into the database
+pub fn create_movies(connection: &PgConnection, new_movies: Vec<NewMovie>) -> Vec<MovieModel> {
+    diesel::insert_into(movie_table::table)
+        .values(&new_movies)
+        .get_results(connection)
+        .unwrap_or_else(|err| {
+            error!("Error saving new movies. Err: {}", err);
+            panic!();
+        })
+}
+
+///Inserts extra data into the database
+pub fn create_extras(connection: &PgConnection, new_extras: Vec<NewExtra>) -> Vec<ExtraModel> {
+    diesel::insert_into(extra_table::table)
+        .values(&new_extras)
+        .get_results(connection)
+        .unwrap_or_else(|err| {
+            error!("Error saving new extras. Err: {}", err);
+            panic!();
+        })
+}
+
+///Inserts sidecar data into the database
+pub fn create_sidecars(
+    connection: &PgConnection,
+    new_sidecars: Vec<NewSidecar>,
+) -> Vec<SidecarModel> {
+    diesel::insert_into(sidecar_table::table)
+        .values(&new_sidecars)
+        .get_results(connection)
+        .unwrap_or_else(|err| {
+            error!("Error saving new sidecars. Err: {}", err);
+            panic!();
+        })
+}
+
+///Points a sidecar at a new path after it has been renamed
+pub fn update_sidecar_path(connection: &PgConnection, sidecar_id: i32, full_path: &Path) {
+    if let Err(err) = diesel::update(sidecar_table::table.find(sidecar_id))
+        .set(sidecar_table::full_path.eq(pathbuf_to_string(full_path)))
+        .execute(connection)
+    {
+        error!("Error updating sidecar. Err: {}", err);
+        panic!();
+    }
+}
+
+pub fn get_all_sidecars() -> Vec<Sidecar> {
+    sidecar_db
+        .load::<SidecarModel>(&establish_connection())
+        .unwrap_or_else(|err| {
+            error!("Error loading sidecars. Err: {}", err);
+            panic!();
+        })
+        .into_iter()
+        .filter_map(Sidecar::from_model)
+        .collect()
+}
 
+///Inserts show data into the database
+pub fn create_show(connection: &PgConnection, new_show: NewShow) -> ShowModel {
     diesel::insert_into(show_table::table)
         .values(&new_show)
         .get_result(connection)
@@ -78,6 +249,47 @@ pub fn create_show(connection: &PgConnection, show_title: String) -> ShowModel {
         })
 }
 
+pub fn update_show(connection: &PgConnection, show: &Show) {
+    if let Err(err) = diesel::update(show_table::table.find(show.show_uid))
+        .set((
+            show_table::show_title.eq(&show.show_title),
+            show_table::show_year.eq(show.show_year),
+            show_table::plot.eq(&show.plot),
+        ))
+        .execute(connection)
+    {
+        error!("Failed to update show. Err: {}", err);
+        panic!();
+    }
+}
+
+///Updates the metadata of one episode number of an episode, multi-episode files have a row for each
+pub fn update_episode_metadata(
+    connection: &PgConnection,
+    episode: &Episode,
+    episode_number: i32,
+    episode_title: &str,
+    air_date: Option<&str>,
+    plot: Option<&str>,
+) {
+    if let Err(err) = diesel::update(episode_table::table.find((
+        episode.generic.get_generic_uid(),
+        episode.show_uid,
+        episode.show_season,
+        episode_number,
+    )))
+    .set((
+        episode_table::episode_title.eq(episode_title),
+        episode_table::air_date.eq(air_date),
+        episode_table::plot.eq(plot),
+    ))
+    .execute(connection)
+    {
+        error!("Failed to update episode. Err: {}", err);
+        panic!();
+    }
+}
+
 ///Inserts episode data into the database
 pub fn create_episodes(conn: &PgConnection, new_episode: Vec<NewEpisode>) -> Vec<EpisodeModel> {
     diesel::insert_into(episode_table::table)
@@ -89,6 +301,137 @@ pub fn create_episodes(conn: &PgConnection, new_episode: Vec<NewEpisode>) -> Vec
         })
 }
 
+///Inserts rejected files into the database, replacing the reason for any that already exist.
+///Rejected files are only kept for the WebUI, so failing to save them is logged rather than fatal
+pub fn create_or_update_rejected_files(
+    connection: &PgConnection,
+    new_rejected_files: Vec<NewRejectedFile>,
+) {
+    //Each row takes 4 bind parameters and a query can only have 65535 of them
+    for chunk in new_rejected_files.chunks(REJECTED_FILES_CHUNK_SIZE) {
+        if let Err(err) = diesel::insert_into(rejected_file_table::table)
+            .values(chunk)
+            .on_conflict(rejected_file_table::full_path)
+            .do_update()
+            .set((
+                rejected_file_table::reason.eq(excluded(rejected_file_table::reason)),
+                rejected_file_table::matched_rule.eq(excluded(rejected_file_table::matched_rule)),
+                rejected_file_table::rejected_at.eq(excluded(rejected_file_table::rejected_at)),
+            ))
+            .execute(connection)
+        {
+            error!("Error saving {} rejected files. Err: {}", chunk.len(), err);
+        }
+    }
+}
+
+///Deletes rejected files that are no longer rejected
+pub fn delete_rejected_files(connection: &PgConnection, full_paths: Vec<String>) {
+    //Each path takes a bind parameter, so large config changes are deleted in chunks too
+    for chunk in full_paths.chunks(REJECTED_FILES_CHUNK_SIZE) {
+        if let Err(err) = diesel::delete(
+            rejected_file_table::table.filter(rejected_file_table::full_path.eq_any(chunk)),
+        )
+        .execute(connection)
+        {
+            error!(
+                "Error deleting {} rejected files. Err: {}",
+                chunk.len(),
+                err
+            );
+        }
+    }
+}
+
+///Deletes a FileVersion that has been removed from disk
+pub fn delete_file_version(connection: &PgConnection, file_version_id: i32) {
+    if let Err(err) =
+        diesel::delete(file_version_table::table.find(file_version_id)).execute(connection)
+    {
+        error!("Error deleting file_version. Err: {}", err);
+        panic!();
+    }
+}
+
+///Deletes a generic along with the episode, movie, extra and sidecar rows that belong to it,
+///extras of a deleted movie are kept but no longer linked to it
+pub fn delete_generic(connection: &PgConnection, generic_uid: i32) {
+    let result = connection.transaction::<_, diesel::result::Error, _>(|| {
+        diesel::update(extra_table::table.filter(extra_table::movie_generic_uid.eq(generic_uid)))
+            .set(extra_table::movie_generic_uid.eq(None::<i32>))
+            .execute(connection)?;
+        diesel::delete(sidecar_table::table.filter(sidecar_table::generic_uid.eq(generic_uid)))
+            .execute(connection)?;
+        diesel::delete(episode_table::table.filter(episode_table::generic_uid.eq(generic_uid)))
+            .execute(connection)?;
+        diesel::delete(movie_table::table.find(generic_uid)).execute(connection)?;
+        diesel::delete(extra_table::table.find(generic_uid)).execute(connection)?;
+        diesel::delete(
+            file_version_table::table.filter(file_version_table::generic_uid.eq(generic_uid)),
+        )
+        .execute(connection)?;
+        diesel::delete(generic_table::table.find(generic_uid)).execute(connection)?;
+        Ok(())
+    });
+    if let Err(err) = result {
+        error!("Error deleting generic. Err: {}", err);
+        panic!();
+    }
+}
+
+pub fn get_all_rejected_files() -> Vec<RejectedFileModel> {
+    rejected_file_data
+        .load::<RejectedFileModel>(&establish_connection())
+        .unwrap_or_else(|err| {
+            error!("Error loading rejected files. Err: {}", err);
+            panic!();
+        })
+}
+
+pub fn create_hash_run(connection: &PgConnection, new_hash_run: NewHashRun) -> HashRunModel {
+    diesel::insert_into(hash_run_table::table)
+        .values(&new_hash_run)
+        .get_result(connection)
+        .unwrap_or_else(|err| {
+            error!("Error saving new hash run. Err: {}", err);
+            panic!();
+        })
+}
+
+///The most recent hashing run that was stopped before it finished, if any
+pub fn get_unfinished_hash_run(connection: &PgConnection) -> Option<HashRunModel> {
+    hash_run_table::table
+        .filter(hash_run_table::finished.eq(false))
+        .order(hash_run_table::id.desc())
+        .first::<HashRunModel>(connection)
+        .optional()
+        .unwrap_or_else(|err| {
+            error!("Error loading hash runs. Err: {}", err);
+            panic!();
+        })
+}
+
+pub fn update_hash_run(
+    connection: &PgConnection,
+    id: i32,
+    hash_progress: &HashProgress,
+    finished: bool,
+) {
+    if let Err(err) = diesel::update(hash_run_table::table.find(id))
+        .set((
+            hash_run_table::total_files.eq(hash_progress.total_files as i32),
+            hash_run_table::total_bytes.eq(hash_progress.total_bytes as i64),
+            hash_run_table::hashed_files.eq(hash_progress.hashed_files as i32),
+            hash_run_table::hashed_bytes.eq(hash_progress.hashed_bytes as i64),
+            hash_run_table::finished.eq(finished),
+        ))
+        .execute(connection)
+    {
+        error!("Failed to update hash run. Err: {}", err);
+        panic!();
+    }
+}
+
 pub fn create_worker(conn: &PgConnection, new_worker: NewWorker) -> i32 {
     let worker: WorkerModel = diesel::insert_into(worker_table::table)
         .values(&new_worker)
@@ -125,13 +468,56 @@ pub fn get_all_file_versions() -> Vec<FileVersion> {
             panic!();
         });
 
+    let mut streams: HashMap<i32, Vec<Stream>> = HashMap::new();
+    for stream_model in get_all_streams(&connection) {
+        let file_version_id = stream_model.file_version_id;
+        if let Some(stream) = Stream::from_model(stream_model) {
+            streams.entry(file_version_id).or_default().push(stream);
+        }
+    }
+
     let mut file_versions: Vec<FileVersion> = Vec::new();
     for file_version_model in file_version_models {
-        file_versions.push(FileVersion::from_model(file_version_model));
+        let mut file_version = FileVersion::from_model(file_version_model);
+        file_version.streams = streams.remove(&file_version.id).unwrap_or_default();
+        file_versions.push(file_version);
     }
     file_versions
 }
 
+///Every stream ordered by its position in its file
+fn get_all_streams(connection: &PgConnection) -> Vec<StreamModel> {
+    stream_table::table
+        .order((stream_table::file_version_id, stream_table::stream_index))
+        .load::<StreamModel>(connection)
+        .unwrap_or_else(|err| {
+            error!("Error loading streams. Err: {}", err);
+            panic!();
+        })
+}
+
+///Replaces the stored streams of a file version with the ones it was just probed with
+pub fn replace_streams(connection: &PgConnection, file_version_id: i32, streams: &[Stream]) {
+    let new_streams: Vec<NewStream> = streams
+        .iter()
+        .map(|stream| NewStream::new(file_version_id, stream))
+        .collect();
+    let result = connection.transaction::<_, diesel::result::Error, _>(|| {
+        diesel::delete(
+            stream_table::table.filter(stream_table::file_version_id.eq(file_version_id)),
+        )
+        .execute(connection)?;
+        diesel::insert_into(stream_table::table)
+            .values(&new_streams)
+            .execute(connection)?;
+        Ok(())
+    });
+    if let Err(err) = result {
+        error!("Error saving streams. Err: {}", err);
+        panic!();
+    }
+}
+
 ///Get all generic from the database
 pub fn get_all_generics() -> Vec<Generic> {
     let connection = establish_connection();
@@ -179,7 +565,10 @@ pub fn get_all_shows() -> Vec<Show> {
 
     //these all contain the episode designation
     let generic_models = generic_data
-        .filter(designation.eq(Designation::Episode as i32))
+        .filter(designation.eq_any(vec![
+            Designation::Episode as i32,
+            Designation::Special as i32,
+        ]))
         .load::<GenericModel>(&connection)
         .unwrap_or_else(|err| {
             error!("Error loading generic. Err: {}", err);
@@ -197,23 +586,7 @@ pub fn get_all_shows() -> Vec<Show> {
             error!("Error loading episodes. Err: {}", err);
             panic!();
         });
-    let mut episodes: Vec<Episode> = Vec::new();
-
-    for episode_model in episode_models {
-        for generic in &generics {
-            if generic.get_generic_uid() == episode_model.generic_uid {
-                let episode = Episode::new(
-                    generic.clone(),
-                    episode_model.show_uid,
-                    "".to_string(),
-                    episode_model.season_number,
-                    vec![episode_model.episode_number],
-                ); //temporary first episode_number
-                episodes.push(episode);
-                break;
-            }
-        }
-    }
+    let episodes = Episode::from_episode_models(&generics, episode_models);
 
     let mut shows: Vec<Show> = Vec::new();
     for show in raw_shows {
@@ -230,3 +603,83 @@ pub fn get_all_shows() -> Vec<Show> {
     }
     shows
 }
+
+pub fn get_all_movies() -> Vec<Movie> {
+    let connection = establish_connection();
+
+    //these all contain the movie designation
+    let generic_models = generic_data
+        .filter(designation.eq(Designation::Movie as i32))
+        .load::<GenericModel>(&connection)
+        .unwrap_or_else(|err| {
+            error!("Error loading generic. Err: {}", err);
+            panic!();
+        });
+
+    let mut generics: HashMap<i32, Generic> = HashMap::new();
+    for generic_model in generic_models {
+        generics.insert(
+            generic_model.generic_uid,
+            Generic::from_generic_model(generic_model),
+        );
+    }
+
+    let movie_models = movie_db
+        .load::<MovieModel>(&connection)
+        .unwrap_or_else(|err| {
+            error!("Error loading movies. Err: {}", err);
+            panic!();
+        });
+
+    let mut movies: Vec<Movie> = Vec::new();
+    for movie_model in movie_models {
+        match generics.remove(&movie_model.generic_uid) {
+            Some(generic) => movies.push(Movie::from_movie_model(movie_model, generic)),
+            None => warn!(
+                "Movie \"{}\" has no generic with generic_uid: {}",
+                movie_model.movie_title, movie_model.generic_uid
+            ),
+        }
+    }
+    movies
+}
+
+pub fn get_all_extras() -> Vec<Extra> {
+    let connection = establish_connection();
+
+    //these all contain the extra or trailer designation
+    let generic_models = generic_data
+        .filter(designation.eq_any(vec![Designation::Extra as i32, Designation::Trailer as i32]))
+        .load::<GenericModel>(&connection)
+        .unwrap_or_else(|err| {
+            error!("Error loading generic. Err: {}", err);
+            panic!();
+        });
+
+    let mut generics: HashMap<i32, Generic> = HashMap::new();
+    for generic_model in generic_models {
+        generics.insert(
+            generic_model.generic_uid,
+            Generic::from_generic_model(generic_model),
+        );
+    }
+
+    let extra_models = extra_db
+        .load::<ExtraModel>(&connection)
+        .unwrap_or_else(|err| {
+            error!("Error loading extras. Err: {}", err);
+            panic!();
+        });
+
+    let mut extras: Vec<Extra> = Vec::new();
+    for extra_model in extra_models {
+        match generics.remove(&extra_model.generic_uid) {
+            Some(generic) => extras.push(Extra::from_extra_model(extra_model, generic)),
+            None => warn!(
+                "Extra has no generic with generic_uid: {}",
+                extra_model.generic_uid
+            ),
+        }
+    }
+    extras
+}
@@ -76,6 +76,8 @@ pub fn run_completeness_check(file_manager: Arc<Mutex<FileManager>>) {
         }
     }
     debug!("Episodes: {}", episodes_count);
+    debug!("Movies: {}", file_manager_lock.movies.len());
+    debug!("Extras: {}", file_manager_lock.extras.len());
 
     for generic in file_manager_lock.generic_files.iter() {
         for file_version in generic.file_versions.iter() {
@@ -91,6 +93,16 @@ pub fn run_completeness_check(file_manager: Arc<Mutex<FileManager>>) {
             }
         }
     }
+    for movie in file_manager_lock.movies.iter() {
+        for file_version in movie.generic.file_versions.iter() {
+            line_output(file_version);
+        }
+    }
+    for extra in file_manager_lock.extras.iter() {
+        for file_version in extra.generic.file_versions.iter() {
+            line_output(file_version);
+        }
+    }
     debug!("Finishing completeness check");
 }
 
@@ -104,8 +116,9 @@ pub fn output_tracked_paths(file_manager: Arc<Mutex<FileManager>>) {
         .get_root_directories()
     {
         debug!(
-            "Tracked directory: {}",
-            pathbuf_to_string(tracked_directory)
+            "Tracked directory: {}, library type: {:?}",
+            pathbuf_to_string(&tracked_directory.path),
+            tracked_directory.library_type
         );
     }
     debug!(
@@ -137,4 +150,4 @@ pub fn encode_all_files(
             .unwrap()
             .push_back(encode);
     }
-}
\ No newline at end of file
+}
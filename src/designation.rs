@@ -5,6 +5,18 @@ pub enum Designation {
     Generic = 1,
     Episode = 2,
     Movie = 3,
+    //Episodes in season 0, found in Specials or Season 0 folders
+    Special = 4,
+    //Featurettes, behind the scenes and other bonus content of a show or movie
+    Extra = 5,
+    Trailer = 6,
+}
+
+impl Designation {
+    ///Specials, extras and trailers are left out of bulk encodes unless the profile opts in
+    pub fn is_bonus_content(&self) -> bool {
+        matches!(self, Self::Special | Self::Extra | Self::Trailer)
+    }
 }
 
 pub fn from_i32(input: i32) -> Designation {
@@ -12,6 +24,9 @@ pub fn from_i32(input: i32) -> Designation {
         1 => Designation::Generic,
         2 => Designation::Episode,
         3 => Designation::Movie,
+        4 => Designation::Special,
+        5 => Designation::Extra,
+        6 => Designation::Trailer,
         _ => Designation::Generic,
     }
 }
@@ -0,0 +1,287 @@
+//!What each playback device can play without it being transcoded, so the library can be checked
+//!for files that won't direct play on a device and encoded for it. Chromecasts and browsers are
+//!built in and more devices can be defined in the config
+use {
+    crate::{
+        encode::EncodeProfile,
+        generic::FileVersion,
+        profile::{AudioCodec, Container, DynamicRange, VideoCodec},
+        stream::StreamType,
+    },
+    serde::{Deserialize, Serialize},
+    std::{collections::BTreeMap, fmt},
+};
+
+///The limits a device has for one video codec, limits that aren't set aren't checked
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VideoSupport {
+    pub codec: VideoCodec,
+    //Such as 4.1
+    pub max_level: Option<f64>,
+    pub max_bit_depth: Option<i32>,
+    pub max_width: Option<i32>,
+    pub max_height: Option<i32>,
+    pub max_framerate: Option<f64>,
+}
+
+impl VideoSupport {
+    fn new(codec: VideoCodec, max_level: Option<f64>, max_bit_depth: i32, max_width: i32) -> Self {
+        Self {
+            codec,
+            max_level,
+            max_bit_depth: Some(max_bit_depth),
+            max_width: Some(max_width),
+            max_height: Some(max_width * 9 / 16),
+            max_framerate: Some(60.0),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeviceProfile {
+    //Devices in the config replace built in devices with the same name
+    pub name: String,
+    pub containers: Vec<Container>,
+    pub video_codecs: Vec<VideoSupport>,
+    pub audio_codecs: Vec<AudioCodec>,
+    #[serde(default = "default_dynamic_ranges")]
+    pub dynamic_ranges: Vec<DynamicRange>,
+    //Used to make a version of a file the device can play
+    pub encode_profile: EncodeProfile,
+}
+
+fn default_dynamic_ranges() -> Vec<DynamicRange> {
+    vec![DynamicRange::SDR]
+}
+
+///Why a file won't direct play on a device
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum PlaybackIssue {
+    //Files have to be profiled before they can be checked
+    NotProfiled,
+    Container(Option<Container>),
+    VideoCodec(String),
+    VideoLevel(String),
+    BitDepth(i32),
+    //Width and height
+    Resolution(i32, i32),
+    Framerate(f64),
+    DynamicRange(DynamicRange),
+    AudioCodec(String),
+}
+
+impl fmt::Display for PlaybackIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotProfiled => write!(f, "The file hasn't been profiled"),
+            Self::Container(Some(container)) => write!(f, "Unsupported container: {}", container),
+            Self::Container(None) => write!(f, "Unknown container"),
+            Self::VideoCodec(codec) => write!(f, "Unsupported video codec: {}", codec),
+            Self::VideoLevel(level) => write!(f, "Video level {} is too high", level),
+            Self::BitDepth(bit_depth) => write!(f, "{} bit video is unsupported", bit_depth),
+            Self::Resolution(width, height) => {
+                write!(f, "{}x{} is above the maximum resolution", width, height)
+            }
+            Self::Framerate(framerate) => {
+                write!(f, "{:.3} fps is above the maximum frame rate", framerate)
+            }
+            Self::DynamicRange(dynamic_range) => write!(f, "{} is unsupported", dynamic_range),
+            Self::AudioCodec(codec) => write!(f, "Unsupported audio codec: {}", codec),
+        }
+    }
+}
+
+impl DeviceProfile {
+    ///Every reason the file won't direct play, empty if it will. Only the audio stream a player
+    ///picks by default is checked
+    pub fn check_direct_play(&self, file_version: &FileVersion) -> Vec<PlaybackIssue> {
+        let video_stream = match file_version.get_video_stream() {
+            Some(video_stream) if !file_version.profile_is_none() => video_stream,
+            _ => return vec![PlaybackIssue::NotProfiled],
+        };
+        let mut issues: Vec<PlaybackIssue> = Vec::new();
+        if file_version
+            .container
+            .map_or(true, |container| !self.containers.contains(&container))
+        {
+            issues.push(PlaybackIssue::Container(file_version.container));
+        }
+
+        match self
+            .video_codecs
+            .iter()
+            .find(|video_support| video_stream.get_video_codec() == Some(video_support.codec))
+        {
+            Some(video_support) => {
+                if let Some(level) = &video_stream.level {
+                    if video_support.max_level.is_some_and(|max_level| {
+                        level.parse::<f64>().is_ok_and(|level| level > max_level)
+                    }) {
+                        issues.push(PlaybackIssue::VideoLevel(level.clone()));
+                    }
+                }
+                if let Some(bit_depth) = video_stream.bit_depth {
+                    if video_support
+                        .max_bit_depth
+                        .is_some_and(|max_bit_depth| bit_depth > max_bit_depth)
+                    {
+                        issues.push(PlaybackIssue::BitDepth(bit_depth));
+                    }
+                }
+                if let (Some(width), Some(height)) = (file_version.width, file_version.height) {
+                    if video_support
+                        .max_width
+                        .is_some_and(|max_width| width > max_width)
+                        || video_support
+                            .max_height
+                            .is_some_and(|max_height| height > max_height)
+                    {
+                        issues.push(PlaybackIssue::Resolution(width, height));
+                    }
+                }
+                if let Some(framerate) = file_version.framerate {
+                    if video_support
+                        .max_framerate
+                        .is_some_and(|max_framerate| framerate > max_framerate)
+                    {
+                        issues.push(PlaybackIssue::Framerate(framerate));
+                    }
+                }
+            }
+            None => issues.push(PlaybackIssue::VideoCodec(video_stream.codec.clone())),
+        }
+
+        let dynamic_range = file_version.get_dynamic_range();
+        if !self.dynamic_ranges.contains(&dynamic_range) {
+            issues.push(PlaybackIssue::DynamicRange(dynamic_range));
+        }
+
+        let audio_streams: Vec<_> = file_version
+            .streams
+            .iter()
+            .filter(|stream| stream.stream_type == StreamType::Audio)
+            .collect();
+        if let Some(audio_stream) = audio_streams
+            .iter()
+            .find(|stream| stream.is_default)
+            .or_else(|| audio_streams.first())
+        {
+            if audio_stream.get_audio_codec().map_or(true, |audio_codec| {
+                !self.audio_codecs.contains(&audio_codec)
+            }) {
+                issues.push(PlaybackIssue::AudioCodec(audio_stream.codec.clone()));
+            }
+        }
+        issues
+    }
+
+    pub fn can_direct_play(&self, file_version: &FileVersion) -> bool {
+        self.check_direct_play(file_version).is_empty()
+    }
+}
+
+///Limits are from Google's list of Chromecast supported media
+pub fn get_builtin_devices() -> Vec<DeviceProfile> {
+    let containers = vec![Container::MP4, Container::MKV, Container::WEBM];
+    let audio_codecs = vec![
+        AudioCodec::FLAC,
+        AudioCodec::HEAAC,
+        AudioCodec::LCAAC,
+        AudioCodec::MP3,
+        AudioCodec::Opus,
+        AudioCodec::Vorbis,
+        AudioCodec::WAVLPCM,
+    ];
+    vec![
+        DeviceProfile {
+            name: "chromecast_gen2".to_string(),
+            containers: containers.clone(),
+            video_codecs: vec![
+                VideoSupport::new(VideoCodec::H264, Some(4.1), 8, 1920),
+                VideoSupport::new(VideoCodec::VP8, None, 8, 1920),
+            ],
+            audio_codecs: audio_codecs.clone(),
+            dynamic_ranges: default_dynamic_ranges(),
+            encode_profile: EncodeProfile::H264_TV_1080p,
+        },
+        DeviceProfile {
+            name: "chromecast_gen3".to_string(),
+            containers: containers.clone(),
+            video_codecs: vec![
+                VideoSupport::new(VideoCodec::H264, Some(4.2), 8, 1920),
+                VideoSupport::new(VideoCodec::VP8, None, 8, 1920),
+            ],
+            audio_codecs: audio_codecs.clone(),
+            dynamic_ranges: default_dynamic_ranges(),
+            encode_profile: EncodeProfile::H264_TV_1080p,
+        },
+        DeviceProfile {
+            name: "chromecast_ultra".to_string(),
+            containers: containers.clone(),
+            video_codecs: vec![
+                VideoSupport::new(VideoCodec::H264, Some(4.2), 8, 1920),
+                VideoSupport::new(VideoCodec::H265, Some(5.1), 10, 3840),
+                VideoSupport::new(VideoCodec::VP8, None, 8, 3840),
+                VideoSupport::new(VideoCodec::VP9, None, 10, 3840),
+            ],
+            audio_codecs: audio_codecs.clone(),
+            dynamic_ranges: vec![DynamicRange::SDR, DynamicRange::HDR10],
+            encode_profile: EncodeProfile::H265_TV_4K_HDR,
+        },
+        DeviceProfile {
+            name: "chromecast_google_tv".to_string(),
+            containers: containers.clone(),
+            video_codecs: vec![
+                VideoSupport::new(VideoCodec::H264, Some(5.1), 8, 3840),
+                VideoSupport::new(VideoCodec::H265, Some(5.1), 10, 3840),
+                VideoSupport::new(VideoCodec::VP9, None, 10, 3840),
+            ],
+            audio_codecs: audio_codecs.clone(),
+            dynamic_ranges: vec![DynamicRange::SDR, DynamicRange::HDR10, DynamicRange::HLG],
+            encode_profile: EncodeProfile::H265_TV_4K_HDR,
+        },
+        //What every major browser plays, HEVC and Matroska aren't supported by all of them
+        DeviceProfile {
+            name: "browser".to_string(),
+            containers: vec![Container::MP4, Container::WEBM],
+            video_codecs: vec![
+                VideoSupport::new(VideoCodec::H264, None, 8, 3840),
+                VideoSupport::new(VideoCodec::VP8, None, 8, 3840),
+                VideoSupport::new(VideoCodec::VP9, None, 8, 3840),
+            ],
+            audio_codecs: vec![
+                AudioCodec::FLAC,
+                AudioCodec::LCAAC,
+                AudioCodec::MP3,
+                AudioCodec::Opus,
+                AudioCodec::Vorbis,
+            ],
+            dynamic_ranges: default_dynamic_ranges(),
+            encode_profile: EncodeProfile::H264_TV_1080p,
+        },
+    ]
+}
+
+///Whether a FileVersion will direct play on each device, keyed by the device's name with the
+///reasons it won't, empty for devices it will direct play on
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DirectPlay {
+    pub generic_uid: i32,
+    pub id: i32,
+    pub file_name: String,
+    pub devices: BTreeMap<String, Vec<PlaybackIssue>>,
+}
+
+impl DirectPlay {
+    pub fn new(file_version: &FileVersion, devices: &[DeviceProfile]) -> Self {
+        Self {
+            generic_uid: file_version.generic_uid,
+            id: file_version.id,
+            file_name: file_version.get_file_name(),
+            devices: devices
+                .iter()
+                .map(|device| (device.name.clone(), device.check_direct_play(file_version)))
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//!Finds files that are stored more than once in the library, either as identical copies found by
+//!their hash or as the same episode or movie stored under different paths
+use {
+    crate::{
+        generic::{hash_with_version, FileVersion, Generic},
+        movie::Movie,
+        pathbuf_to_string,
+        show::Show,
+    },
+    serde::{Deserialize, Serialize},
+    std::{collections::BTreeMap, fs, path::PathBuf},
+    tracing::info,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DuplicateKind {
+    //Files with the same full hash
+    Identical,
+    //Different files for the same show, season and episodes
+    Episode,
+    //Different files for the same movie, usually at different resolutions
+    Movie,
+}
+
+///A copy of a file in a duplicate group with enough information to decide which one to keep
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateFileVersion {
+    pub generic_uid: i32,
+    pub id: i32,
+    pub full_path: PathBuf,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    //Bytes, 0 if the file couldn't be read
+    pub size: u64,
+    pub hash: Option<String>,
+    pub hash_version: i32,
+}
+
+impl DuplicateFileVersion {
+    pub fn from_file_version(file_version: &FileVersion) -> Self {
+        Self {
+            generic_uid: file_version.generic_uid,
+            id: file_version.id,
+            full_path: file_version.full_path.clone(),
+            width: file_version.width,
+            height: file_version.height,
+            size: fs::metadata(&file_version.full_path).map_or(0, |metadata| metadata.len()),
+            hash: file_version.hash.clone(),
+            hash_version: file_version.hash_version,
+        }
+    }
+
+    ///Whether the file on disk is still the one the report was made from, checked by its hash or
+    ///by its size if it hadn't been hashed
+    pub fn is_unchanged(&self) -> bool {
+        let size = match fs::metadata(&self.full_path) {
+            Ok(metadata) => metadata.len(),
+            Err(_) => return false,
+        };
+        match &self.hash {
+            //A file that can't be read is treated as changed
+            Some(hash) => hash_with_version(&self.full_path, self.hash_version)
+                .is_ok_and(|actual_hash| actual_hash == *hash),
+            None => size == self.size,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub group_id: usize,
+    pub kind: DuplicateKind,
+    pub file_versions: Vec<DuplicateFileVersion>,
+}
+
+impl DuplicateGroup {
+    pub fn contains(&self, generic_uid: i32, id: i32) -> bool {
+        self.file_versions
+            .iter()
+            .any(|file_version| file_version.generic_uid == generic_uid && file_version.id == id)
+    }
+}
+
+///Every group of duplicates found in the library, kept until the next FindDuplicates task so the
+///WebUI can request it and pick which copy to keep
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DuplicateReport {
+    pub groups: Vec<DuplicateGroup>,
+}
+
+impl DuplicateReport {
+    ///Groups identical files by their full hash, then files of the same episode and of the same
+    ///movie that belong to different generics. Missing files are left out
+    pub fn find(generics: &[&Generic], shows: &[Show], movies: &[Movie]) -> Self {
+        let mut report = Self::default();
+
+        //BTreeMaps keep the group order stable between runs
+        let mut by_hash: BTreeMap<&str, Vec<&FileVersion>> = BTreeMap::new();
+        for generic in generics {
+            for file_version in &generic.file_versions {
+                if let (Some(hash), false) = (&file_version.hash, file_version.missing) {
+                    by_hash.entry(hash).or_default().push(file_version);
+                }
+            }
+        }
+        for file_versions in by_hash.into_values() {
+            report.add_group(DuplicateKind::Identical, file_versions);
+        }
+
+        //Multi-episode files are only duplicates of files with exactly the same episodes
+        let mut by_episodes: BTreeMap<(i32, i32, &[i32]), Vec<&Generic>> = BTreeMap::new();
+        for show in shows {
+            for season in &show.seasons {
+                for episode in &season.episodes {
+                    let generics = by_episodes
+                        .entry((episode.show_uid, episode.show_season, &episode.show_episode))
+                        .or_default();
+                    if !generics.iter().any(|generic| {
+                        generic.get_generic_uid() == episode.generic.get_generic_uid()
+                    }) {
+                        generics.push(&episode.generic);
+                    }
+                }
+            }
+        }
+        for episode_generics in by_episodes.into_values() {
+            if episode_generics.len() > 1 {
+                report.add_generics_group(DuplicateKind::Episode, episode_generics);
+            }
+        }
+
+        let mut by_movie: BTreeMap<(&str, i32), Vec<&Generic>> = BTreeMap::new();
+        for movie in movies {
+            by_movie
+                .entry((&movie.movie_title, movie.movie_year))
+                .or_default()
+                .push(&movie.generic);
+        }
+        for movie_generics in by_movie.into_values() {
+            if movie_generics.len() > 1 {
+                report.add_generics_group(DuplicateKind::Movie, movie_generics);
+            }
+        }
+
+        report
+    }
+
+    ///Only the master file of each generic is added, its other FileVersions are encodes of it
+    ///rather than duplicates
+    fn add_generics_group(&mut self, kind: DuplicateKind, generics: Vec<&Generic>) {
+        let file_versions: Vec<&FileVersion> = generics
+            .into_iter()
+            .flat_map(|generic| generic.file_versions.iter())
+            .filter(|file_version| file_version.master_file && !file_version.missing)
+            .collect();
+        self.add_group(kind, file_versions);
+    }
+
+    ///Groups with less than 2 files aren't duplicates and are ignored
+    fn add_group(&mut self, kind: DuplicateKind, file_versions: Vec<&FileVersion>) {
+        if file_versions.len() < 2 {
+            return;
+        }
+        let group_id = self.groups.last().map_or(0, |group| group.group_id + 1);
+        self.groups.push(DuplicateGroup {
+            group_id,
+            kind,
+            file_versions: file_versions
+                .into_iter()
+                .map(DuplicateFileVersion::from_file_version)
+                .collect(),
+        });
+    }
+
+    pub fn get_group(&self, group_id: usize) -> Option<&DuplicateGroup> {
+        self.groups.iter().find(|group| group.group_id == group_id)
+    }
+
+    ///Takes (generic_uid, id) pairs of FileVersions that no longer exist out of every group,
+    ///dropping any group that no longer has duplicates
+    pub fn remove_file_versions(&mut self, removed: &[(i32, i32)]) {
+        for group in self.groups.iter_mut() {
+            group.file_versions.retain(|file_version| {
+                !removed.contains(&(file_version.generic_uid, file_version.id))
+            });
+        }
+        self.groups.retain(|group| group.file_versions.len() > 1);
+    }
+
+    pub fn print(&self) {
+        for group in &self.groups {
+            info!("Duplicate group {} ({:?}):", group.group_id, group.kind);
+            for file_version in &group.file_versions {
+                info!(
+                    "    [{}, {}] {}",
+                    file_version.generic_uid,
+                    file_version.id,
+                    pathbuf_to_string(&file_version.full_path)
+                );
+            }
+        }
+        info!("Found {} groups of duplicates", self.groups.len());
+    }
+}
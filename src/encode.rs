@@ -2,7 +2,8 @@ use {
     crate::{
         config::ServerConfig, copy, generic::FileVersion, get_file_name, get_file_stem,
         pathbuf_to_string, pathbuf_with_suffix,
-        profile::ResolutionStandard,
+        profile::{DynamicRange, ResolutionStandard, VideoCodec},
+        stream::Stream,
     },
     core::fmt,
     serde::{Deserialize, Serialize},
@@ -21,6 +22,7 @@ pub struct Encode {
     pub source_path: PathBuf,
     pub target_path: PathBuf,
     pub temp_target_path: PathBuf,
+    pub encode_profile: EncodeProfile,
     pub encode_string: EncodeString,
 }
 
@@ -48,6 +50,7 @@ impl Encode {
             source_path: file_version.full_path.clone(),
             target_path,
             temp_target_path,
+            encode_profile: encode_profile.clone(),
             encode_string: EncodeString::generate_deactivated(file_version, encode_profile),
         }
     }
@@ -68,9 +71,13 @@ impl Encode {
             panic!();
         }
 
-        if let Err(err) = remove_file(&self.encode_string.get_target_path()) {
-            error!("Failed to remove file from temp. IO output: {}", err);
-            panic!();
+        //ffmpeg may have failed before it created the output
+        let target_path = PathBuf::from(self.encode_string.get_target_path());
+        if target_path.exists() {
+            if let Err(err) = remove_file(&target_path) {
+                error!("Failed to remove file from temp. IO output: {}", err);
+                panic!();
+            }
         }
     }
 
@@ -134,34 +141,77 @@ impl EncodeString {
         file_version: &FileVersion,
         encode_profile: &EncodeProfile,
     ) -> Self {
+        let dynamic_range = file_version.get_dynamic_range();
+        let keep_hdr = dynamic_range.is_hdr() && encode_profile.keeps_hdr();
+
         let mut encode_string: Vec<String> = vec!["-i".to_string()]; //0
 
         //Get the index of the source path
         let source_index = encode_string.len();
         encode_string.push(String::new()); //1: to_string(&file_version.full_path)
 
-        //TODO: Add settings based on the profile used
         //Video
-        encode_string.push("-c:v".to_string()); //2
-        encode_string.push("libx265".to_string()); //3
-        encode_string.push("-crf".to_string()); //4
-        encode_string.push("25".to_string()); //5
-        encode_string.push("-preset".to_string()); //6
-        encode_string.push("slower".to_string()); //7
-        encode_string.push("-profile:v".to_string()); //8
-        encode_string.push("main".to_string()); //9
+        encode_string.push("-c:v".to_string());
+        match encode_profile.get_video_codec() {
+            //High profile at level 4.1 in 8-bit plays on every device that plays H.264
+            VideoCodec::H264 => {
+                encode_string.push("libx264".to_string());
+                encode_string.push("-crf".to_string());
+                encode_string.push("23".to_string());
+                encode_string.push("-preset".to_string());
+                encode_string.push("slower".to_string());
+                encode_string.push("-profile:v".to_string());
+                encode_string.push("high".to_string());
+                encode_string.push("-level:v".to_string());
+                encode_string.push("4.1".to_string());
+                encode_string.push("-pix_fmt".to_string());
+                encode_string.push("yuv420p".to_string());
+            }
+            _ => {
+                encode_string.push("libx265".to_string());
+                encode_string.push("-crf".to_string());
+                encode_string.push("25".to_string());
+                encode_string.push("-preset".to_string());
+                encode_string.push("slower".to_string());
+                encode_string.push("-profile:v".to_string());
+                encode_string.push(if keep_hdr { "main10" } else { "main" }.to_string());
+            }
+        }
 
         //Audio
-        encode_string.push("-c:a".to_string()); //10
-        encode_string.push("aac".to_string()); //11
-        encode_string.push("-q:a".to_string()); //12
-        encode_string.push("224k".to_string()); //13
+        encode_string.push("-c:a".to_string());
+        encode_string.push("aac".to_string());
+        encode_string.push("-q:a".to_string());
+        encode_string.push("224k".to_string());
+
+        //Colour
+        //HDR sources encoded without their colour data come out washed out, so they're either
+        //kept as HDR or tone mapped to SDR depending on the profile. SDR sources get nothing here
+        let mut video_filters: Vec<String> = Vec::new();
+        if let Some(video_stream) = file_version.get_video_stream() {
+            if keep_hdr {
+                encode_string.append(&mut get_keep_hdr_arguments(video_stream, dynamic_range));
+            } else if dynamic_range.is_hdr() {
+                video_filters.push(get_tone_map_filter(video_stream));
+                encode_string.append(&mut get_sdr_colour_arguments());
+            }
+        }
 
-        encode_string.push("-y".to_string()); //14
+        //Resolution
+        //Sources wider than the profile are scaled down to it, smaller ones are never scaled up
+        if let Some(max_width) = encode_profile.get_max_width() {
+            video_filters.push(format!("scale='min({},iw)':-2", max_width));
+        }
+        if !video_filters.is_empty() {
+            encode_string.push("-vf".to_string());
+            encode_string.push(video_filters.join(","));
+        }
+
+        encode_string.push("-y".to_string());
 
         //Get the index of the destination path
         let destination_index = encode_string.len();
-        encode_string.push(String::new()); //15
+        encode_string.push(String::new());
 
         Self {
             activated: false,
@@ -215,7 +265,82 @@ impl EncodeString {
     }
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+///Keeps the source's colour data and HDR10 metadata in 10-bit HEVC
+fn get_keep_hdr_arguments(video_stream: &Stream, dynamic_range: DynamicRange) -> Vec<String> {
+    let mut arguments: Vec<String> = vec!["-pix_fmt".to_string(), "yuv420p10le".to_string()];
+    let mut x265_params: Vec<String> = vec!["repeat-headers=1".to_string()];
+    for (flag, param, value) in [
+        (
+            "-color_primaries",
+            "colorprim",
+            &video_stream.colour_primaries,
+        ),
+        (
+            "-color_trc",
+            "transfer",
+            &video_stream.transfer_characteristics,
+        ),
+        (
+            "-colorspace",
+            "colormatrix",
+            &video_stream.matrix_coefficients,
+        ),
+    ] {
+        if let Some(value) = value {
+            arguments.push(flag.to_string());
+            arguments.push(value.clone());
+            x265_params.push(format!("{}={}", param, value));
+        }
+    }
+    //HLG needs no metadata, its transfer is enough for displays to show it as HDR
+    if dynamic_range == DynamicRange::HDR10 {
+        x265_params.push("hdr10=1".to_string());
+        x265_params.push("hdr10-opt=1".to_string());
+        if let Some(mastering_display) = &video_stream.mastering_display {
+            x265_params.push(format!("master-display={}", mastering_display));
+        }
+        if let Some(content_light_level) = &video_stream.content_light_level {
+            x265_params.push(format!("max-cll={}", content_light_level));
+        }
+    }
+    arguments.push("-x265-params".to_string());
+    arguments.push(x265_params.join(":"));
+    arguments
+}
+
+///Tone maps to BT.709 SDR with zscale and tonemap. zscale is told the source's colour data in case
+///the decoder doesn't pass it on
+fn get_tone_map_filter(video_stream: &Stream) -> String {
+    let mut input: String = String::new();
+    for (option, value) in [
+        ("tin", &video_stream.transfer_characteristics),
+        ("pin", &video_stream.colour_primaries),
+        ("min", &video_stream.matrix_coefficients),
+    ] {
+        if let Some(value) = value {
+            input.push_str(&format!("{}={}:", option, value));
+        }
+    }
+    format!(
+        "zscale={}t=linear:npl=100,format=gbrpf32le,zscale=p=bt709,\
+        tonemap=tonemap=hable:desat=0,zscale=t=bt709:m=bt709:r=tv,format=yuv420p",
+        input
+    )
+}
+
+///Tags the output of the tone map filter as BT.709
+fn get_sdr_colour_arguments() -> Vec<String> {
+    vec![
+        "-color_primaries".to_string(),
+        "bt709".to_string(),
+        "-color_trc".to_string(),
+        "bt709".to_string(),
+        "-colorspace".to_string(),
+        "bt709".to_string(),
+    ]
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 #[allow(non_camel_case_types)]
 pub enum EncodeProfile {
     H264_TV_1080p,
@@ -224,6 +349,10 @@ pub enum EncodeProfile {
     H265_TV_1080p,
     H265_TV_4K,
     H265_TV_720p,
+
+    //Keep HDR sources as HDR, every other profile tone maps them to SDR
+    H265_HDR,
+    H265_TV_4K_HDR,
 }
 
 impl EncodeProfile {
@@ -236,6 +365,31 @@ impl EncodeProfile {
             Self::H265_TV_1080p => "mp4".to_string(),
             Self::H265_TV_4K => "mp4".to_string(),
             Self::H265_TV_720p => "mp4".to_string(),
+
+            Self::H265_HDR => "mp4".to_string(),
+            Self::H265_TV_4K_HDR => "mp4".to_string(),
+        }
+    }
+
+    ///Whether HDR sources are kept as HDR rather than tone mapped to SDR
+    pub fn keeps_hdr(&self) -> bool {
+        matches!(self, Self::H265_HDR | Self::H265_TV_4K_HDR)
+    }
+
+    pub fn get_video_codec(&self) -> VideoCodec {
+        match self {
+            Self::H264_TV_1080p => VideoCodec::H264,
+            _ => VideoCodec::H265,
+        }
+    }
+
+    ///The width the TV profiles are scaled down to, none for profiles that keep the resolution
+    pub fn get_max_width(&self) -> Option<u32> {
+        match self {
+            Self::H264_TV_1080p | Self::H265_TV_1080p => Some(1920),
+            Self::H265_TV_4K | Self::H265_TV_4K_HDR => Some(3840),
+            Self::H265_TV_720p => Some(1280),
+            Self::H265 | Self::H265_HDR => None,
         }
     }
 
@@ -301,6 +455,13 @@ impl EncodeProfile {
                 encode_string.append(&mut h265_base());
                 encode_string.push(scale(ResolutionStandard::HD));
             },
+            Self::H265_HDR => {
+                encode_string.append(&mut h265_base());
+            }
+            Self::H265_TV_4K_HDR => {
+                encode_string.append(&mut h265_base());
+                encode_string.push(scale(ResolutionStandard::UHD));
+            }
             _ => {
                 error!("Until all profiles are implemented, you unfortunately have to go get fucked.");
                 panic!();
@@ -316,8 +477,10 @@ impl fmt::Display for EncodeProfile {
             Self::H264_TV_1080p => write!(f, "H264_TV_1080p"),
             Self::H265 =>          write!(f, "H265"),
             Self::H265_TV_1080p => write!(f, "H265_TV_1080p"),
-            Self::H265_TV_4K =>    write!(f, "H265_TV_4K"),
-            Self::H265_TV_720p =>  write!(f, "H265_TV_720p"),
+            Self::H265_TV_4K => write!(f, "H265_TV_4K"),
+            Self::H265_TV_720p => write!(f, "H265_TV_720p"),
+            Self::H265_HDR => write!(f, "H265_HDR"),
+            Self::H265_TV_4K_HDR => write!(f, "H265_TV_4K_HDR"),
         }
     }
 }
@@ -0,0 +1,30 @@
+//!Module containing the structure used to represent extras and trailers
+use crate::{generic::Generic, model::*};
+
+///Bonus content such as a featurette or trailer, along with the show or movie it belongs to.
+///The kind of extra is the designation of the generic
+#[derive(Clone, Debug)]
+pub struct Extra {
+    pub generic: Generic,
+    pub show_uid: Option<i32>,
+    pub movie_generic_uid: Option<i32>,
+}
+
+impl Extra {
+    pub fn new(generic: Generic, show_uid: Option<i32>, movie_generic_uid: Option<i32>) -> Self {
+        Extra {
+            generic,
+            show_uid,
+            movie_generic_uid,
+        }
+    }
+
+    ///Create Extra from ExtraModel generated by database and the generic it belongs to
+    pub fn from_extra_model(extra_model: ExtraModel, generic: Generic) -> Self {
+        Extra {
+            generic,
+            show_uid: extra_model.show_uid,
+            movie_generic_uid: extra_model.movie_generic_uid,
+        }
+    }
+}
@@ -3,48 +3,170 @@
 use {
     crate::{
         config::ServerConfig,
+        copy,
         database::*,
         designation::Designation,
+        device::DeviceProfile,
+        duplicate::{DuplicateFileVersion, DuplicateReport},
         encode::{Encode, EncodeProfile},
         ensure_path_exists,
-        generic::{FileVersion, Generic},
-        get_extension, get_file_stem, get_show_title_from_pathbuf,
-        model::{NewEpisode, NewFileVersion, NewGeneric},
+        extra::Extra,
+        generic::{fast_hash_with_version, hash_with_version, FileVersion, Generic},
+        get_extension, get_file_name, get_file_stem, get_show_title_from_pathbuf,
+        hashing::HashProgress,
+        ignore::{check_ignore_rules, get_ignore_file_directories, IgnoreRules, IGNORE_FILE_NAME},
+        import_plan::{ImportPlan, PlannedFile, PlannedImport, PlannedRejection},
+        model::{
+            NewEpisode, NewExtra, NewFileVersion, NewGeneric, NewMovie, NewRejectedFile, NewShow,
+            NewSidecar, RejectedFileModel,
+        },
+        movie::Movie,
+        nfo::{find_nfo_episode, read_episode_nfo, read_show_nfo},
+        parser::{
+            parse_episode, parse_extra, parse_movie, parse_show_title, EpisodeParseRule,
+            ParsedEpisode, ParsedMovie,
+        },
         pathbuf_to_string,
         show::{Episode, Show},
+        sidecar::{get_sidecar_language, get_sidecar_suffix, Sidecar, SidecarKind},
     },
     derivative::Derivative,
     diesel::pg::PgConnection,
-    jwalk::WalkDir,
-    lazy_static::lazy_static,
+    jwalk::WalkDirGeneric,
     rayon::prelude::*,
-    regex::Regex,
-    serde::{Deserialize, Serialize},
+    serde::{Deserialize, Deserializer, Serialize},
     std::{
+        borrow::Borrow,
+        collections::BTreeSet,
         collections::HashMap,
         collections::HashSet,
-        env, fmt,
+        env, fmt, fs,
         hash::{Hash, Hasher},
         path::Path,
         path::PathBuf,
-        sync::{Arc, RwLock},
+        sync::{atomic::AtomicBool, Arc, RwLock},
+        time::SystemTime,
     },
-    tracing::{error, info, trace, warn},
+    tracing::{debug, error, info, trace, warn},
 };
 
+///The kind of media kept in a root directory, this decides how new files in it are imported
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LibraryType {
+    //Files are episodes laid out as show/Season X/file
+    Tv,
+    //Files are movies named "Title (Year)"
+    Movies,
+    //Files are imported as they are, such as home videos
+    Generic,
+    //Each file is checked for being an episode and then a movie, used by
+    //root directories that are configured as only a path
+    #[default]
+    Mixed,
+}
+
+///A root directory containing media along with the settings for that library
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RootDirectory {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub library_type: LibraryType,
+    //Replaces the allowed extensions in the config for this root directory when set
+    #[serde(default)]
+    pub allowed_extensions: Option<Vec<String>>,
+    //Ignored on top of the ignored paths in the config, globs are relative to the root directory
+    #[serde(default)]
+    pub ignored_paths: Vec<String>,
+    #[serde(default)]
+    pub ignored_regexes: Vec<String>,
+    #[serde(skip)]
+    pub ignore_rules: IgnoreRules,
+    //Used when a file is encoded without asking for a specific profile
+    #[serde(default)]
+    pub encode_profile: Option<EncodeProfile>,
+    //Symlinked files and directories are followed unless this is turned off
+    #[serde(default = "default_follow_symlinks")]
+    pub follow_symlinks: bool,
+    //Skips directories mounted from another file system instead of crawling into them
+    #[serde(default)]
+    pub same_file_system: bool,
+    //Files reachable from more than one path, such as hardlinks, are only imported once
+    #[serde(default)]
+    pub deduplicate_hardlinks: bool,
+}
+
+impl RootDirectory {
+    pub fn new(path: PathBuf, library_type: LibraryType) -> Self {
+        Self {
+            path,
+            library_type,
+            allowed_extensions: None,
+            ignored_paths: Vec::new(),
+            ignored_regexes: Vec::new(),
+            ignore_rules: IgnoreRules::default(),
+            encode_profile: None,
+            follow_symlinks: default_follow_symlinks(),
+            same_file_system: false,
+            deduplicate_hardlinks: false,
+        }
+    }
+
+    ///Builds the ignore rules, root directories loaded from the config file need this before use
+    pub fn compile(&mut self) {
+        self.ignore_rules = IgnoreRules::new(&self.ignored_paths, &self.ignored_regexes);
+    }
+
+    ///The name of the first directory under the root directory, which for tv libraries is the show
+    pub fn get_show_title(&self, full_path: &Path) -> Option<String> {
+        let mut components = full_path.strip_prefix(&self.path).ok()?.components();
+        let show_directory = components.next()?;
+        //The file itself can't be the show directory
+        components.next()?;
+        Some(show_directory.as_os_str().to_string_lossy().to_string())
+    }
+}
+
+fn default_follow_symlinks() -> bool {
+    true
+}
+
+///Root directories can be written in the config as only a path or as a table with their settings
+fn deserialize_root_directories<'de, D>(deserializer: D) -> Result<Vec<RootDirectory>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum RootDirectoryEntry {
+        Path(PathBuf),
+        RootDirectory(RootDirectory),
+    }
+
+    Ok(Vec::<RootDirectoryEntry>::deserialize(deserializer)?
+        .into_iter()
+        .map(|entry| match entry {
+            RootDirectoryEntry::Path(path) => RootDirectory::new(path, LibraryType::Mixed),
+            RootDirectoryEntry::RootDirectory(root_directory) => root_directory,
+        })
+        .collect())
+}
+
 ///Struct to hold all root directories containing media
 #[derive(Default, Debug, Clone, Deserialize, Serialize)]
 pub struct TrackedDirectories {
-    root_directories: HashSet<PathBuf>,
     cache_directory: Option<PathBuf>,
     //This needs to be accessible by the workers
     global_temp_directory: Option<PathBuf>,
+    //Needs to be last so it can be written to toml as an array of tables
+    #[serde(deserialize_with = "deserialize_root_directories")]
+    root_directories: Vec<RootDirectory>,
 }
 
 impl TrackedDirectories {
     pub fn new_empty() -> Self {
         Self {
-            root_directories: HashSet::new(),
+            root_directories: Vec::new(),
             cache_directory: None,
             global_temp_directory: None,
         }
@@ -64,8 +186,14 @@ impl TrackedDirectories {
         self.global_temp_directory.is_some()
     }
 
-    pub fn add_root_directory(&mut self, tracked_directory: PathBuf) {
-        self.root_directories.insert(tracked_directory);
+    pub fn add_root_directory(&mut self, root_directory: RootDirectory) {
+        if !self
+            .root_directories
+            .iter()
+            .any(|existing| existing.path == root_directory.path)
+        {
+            self.root_directories.push(root_directory);
+        }
     }
 
     //Destructive
@@ -101,10 +229,61 @@ impl TrackedDirectories {
         }
     }
 
-    pub fn get_root_directories(&self) -> &HashSet<PathBuf> {
+    pub fn get_root_directories(&self) -> &Vec<RootDirectory> {
         &self.root_directories
     }
 
+    pub fn get_root_directories_mut(&mut self) -> &mut Vec<RootDirectory> {
+        &mut self.root_directories
+    }
+
+    ///Finds the root directory a file is in, the deepest one wins if root directories are nested
+    pub fn get_root_directory_for_path(&self, full_path: &Path) -> Option<&RootDirectory> {
+        self.root_directories
+            .iter()
+            .filter(|root_directory| full_path.starts_with(&root_directory.path))
+            .max_by_key(|root_directory| root_directory.path.components().count())
+    }
+
+    pub fn get_library_type(&self, full_path: &Path) -> LibraryType {
+        self.get_root_directory_for_path(full_path)
+            .map_or(LibraryType::Mixed, |root_directory| {
+                root_directory.library_type
+            })
+    }
+
+    ///Uses the show directory under the root directory, falling back to the show/Season X/file layout
+    ///for files that aren't in a directory of their own
+    pub fn get_show_title(&self, full_path: &Path) -> String {
+        match self
+            .get_root_directory_for_path(full_path)
+            .and_then(|root_directory| root_directory.get_show_title(full_path))
+        {
+            Some(show_title) => show_title,
+            None => get_show_title_from_pathbuf(full_path),
+        }
+    }
+
+    ///The folder get_show_title takes the show's title from, which is where tvshow.nfo is kept
+    pub fn get_show_directory(&self, full_path: &Path) -> Option<PathBuf> {
+        match self
+            .get_root_directory_for_path(full_path)
+            .and_then(|root_directory| {
+                let show_title = root_directory.get_show_title(full_path)?;
+                Some(root_directory.path.join(show_title))
+            }) {
+            Some(show_directory) => Some(show_directory),
+            None => full_path.ancestors().nth(2).map(Path::to_path_buf),
+        }
+    }
+
+    ///The root directory's encode profile, or the default profile if it doesn't have one
+    pub fn get_encode_profile(&self, full_path: &Path) -> EncodeProfile {
+        self.get_root_directory_for_path(full_path)
+            .and_then(|root_directory| root_directory.encode_profile.clone())
+            .unwrap_or(EncodeProfile::H264_TV_1080p)
+    }
+
     pub fn get_cache_directory(&self) -> &PathBuf {
         if self.cache_directory.is_some() {
             return self.cache_directory.as_ref().unwrap();
@@ -119,17 +298,79 @@ impl TrackedDirectories {
 ///This is used to create a log of files that weren't imported so
 ///that the user can determine the reason that some of their media
 ///was not imported if they expected it to be
-#[derive(Debug, Clone, Derivative)]
+#[derive(Debug, Clone, Copy, Derivative, Serialize, Deserialize)]
 #[derivative(PartialEq, Hash)]
-enum Reason {
-    PathContainsIgnoredPath,
-    ExtensionMissing,
-    ExtensionDisallowed,
+pub enum Reason {
+    PathContainsIgnoredPath = 1,
+    ExtensionMissing = 2,
+    ExtensionDisallowed = 3,
+    //A symlink in a root directory that doesn't follow symlinks
+    Symlink = 4,
+    //A symlink that points back to one of its own parent directories
+    SymlinkLoop = 5,
+    //A directory mounted from another file system than its root directory
+    MountBoundary = 6,
+    //The same file as another path, matched_rule is the path that is imported instead
+    Hardlink = 7,
+}
+
+impl Reason {
+    pub fn from_i32(input: i32) -> Option<Self> {
+        match input {
+            1 => Some(Self::PathContainsIgnoredPath),
+            2 => Some(Self::ExtensionMissing),
+            3 => Some(Self::ExtensionDisallowed),
+            4 => Some(Self::Symlink),
+            5 => Some(Self::SymlinkLoop),
+            6 => Some(Self::MountBoundary),
+            7 => Some(Self::Hardlink),
+            _ => None,
+        }
+    }
+
+    ///Reasons that are only found while crawling the root directories rather than from the path
+    pub fn is_from_crawl(&self) -> bool {
+        matches!(
+            self,
+            Self::Symlink | Self::SymlinkLoop | Self::MountBoundary | Self::Hardlink
+        )
+    }
+}
+
+///A rejected file along with the rule that caused it to be rejected, such as the
+///ignored path or the disallowed extension, and when it was first rejected for that rule
+#[derive(Debug, Clone)]
+pub struct PathBufReason {
+    pub pathbuf: PathBuf,
+    pub reason: Reason,
+    pub matched_rule: Option<String>,
+    pub rejected_at: SystemTime,
+}
+
+impl PathBufReason {
+    pub fn from_model(model: RejectedFileModel) -> Option<Self> {
+        match Reason::from_i32(model.reason) {
+            Some(reason) => Some(Self {
+                pathbuf: PathBuf::from(model.full_path),
+                reason,
+                matched_rule: model.matched_rule,
+                rejected_at: model.rejected_at,
+            }),
+            None => {
+                warn!(
+                    "Rejected file has an unknown reason: {}, path: {}",
+                    model.reason, model.full_path
+                );
+                None
+            }
+        }
+    }
 }
 
-struct PathBufReason {
-    pathbuf: PathBuf,
-    reason: Reason,
+impl Borrow<Path> for PathBufReason {
+    fn borrow(&self) -> &Path {
+        &self.pathbuf
+    }
 }
 
 impl PartialEq for PathBufReason {
@@ -152,12 +393,49 @@ impl fmt::Display for Reason {
             Self::PathContainsIgnoredPath => "PathContainsIgnoredPath",
             Self::ExtensionMissing => "ExtensionMissing",
             Self::ExtensionDisallowed => "ExtensionDisallowed",
+            Self::Symlink => "Symlink",
+            Self::SymlinkLoop => "SymlinkLoop",
+            Self::MountBoundary => "MountBoundary",
+            Self::Hardlink => "Hardlink",
         };
 
         write!(f, "{}", formatted)
     }
 }
 
+///Outcome of reconciling the tracked FileVersions against the files on disk
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct ReconciliationReport {
+    //(previous path, new path)
+    pub relinked: Vec<(PathBuf, PathBuf)>,
+    pub orphaned: Vec<PathBuf>,
+    pub added: Vec<PathBuf>,
+}
+
+impl ReconciliationReport {
+    pub fn print(&self) {
+        for (previous_path, new_path) in &self.relinked {
+            info!(
+                "Relinked: '{}' to '{}'",
+                pathbuf_to_string(previous_path),
+                pathbuf_to_string(new_path)
+            );
+        }
+        for path in &self.orphaned {
+            info!("Missing: '{}'", pathbuf_to_string(path));
+        }
+        for path in &self.added {
+            debug!("New file: '{}'", pathbuf_to_string(path));
+        }
+        info!(
+            "Reconciliation found {} relinked, {} missing and {} new files",
+            self.relinked.len(),
+            self.orphaned.len(),
+            self.added.len()
+        );
+    }
+}
+
 ///Contains all media data that is stored in ram as well as
 ///a list of rejected files
 pub struct FileManager {
@@ -167,9 +445,28 @@ pub struct FileManager {
     pub episode_files: HashMap<i32, Generic>,
 
     pub shows: Vec<Show>,
+    pub movies: Vec<Movie>,
+    //Specials are kept with the show's episodes in season 0
+    pub extras: Vec<Extra>,
     pub existing_files_hashset: HashSet<PathBuf>,
     pub new_files_queue: Vec<PathBuf>,
+    //Sidecars waiting to be attached to the generic they belong to
+    pub new_sidecars_queue: Vec<PathBuf>,
+    //Results of the last FindDuplicates task
+    pub duplicate_report: DuplicateReport,
+    //Results of the last PlanImport task
+    pub import_plan: ImportPlan,
+    //Progress of the current or last Hash task, kept behind its own lock so it can be read while
+    //files are being hashed
+    pub hash_progress: Arc<RwLock<HashProgress>>,
+    //Set while a Hash task is running so only one runs at a time
+    pub hashing: Arc<AtomicBool>,
+    //Set while a VerifyHashes task is running so scheduled runs don't overlap
+    pub verifying: Arc<AtomicBool>,
     rejected_files: HashSet<PathBufReason>,
+    //Rules from .tlmignore files by the directory they're in,
+    //none if the directory doesn't have one
+    ignore_files: HashMap<PathBuf, Option<IgnoreRules>>,
 }
 
 impl FileManager {
@@ -177,12 +474,23 @@ impl FileManager {
         let mut file_manager = Self {
             config,
             shows: get_all_shows(),
+            movies: get_all_movies(),
+            extras: get_all_extras(),
             generic_files: Vec::new(),
             episode_files: HashMap::new(),
             existing_files_hashset: HashSet::new(),
             new_files_queue: Vec::new(),
+            new_sidecars_queue: Vec::new(),
+            duplicate_report: DuplicateReport::default(),
+            import_plan: ImportPlan::default(),
+            hash_progress: Arc::new(RwLock::new(HashProgress::default())),
+            hashing: Arc::new(AtomicBool::new(false)),
+            verifying: Arc::new(AtomicBool::new(false)),
             rejected_files: HashSet::new(),
+            ignore_files: HashMap::new(),
         };
+
+        file_manager.load_rejected_files();
         
         //add generic_files and generics from their respective episodes to the existing_files_hashset
         file_manager.generic_files = get_all_generics();
@@ -232,9 +540,51 @@ impl FileManager {
                 generic.file_versions = file_versions.to_owned();
             }
         }
+        for show in file_manager.shows.iter_mut() {
+            for season in show.seasons.iter_mut() {
+                for episode in season.episodes.iter_mut() {
+                    let generic_uid = episode.generic.get_generic_uid();
+                    if let Some(file_versions) = collected_file_versions.get(&generic_uid) {
+                        episode.generic.file_versions = file_versions.to_owned();
+                    }
+                }
+            }
+        }
+        for movie in file_manager.movies.iter_mut() {
+            if let Some(file_versions) =
+                collected_file_versions.get(&movie.generic.get_generic_uid())
+            {
+                movie.generic.file_versions = file_versions.to_owned();
+            }
+        }
+        for extra in file_manager.extras.iter_mut() {
+            if let Some(file_versions) =
+                collected_file_versions.get(&extra.generic.get_generic_uid())
+            {
+                extra.generic.file_versions = file_versions.to_owned();
+            }
+        }
+
+        let mut collected_sidecars: HashMap<i32, Vec<Sidecar>> = HashMap::new();
+        for sidecar in get_all_sidecars() {
+            file_manager
+                .existing_files_hashset
+                .insert(sidecar.full_path.clone());
+            collected_sidecars
+                .entry(sidecar.generic_uid)
+                .or_default()
+                .push(sidecar);
+        }
+        for generic in file_manager.generics_mut() {
+            if let Some(sidecars) = collected_sidecars.remove(&generic.get_generic_uid()) {
+                generic.sidecars = sidecars;
+            }
+        }
 
         file_manager.add_existing_files_to_hashset();
         file_manager.add_show_episode_file_versions_to_hashset();
+        file_manager.add_movie_file_versions_to_hashset();
+        file_manager.add_extra_file_versions_to_hashset();
         file_manager
     }
 
@@ -256,6 +606,19 @@ impl FileManager {
                 return true;
             }
         }
+
+        for movie in self.movies.iter_mut() {
+            if movie.insert_file_version(file_version) {
+                return true;
+            }
+        }
+
+        for extra in self.extras.iter_mut() {
+            if extra.generic.get_generic_uid() == file_version.generic_uid {
+                extra.generic.file_versions.push(file_version.clone());
+                return true;
+            }
+        }
         false
     }
 
@@ -266,21 +629,15 @@ impl FileManager {
         encode_profile: &EncodeProfile,
         server_config: Arc<RwLock<ServerConfig>>,
     ) -> Option<Encode> {
-        for generic in &self.generic_files {
-            if generic.get_generic_uid() == generic_uid {
-                if let Some(file_version) = generic.get_file_version_by_id(file_version_id) {
-                    return Some(Encode::new(&file_version, encode_profile, &server_config));
-                }
-            }
-        }
-        for show in &self.shows {
-            if let Some(generic) = show.get_generic_from_uid(generic_uid) {
-                if let Some(file_version) = generic.get_file_version_by_id(file_version_id) {
-                    return Some(Encode::new(&file_version, encode_profile, &server_config));
-                }
-            }
+        let file_version = self
+            .generics()
+            .into_iter()
+            .find(|generic| generic.get_generic_uid() == generic_uid)
+            .and_then(|generic| generic.get_file_version_by_id(file_version_id))?;
+        if !is_encode_source(&file_version) {
+            return None;
         }
-        None
+        Some(Encode::new(&file_version, encode_profile, &server_config))
     }
 
     ///Takes all loaded episodes and adds their all their file_versions to the hashset
@@ -299,6 +656,24 @@ impl FileManager {
         }
     }
 
+    ///Adds the file_versions of all loaded movies to the hashset of existing files
+    fn add_movie_file_versions_to_hashset(&mut self) {
+        for movie in &self.movies {
+            for path in movie.generic.get_all_full_paths() {
+                self.existing_files_hashset.insert(path);
+            }
+        }
+    }
+
+    ///Adds the file_versions of all loaded extras and trailers to the hashset of existing files
+    fn add_extra_file_versions_to_hashset(&mut self) {
+        for extra in &self.extras {
+            for path in extra.generic.get_all_full_paths() {
+                self.existing_files_hashset.insert(path);
+            }
+        }
+    }
+
     ///Adds all generics that exist in the file manager to the hashset to ensure that
     ///files can't be imported twice
     fn add_existing_files_to_hashset(&mut self) {
@@ -321,51 +696,55 @@ impl FileManager {
     }
 
     pub fn print_number_of_episodes(&self) {
-        let mut episode_counter = 0;
-        for show in &self.shows {
-            for season in &show.seasons {
-                episode_counter += season.episodes.len();
-            }
-        }
+        let episode_counter: usize = self.shows.iter().map(|show| show.episode_count()).sum();
 
         info!("Number of episodes loaded in memory: {}", episode_counter);
     }
 
+    pub fn print_number_of_movies(&self) {
+        info!("Number of movies loaded in memory: {}", self.movies.len());
+    }
+
+    pub fn print_number_of_extras(&self) {
+        info!("Number of extras loaded in memory: {}", self.extras.len());
+    }
+
     ///Processes all files in the new files queue and converts them to
-    ///episodes and generics based on pattern matching. So far only accepts
-    ///The filename pattern SxxExx where Sxx is the season number and Exx
-    ///is the episode number
+    ///episodes, movies and generics based on the library type of their root directory.
+    ///Tv files are matched against the episode parse rules in the config, movie files are
+    ///checked for "Title (Year)" naming and mixed root directories try both in that order.
+    ///Files that don't match are imported as generics
     pub fn process_new_files(&mut self) {
         let connection = establish_connection();
         let mut new_episodes = Vec::new();
+        let mut new_movies = Vec::new();
+        let mut new_extras: Vec<(i32, PathBuf)> = Vec::new();
         let mut new_generics = Vec::new();
         let mut new_file_versions = Vec::new();
 
-        lazy_static! {
-            static ref REGEX: Regex = Regex::new(r"S[0-9]*E[0-9\-]*").unwrap();
-        }
+        let episode_parse_rules = self.config.read().unwrap().episode_parse_rules.clone();
+        let tracked_directories = self.config.read().unwrap().tracked_directories.clone();
 
         let mut generics: Vec<Generic> = Vec::new();
+        let mut parsed_files: Vec<ParsedFile> = Vec::new();
         //Indented so temp_generics_and_paths drops out of scope earlier
         {
             //Create Generic and NewGeneric that will be added to the database in a batch
-            let mut temp_generics_and_paths: Vec<(Generic, String)> = self
+            let mut temp_generics_and_paths: Vec<(Generic, String, ParsedFile)> = self
                 .new_files_queue
                 .par_iter()
                 .map(|current| {
                     let mut generic = Generic::default();
                     let master_file_path = pathbuf_to_string(current);
-                    //TODO: Why yes this is slower, no I don't care about 100ms right now
-                    match REGEX.find(&master_file_path) {
-                        None => {}
-                        Some(_) => generic.designation = Designation::Episode,
-                    }
+                    let parsed_file =
+                        parse_new_file(&tracked_directories, &episode_parse_rules, current);
+                    generic.designation = parsed_file.designation();
 
-                    (generic, master_file_path)
+                    (generic, master_file_path, parsed_file)
                 })
                 .collect();
             self.new_files_queue.clear();
-            for (generic, _) in &temp_generics_and_paths {
+            for (generic, _, _) in &temp_generics_and_paths {
                 new_generics.push(NewGeneric::new(generic.designation as i32));
             }
 
@@ -375,13 +754,14 @@ impl FileManager {
                 temp_generics_and_paths[i].0.generic_uid = Some(generic_models[i].generic_uid);
             }
 
-            for (generic, full_path) in temp_generics_and_paths {
+            for (generic, full_path, parsed_file) in temp_generics_and_paths {
                 new_file_versions.push(NewFileVersion::new(
                     generic.generic_uid.unwrap(),
                     full_path,
                     true,
                 ));
                 generics.push(generic);
+                parsed_files.push(parsed_file);
             }
         }
 
@@ -393,244 +773,1383 @@ impl FileManager {
             trace!("Processed {}", generic);
         }
 
-        //Build all the NewEpisodes so we can do a batch insert that is faster than doing one at a time in a loop
-        for generic in generics.iter_mut() {
-            let episode_string: String;
-            match REGEX.find(&generic.file_versions[0].get_file_name()) {
-                None => continue,
-                Some(val) => {
-                    //Removes first character
-                    let mut chars = val.as_str().chars();
-                    chars.next();
-
-                    episode_string = String::from(chars.as_str());
+        //Build all the NewEpisodes and NewMovies so we can do a batch insert that is faster than doing one at a time in a loop
+        let mut unmatched_files_count = 0;
+        for (generic, parsed_file) in generics.iter_mut().zip(parsed_files) {
+            let parsed_episode = match parsed_file {
+                ParsedFile::Episode(parsed_episode) | ParsedFile::Special(parsed_episode) => {
+                    parsed_episode
                 }
-            }
-
-            let mut season_episode_iter = episode_string.split('E');
-            let season_temp = season_episode_iter.next().unwrap().parse::<i32>().unwrap();
-            let mut episodes: Vec<i32> = Vec::new();
-            for episode in season_episode_iter.next().unwrap().split('-') {
-                episodes.push(episode.parse::<i32>().unwrap());
-            }
+                ParsedFile::Extra(_, owner_path) => {
+                    new_extras.push((generic.get_generic_uid(), owner_path));
+                    continue;
+                }
+                ParsedFile::Movie(parsed_movie) => {
+                    trace!(
+                        "Parsed {} as movie: {} ({})",
+                        generic.get_master_full_path(),
+                        parsed_movie.title,
+                        parsed_movie.year
+                    );
+                    new_movies.push(NewMovie::new(
+                        generic.get_generic_uid(),
+                        parsed_movie.title,
+                        parsed_movie.year,
+                    ));
+                    continue;
+                }
+                ParsedFile::Generic => continue,
+                ParsedFile::Unmatched => {
+                    debug!(
+                        "Didn't match the naming expected by its root directory, imported as generic: {}",
+                        generic.get_master_full_path()
+                    );
+                    unmatched_files_count += 1;
+                    continue;
+                }
+            };
+            trace!(
+                "Parsed {} with rule: {}",
+                generic.get_master_full_path(),
+                parsed_episode.rule_name
+            );
 
-            generic.designation = Designation::Episode;
-            let show_title = get_show_title_from_pathbuf(&generic.file_versions[0].full_path);
+            let full_path = &generic.file_versions[0].full_path;
+            let show_info = get_show_info(&tracked_directories, full_path);
+            let show_uid = self.ensure_show_exists(show_info, &connection);
 
-            let show_uid = self.ensure_show_exists(show_title.clone(), &connection);
-            let season_number = season_temp;
-            let episode_number = episodes[0];
+            //Metadata from the episode's nfo file overrides what was parsed from the file name
+            let nfo_episodes = read_episode_nfo(full_path);
 
-            let new_episode = NewEpisode::new(
-                generic.get_generic_uid(),
-                show_uid,
-                "".to_string(), //episode_title
-                season_number,
-                episode_number,
+            //Multi-episode files get a row for each episode, all sharing the one generic
+            for episode_number in parsed_episode.episodes {
+                let nfo_episode =
+                    find_nfo_episode(&nfo_episodes, parsed_episode.season, episode_number);
+                new_episodes.push(NewEpisode {
+                    generic_uid: generic.get_generic_uid(),
+                    show_uid,
+                    episode_title: nfo_episode
+                        .and_then(|nfo_episode| nfo_episode.title.clone())
+                        .or_else(|| parsed_episode.title.clone())
+                        .unwrap_or_default(),
+                    season_number: parsed_episode.season,
+                    episode_number,
+                    release_source: parsed_episode.release_source.clone(),
+                    air_date: nfo_episode
+                        .and_then(|nfo_episode| nfo_episode.aired.clone())
+                        .or_else(|| parsed_episode.date.clone()),
+                    plot: nfo_episode.and_then(|nfo_episode| nfo_episode.plot.clone()),
+                });
+            }
+        }
+        if unmatched_files_count > 0 {
+            info!(
+                "{} new files didn't match the naming expected by their root directory and were imported as generics",
+                unmatched_files_count
             );
-            new_episodes.push(new_episode);
         }
 
         let mut temp_generics_only_episodes: Vec<Generic> = Vec::new();
         let mut temp_generics_only_generics: Vec<Generic> = Vec::new();
+        let mut temp_generics_only_movies: HashMap<i32, Generic> = HashMap::new();
+        let mut temp_generics_only_extras: HashMap<i32, Generic> = HashMap::new();
         for generic in &generics {
             match generic.designation {
                 Designation::Generic => temp_generics_only_generics.push(generic.clone()),
-                Designation::Episode => temp_generics_only_episodes.push(generic.clone()),
-                _ => {}
+                Designation::Episode | Designation::Special => {
+                    temp_generics_only_episodes.push(generic.clone())
+                }
+                Designation::Movie => {
+                    temp_generics_only_movies.insert(generic.get_generic_uid(), generic.clone());
+                }
+                Designation::Extra | Designation::Trailer => {
+                    temp_generics_only_extras.insert(generic.get_generic_uid(), generic.clone());
+                }
             }
         }
 
         self.generic_files.append(&mut temp_generics_only_generics);
 
-        let mut episodes: Vec<Episode> = Vec::new();
-        for episode_model in create_episodes(&connection, new_episodes) {
-            for generic in &temp_generics_only_episodes {
-                if generic.get_generic_uid() == episode_model.generic_uid {
-                    let episode = Episode::new(
-                        generic.clone(),
-                        episode_model.show_uid,
-                        "".to_string(),
-                        episode_model.season_number,
-                        vec![episode_model.episode_number],
-                    );
-                    episodes.push(episode);
-                    break;
+        if !new_movies.is_empty() {
+            for movie_model in create_movies(&connection, new_movies) {
+                if let Some(generic) = temp_generics_only_movies.remove(&movie_model.generic_uid) {
+                    self.movies
+                        .push(Movie::from_movie_model(movie_model, generic));
                 }
             }
         }
 
-        self.insert_episodes(episodes);
-    }
+        let episodes = Episode::from_episode_models(
+            &temp_generics_only_episodes,
+            create_episodes(&connection, new_episodes),
+        );
 
-    pub fn generate_profiles(&mut self) {
-        let connection = &establish_connection();
-        for generic in self.generic_files.iter_mut() {
-            generic.generate_file_version_profiles_if_none(connection);
-        }
+        self.insert_episodes(episodes);
 
-        for show in self.shows.iter_mut() {
-            for season in show.seasons.iter_mut() {
-                for episode in season.episodes.iter_mut() {
-                    episode
-                        .generic
-                        .generate_file_version_profiles_if_none(connection);
+        //Extras are linked last so they can belong to shows and movies from this batch
+        if !new_extras.is_empty() {
+            let new_extras: Vec<NewExtra> = new_extras
+                .into_iter()
+                .map(|(generic_uid, owner_path)| {
+                    let (show_uid, movie_generic_uid) =
+                        self.find_extra_owner(&tracked_directories, &owner_path, &connection);
+                    if show_uid.is_none() && movie_generic_uid.is_none() {
+                        debug!(
+                            "Couldn't find the show or movie for extra with generic_uid: {}",
+                            generic_uid
+                        );
+                    }
+                    NewExtra::new(generic_uid, show_uid, movie_generic_uid)
+                })
+                .collect();
+            for extra_model in create_extras(&connection, new_extras) {
+                if let Some(generic) = temp_generics_only_extras.remove(&extra_model.generic_uid) {
+                    self.extras
+                        .push(Extra::from_extra_model(extra_model, generic));
                 }
             }
         }
+
+        self.attach_new_sidecars(&connection);
     }
 
-    ///returns none when a file is rejected because is accepted, or already exists in the existing_files_hashset
-    fn accept_or_reject_file(&mut self, full_path: PathBuf, store_reasons: bool) {
-        let mut reason = None;
-        //rejects if the path contains any element of an ignored path
-        for ignored_path in &self.config.read().unwrap().ignored_paths_regex {
-            if ignored_path
-                .is_match(&pathbuf_to_string(&full_path))
-                .unwrap()
-            {
-                reason = Some(Reason::PathContainsIgnoredPath);
-            }
-        }
+    ///Copies what a dry run of importing needs, so the crawl can be done by the ImportPlanner
+    ///without holding the FileManager's lock
+    pub fn get_import_planner(&self) -> ImportPlanner {
+        ImportPlanner::new(
+            self.config.read().unwrap().clone(),
+            self.existing_files_hashset.clone(),
+            self.new_files_queue.clone(),
+            self.shows
+                .iter()
+                .map(|show| (show.show_title.clone(), show.show_year))
+                .collect(),
+            self.movies
+                .iter()
+                .map(|movie| (movie.movie_title.clone(), movie.movie_year))
+                .collect(),
+        )
+    }
 
-        //rejects if the path doesn't have an extension
-        if reason.is_none() {
-            if full_path.extension().is_none() {
-                reason = Some(Reason::ExtensionMissing);
-            } else {
-                //rejects if the file doesn't have an allowed extension
-                if !self
-                    .config
-                    .read()
-                    .unwrap()
-                    .allowed_extensions
-                    .contains(&get_extension(&full_path).to_lowercase())
-                {
-                    reason = Some(Reason::ExtensionDisallowed);
+    ///Finds the (show_uid, movie generic_uid) an extra belongs to from the path it would have
+    ///without its extras folder or suffix. Tv libraries create the show if it doesn't exist yet
+    fn find_extra_owner(
+        &mut self,
+        tracked_directories: &TrackedDirectories,
+        owner_path: &Path,
+        connection: &PgConnection,
+    ) -> (Option<i32>, Option<i32>) {
+        let library_type = tracked_directories.get_library_type(owner_path);
+        if library_type != LibraryType::Tv {
+            if let Some(parsed_movie) = parse_movie(owner_path) {
+                if let Some(movie) = self.movies.iter().find(|movie| {
+                    movie.movie_title == parsed_movie.title && movie.movie_year == parsed_movie.year
+                }) {
+                    return (None, Some(movie.generic.get_generic_uid()));
                 }
             }
         }
 
-        if let Some(reason) = reason {
-            if store_reasons {
-                trace!("Rejected {} for {}", pathbuf_to_string(&full_path), reason);
-                self.rejected_files.insert(PathBufReason {
-                    pathbuf: full_path,
-                    reason,
-                });
+        match library_type {
+            LibraryType::Tv => {
+                let show_info = get_show_info(tracked_directories, owner_path);
+                (Some(self.ensure_show_exists(show_info, connection)), None)
             }
-        } else if self.existing_files_hashset.insert(full_path.clone()) {
-            self.new_files_queue.push(full_path);
+            LibraryType::Mixed => match tracked_directories
+                .get_root_directory_for_path(owner_path)
+                .and_then(|root_directory| root_directory.get_show_title(owner_path))
+            {
+                Some(_) => {
+                    let show_info = get_show_info(tracked_directories, owner_path);
+                    (self.show_exists(&show_info.title, show_info.year), None)
+                }
+                None => (None, None),
+            },
+            _ => (None, None),
         }
     }
 
-    ///Import all files in the list of tracked root directories
-    ///into a queue for later processing. Uses a Hash set to
-    ///guarantee no duplicates in O(1) time
-    pub fn import_files(&mut self) {
-        //import all files in tracked root directories
-        let root_directories = &self
-            .config
-            .read()
-            .unwrap()
-            .clone()
-            .tracked_directories
-            .root_directories;
-        for directory in root_directories {
-            //If we do thi first we can max out IO without waiting
-            //for accept_or_reject files. Will increase memory overhead obviously
-            for entry in WalkDir::new(directory) {
-                if entry.as_ref().unwrap().path().is_file() {
-                    self.accept_or_reject_file(entry.unwrap().path(), true);
-                }
-            }
+    ///Attaches queued sidecars to the generic of the video they are named after, sidecars that aren't
+    ///named after a video, such as folder.jpg, go to the only video in their directory if there is one.
+    ///Sidecars without an owner stay queued in case their video hasn't been processed yet
+    fn attach_new_sidecars(&mut self, connection: &PgConnection) {
+        if self.new_sidecars_queue.is_empty() {
+            return;
         }
-    }
 
-    pub fn generate_encodes_for_all(&self, encode_profile: &EncodeProfile) -> Vec<Encode> {
-        let mut encodes: Vec<Encode> = Vec::new();
-        for generic in &self.generic_files {
+        //(video file stem, generic_uid) of every video in each directory
+        let mut videos_by_directory: HashMap<PathBuf, Vec<(String, i32)>> = HashMap::new();
+        for generic in self.generics() {
             for file_version in &generic.file_versions {
-                encodes.push(Encode::new(file_version, encode_profile, &self.config));
+                if let Some(directory) = file_version.full_path.parent() {
+                    videos_by_directory
+                        .entry(directory.to_path_buf())
+                        .or_default()
+                        .push((
+                            get_file_stem(&file_version.full_path),
+                            file_version.generic_uid,
+                        ));
+                }
             }
         }
-        for show in &self.shows {
-            for season in &show.seasons {
-                for episode in &season.episodes {
-                    for file_version in &episode.generic.file_versions {
-                        encodes.push(Encode::new(file_version, encode_profile, &self.config));
+
+        let mut new_sidecars: Vec<NewSidecar> = Vec::new();
+        let mut unattached_sidecars: Vec<PathBuf> = Vec::new();
+        for full_path in std::mem::take(&mut self.new_sidecars_queue) {
+            let kind = match SidecarKind::from_path(&full_path) {
+                Some(kind) => kind,
+                None => continue,
+            };
+            let videos = match full_path
+                .parent()
+                .and_then(|directory| videos_by_directory.get(directory))
+            {
+                Some(videos) => videos,
+                None => {
+                    unattached_sidecars.push(full_path);
+                    continue;
+                }
+            };
+
+            let sidecar_file_name = get_file_name(&full_path);
+            //The longest matching name wins so "Show - S01E01" doesn't take "Show - S01E01 - Part 2.srt"
+            let named_owner = videos
+                .iter()
+                .filter_map(|(video_file_stem, generic_uid)| {
+                    get_sidecar_suffix(video_file_stem, &sidecar_file_name)
+                        .map(|suffix| (video_file_stem.len(), *generic_uid, suffix))
+                })
+                .max_by_key(|(video_file_stem_length, _, _)| *video_file_stem_length);
+            let owner = match named_owner {
+                Some((_, generic_uid, suffix)) => Some((generic_uid, get_sidecar_language(suffix))),
+                None => {
+                    let generic_uids: HashSet<i32> =
+                        videos.iter().map(|(_, generic_uid)| *generic_uid).collect();
+                    if generic_uids.len() == 1 {
+                        generic_uids
+                            .into_iter()
+                            .next()
+                            .map(|generic_uid| (generic_uid, None))
+                    } else {
+                        None
                     }
                 }
+            };
+
+            match owner {
+                Some((generic_uid, language)) => {
+                    new_sidecars.push(NewSidecar::new(generic_uid, &full_path, kind, language))
+                }
+                None => unattached_sidecars.push(full_path),
             }
         }
-        encodes
+        if !unattached_sidecars.is_empty() {
+            debug!(
+                "{} sidecars couldn't be matched to a video yet",
+                unattached_sidecars.len()
+            );
+        }
+        self.new_sidecars_queue = unattached_sidecars;
+
+        if new_sidecars.is_empty() {
+            return;
+        }
+        let sidecars: Vec<Sidecar> = create_sidecars(connection, new_sidecars)
+            .into_iter()
+            .filter_map(Sidecar::from_model)
+            .collect();
+        info!("Attached {} sidecars", sidecars.len());
+        self.insert_sidecars(sidecars);
     }
 
-    pub fn generate_encode_for_file(&self, encode_profile: &EncodeProfile, generic_uid: i32, id: i32) -> Option<Encode> {
-        for generic in &self.generic_files {
-            if generic.get_generic_uid() == generic_uid {
-                for file_version in &generic.file_versions {
-                    if file_version.id == id {
-                        return Some(Encode::new(file_version, encode_profile, &self.config));
+    fn insert_sidecars(&mut self, sidecars: Vec<Sidecar>) {
+        let mut sidecars_by_generic: HashMap<i32, Vec<Sidecar>> = HashMap::new();
+        for sidecar in sidecars {
+            sidecars_by_generic
+                .entry(sidecar.generic_uid)
+                .or_default()
+                .push(sidecar);
+        }
+        for generic in self.generics_mut() {
+            if let Some(mut sidecars) = sidecars_by_generic.remove(&generic.get_generic_uid()) {
+                generic.sidecars.append(&mut sidecars);
+            }
+        }
+    }
+
+    ///Gives a newly encoded file its own copy of the sidecars named after the file it was encoded from.
+    ///If the source file is gone, the sidecars are renamed instead
+    pub fn copy_sidecars_for_encode(
+        &mut self,
+        generic_uid: i32,
+        source_path: &Path,
+        target_path: &Path,
+    ) {
+        let sidecars: Vec<Sidecar> = match self
+            .generics()
+            .into_iter()
+            .find(|generic| generic.get_generic_uid() == generic_uid)
+        {
+            Some(generic) => generic.sidecars.clone(),
+            None => return,
+        };
+
+        let connection = establish_connection();
+        let rename = !source_path.exists();
+        let mut new_sidecars: Vec<NewSidecar> = Vec::new();
+        for sidecar in sidecars {
+            let new_path = match sidecar.get_path_for_video(source_path, target_path) {
+                Some(new_path) => new_path,
+                None => continue,
+            };
+            if rename {
+                if let Err(err) = fs::rename(&sidecar.full_path, &new_path) {
+                    warn!(
+                        "Failed to rename sidecar {}. Err: {}",
+                        pathbuf_to_string(&sidecar.full_path),
+                        err
+                    );
+                    continue;
+                }
+                update_sidecar_path(&connection, sidecar.id, &new_path);
+                self.existing_files_hashset.remove(&sidecar.full_path);
+                for generic in self.generics_mut() {
+                    for existing_sidecar in generic.sidecars.iter_mut() {
+                        if existing_sidecar.id == sidecar.id {
+                            existing_sidecar.full_path = new_path.clone();
+                        }
                     }
                 }
+            } else {
+                if let Err(err) = copy(&sidecar.full_path, &new_path) {
+                    warn!(
+                        "Failed to copy sidecar {}. Err: {}",
+                        pathbuf_to_string(&sidecar.full_path),
+                        err
+                    );
+                    continue;
+                }
+                new_sidecars.push(NewSidecar::new(
+                    generic_uid,
+                    &new_path,
+                    sidecar.kind,
+                    sidecar.language.clone(),
+                ));
             }
+            self.existing_files_hashset.insert(new_path);
+        }
+
+        if !new_sidecars.is_empty() {
+            let sidecars = create_sidecars(&connection, new_sidecars)
+                .into_iter()
+                .filter_map(Sidecar::from_model)
+                .collect();
+            self.insert_sidecars(sidecars);
         }
+    }
+
+    ///Every generic stored in ram, whether it is a plain generic or belongs to an episode, a movie or an extra
+    pub fn generics(&self) -> Vec<&Generic> {
+        let mut generics: Vec<&Generic> = self.generic_files.iter().collect();
         for show in &self.shows {
             for season in &show.seasons {
-                for episode in &season.episodes {
-                    if episode.generic.get_generic_uid() == generic_uid {
-                        for file_version in &episode.generic.file_versions {
-                            return Some(Encode::new(file_version, encode_profile, &self.config));
-                        }
-                    }
-                }
+                generics.extend(season.episodes.iter().map(|episode| &episode.generic));
             }
         }
-        None
+        generics.extend(self.movies.iter().map(|movie| &movie.generic));
+        generics.extend(self.extras.iter().map(|extra| &extra.generic));
+        generics
     }
 
-    ///Insert a vector of episodes into an existing show
-    pub fn insert_episodes(&mut self, episodes: Vec<Episode>) {
-        //find the associated show
-        //insert episode into that show
-        for episode in episodes {
-            let show_uid = episode.show_uid;
-            for show in &mut self.shows {
-                if show.show_uid == show_uid {
-                    show.insert_episode(episode);
-                    break;
-                }
+    ///Copies the hashes of FileVersions hashed in the background onto the ones in memory,
+    ///FileVersions that have since been removed are ignored
+    pub fn update_hashes(&mut self, hashed_file_versions: &[FileVersion]) {
+        let mut by_generic: HashMap<i32, Vec<FileVersion>> = HashMap::new();
+        for file_version in hashed_file_versions {
+            by_generic
+                .entry(file_version.generic_uid)
+                .or_default()
+                .push(file_version.clone());
+        }
+        for generic in self.generics_mut() {
+            if let Some(file_versions) = by_generic.get(&generic.get_generic_uid()) {
+                generic.update_hashes_from_file_versions(file_versions);
             }
         }
     }
 
-    ///Check if a show exists in ram
-    fn show_exists(&self, show_title: String) -> Option<i32> {
-        for show in &self.shows {
-            if show.show_title == show_title {
-                return Some(show.show_uid);
+    ///Copies the result of verifying a FileVersion's hash onto the one in memory
+    pub fn update_verification(&mut self, verified_file_version: &FileVersion) {
+        for file_version in self.file_versions_mut() {
+            if file_version.id == verified_file_version.id {
+                file_version.corrupted = verified_file_version.corrupted;
+                file_version.verified_at = verified_file_version.verified_at;
             }
         }
-        None
     }
 
-    ///Make sure a show exists by checking for it in ram and inserting it into
-    ///the database if it doesn't exist yet
-    fn ensure_show_exists(&mut self, show_title: String, connection: &PgConnection) -> i32 {
-        let show_uid = self.show_exists(show_title.clone());
+    pub fn generics_mut(&mut self) -> Vec<&mut Generic> {
+        let mut generics: Vec<&mut Generic> = self.generic_files.iter_mut().collect();
+        for show in self.shows.iter_mut() {
+            for season in show.seasons.iter_mut() {
+                generics.extend(
+                    season
+                        .episodes
+                        .iter_mut()
+                        .map(|episode| &mut episode.generic),
+                );
+            }
+        }
+        generics.extend(self.movies.iter_mut().map(|movie| &mut movie.generic));
+        generics.extend(self.extras.iter_mut().map(|extra| &mut extra.generic));
+        generics
+    }
+
+    pub fn generate_profiles(&mut self) {
+        let connection = &establish_connection();
+        let prober = self.config.read().unwrap().media_prober.get_prober();
+        let prober = prober.as_ref();
+        for generic in self.generic_files.iter_mut() {
+            generic.generate_file_version_profiles_if_none(connection, prober);
+        }
+
+        for show in self.shows.iter_mut() {
+            for season in show.seasons.iter_mut() {
+                for episode in season.episodes.iter_mut() {
+                    episode
+                        .generic
+                        .generate_file_version_profiles_if_none(connection, prober);
+                }
+            }
+        }
+
+        for movie in self.movies.iter_mut() {
+            movie
+                .generic
+                .generate_file_version_profiles_if_none(connection, prober);
+        }
+
+        for extra in self.extras.iter_mut() {
+            extra
+                .generic
+                .generate_file_version_profiles_if_none(connection, prober);
+        }
+    }
+
+    ///Only generates profiles for FileVersions at the given paths, used so a handful of
+    ///newly imported files don't require a pass over the whole library
+    pub fn generate_profiles_for_paths(&mut self, paths: &HashSet<PathBuf>) {
+        let connection = &establish_connection();
+        let prober = self.config.read().unwrap().media_prober.get_prober();
+        let prober = prober.as_ref();
+        for generic in self.generic_files.iter_mut() {
+            for file_version in generic.file_versions.iter_mut() {
+                if paths.contains(&file_version.full_path) {
+                    file_version.generate_profile_if_none(connection, prober);
+                }
+            }
+        }
+
+        for show in self.shows.iter_mut() {
+            for season in show.seasons.iter_mut() {
+                for episode in season.episodes.iter_mut() {
+                    for file_version in episode.generic.file_versions.iter_mut() {
+                        if paths.contains(&file_version.full_path) {
+                            file_version.generate_profile_if_none(connection, prober);
+                        }
+                    }
+                }
+            }
+        }
+
+        for movie in self.movies.iter_mut() {
+            for file_version in movie.generic.file_versions.iter_mut() {
+                if paths.contains(&file_version.full_path) {
+                    file_version.generate_profile_if_none(connection, prober);
+                }
+            }
+        }
+
+        for extra in self.extras.iter_mut() {
+            for file_version in extra.generic.file_versions.iter_mut() {
+                if paths.contains(&file_version.full_path) {
+                    file_version.generate_profile_if_none(connection, prober);
+                }
+            }
+        }
+    }
+
+    fn check_file(&self, full_path: &Path) -> Option<(Reason, Option<String>)> {
+        check_file(&self.config.read().unwrap(), &self.ignore_files, full_path)
+    }
+
+    ///returns none when a file is rejected because is accepted, or already exists in the existing_files_hashset
+    fn accept_or_reject_file(&mut self, full_path: PathBuf, store_reasons: bool) {
+        self.load_ignore_files(&full_path);
+        if let Some((reason, matched_rule)) = self.check_file(&full_path) {
+            //Sidecars aren't videos but are kept to be attached to the video they belong to
+            if reason == Reason::ExtensionDisallowed && SidecarKind::from_path(&full_path).is_some()
+            {
+                self.rejected_files.remove(full_path.as_path());
+                if self.existing_files_hashset.insert(full_path.clone()) {
+                    self.new_sidecars_queue.push(full_path);
+                }
+                return;
+            }
+            if store_reasons {
+                self.reject_file(full_path, reason, matched_rule);
+            }
+            return;
+        }
+
+        self.rejected_files.remove(full_path.as_path());
+        if self.existing_files_hashset.insert(full_path.clone()) {
+            self.new_files_queue.push(full_path);
+        }
+    }
+
+    fn reject_file(&mut self, full_path: PathBuf, reason: Reason, matched_rule: Option<String>) {
+        trace!("Rejected {} for {}", pathbuf_to_string(&full_path), reason);
+        self.rejected_files.replace(PathBufReason {
+            pathbuf: full_path,
+            reason,
+            matched_rule,
+            rejected_at: SystemTime::now(),
+        });
+    }
+
+    fn load_ignore_files(&mut self, full_path: &Path) {
+        load_ignore_files(
+            &self.config.read().unwrap(),
+            &mut self.ignore_files,
+            full_path,
+        );
+    }
+
+    ///Makes the next file checked under this directory read the changed .tlmignore file again
+    fn forget_ignore_file(&mut self, ignore_file_path: &Path) {
+        if let Some(directory) = ignore_file_path.parent() {
+            self.ignore_files.remove(directory);
+        }
+    }
+
+    ///Checks a file found outside of a crawl, such as by the watcher, against the symlink and
+    ///file system options of its root directory. Hardlinks are only deduplicated while crawling
+    fn check_file_links(&self, full_path: &Path) -> Option<(Reason, Option<String>)> {
+        let config = self.config.read().unwrap();
+        let root_directory = config
+            .tracked_directories
+            .get_root_directory_for_path(full_path)?;
+
+        if !root_directory.follow_symlinks {
+            for path in full_path
+                .ancestors()
+                .take_while(|path| *path != root_directory.path)
+            {
+                if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_symlink()) {
+                    return Some((Reason::Symlink, None));
+                }
+            }
+        }
+
+        if root_directory.same_file_system
+            && get_device(full_path) != get_device(&root_directory.path)
+        {
+            return Some((Reason::MountBoundary, None));
+        }
+        None
+    }
+
+    fn crawl_root_directories(&self) -> Crawl {
+        let root_directories = self
+            .config
+            .read()
+            .unwrap()
+            .tracked_directories
+            .root_directories
+            .clone();
+        crawl_root_directories(&root_directories, &self.existing_files_hashset)
+    }
+
+    ///Import a single file into the new files queue, used when a file is found outside
+    ///of a full crawl of the root directories. Returns true if the file was queued
+    pub fn import_file(&mut self, full_path: PathBuf) -> bool {
+        if is_ignore_file(&full_path) {
+            self.forget_ignore_file(&full_path);
+            return false;
+        }
+        let queue_length = self.new_files_queue.len() + self.new_sidecars_queue.len();
+        let previous_rejection = self.rejected_files.take(full_path.as_path());
+        match self.check_file_links(&full_path) {
+            Some((reason, matched_rule)) => {
+                self.reject_file(full_path.clone(), reason, matched_rule)
+            }
+            None => self.accept_or_reject_file(full_path.clone(), true),
+        }
+
+        let connection = establish_connection();
+        match self.rejected_files.take(full_path.as_path()) {
+            Some(mut rejected_file) => {
+                keep_rejection_time(&mut rejected_file, previous_rejection.as_ref());
+                create_or_update_rejected_files(
+                    &connection,
+                    vec![NewRejectedFile::from_path_buf_reason(&rejected_file)],
+                );
+                self.rejected_files.insert(rejected_file);
+            }
+            None => {
+                if previous_rejection.is_some() {
+                    delete_rejected_files(&connection, vec![pathbuf_to_string(&full_path)]);
+                }
+            }
+        }
+        self.new_files_queue.len() + self.new_sidecars_queue.len() > queue_length
+    }
+
+    ///Stores the entries that were skipped by walking a directory found outside of a full crawl,
+    ///such as one moved into a root directory while it's watched
+    pub fn reject_crawled_files(&mut self, skipped: Vec<(PathBuf, Reason, Option<String>)>) {
+        let mut new_rejected_files: Vec<NewRejectedFile> = Vec::new();
+        for (full_path, reason, matched_rule) in skipped {
+            let previous_rejection = self.rejected_files.take(full_path.as_path());
+            let mut rejected_file = PathBufReason {
+                pathbuf: full_path,
+                reason,
+                matched_rule,
+                rejected_at: SystemTime::now(),
+            };
+            keep_rejection_time(&mut rejected_file, previous_rejection.as_ref());
+            new_rejected_files.push(NewRejectedFile::from_path_buf_reason(&rejected_file));
+            self.rejected_files.insert(rejected_file);
+        }
+        if !new_rejected_files.is_empty() {
+            create_or_update_rejected_files(&establish_connection(), new_rejected_files);
+        }
+    }
+
+    ///Loads the rejected files stored by previous runs, clearing any that
+    ///the current config would now accept
+    fn load_rejected_files(&mut self) {
+        let mut cleared_rejected_files: Vec<String> = Vec::new();
+        for rejected_file in get_all_rejected_files()
+            .into_iter()
+            .filter_map(PathBufReason::from_model)
+        {
+            self.load_ignore_files(&rejected_file.pathbuf);
+            //Crawl reasons can only be cleared by the next crawl
+            if rejected_file.reason.is_from_crawl()
+                || self.check_file(&rejected_file.pathbuf).is_some()
+            {
+                self.rejected_files.insert(rejected_file);
+            } else {
+                cleared_rejected_files.push(pathbuf_to_string(&rejected_file.pathbuf));
+            }
+        }
+        if !cleared_rejected_files.is_empty() {
+            info!(
+                "Cleared {} rejected files that are now allowed by the config",
+                cleared_rejected_files.len()
+            );
+            delete_rejected_files(&establish_connection(), cleared_rejected_files);
+        }
+    }
+
+    ///Get rejected files, optionally only those rejected for a specific reason
+    pub fn get_rejected_files(&self, reason: Option<Reason>) -> Vec<&PathBufReason> {
+        self.rejected_files
+            .iter()
+            .filter(|rejected_file| reason.is_none() || Some(rejected_file.reason) == reason)
+            .collect()
+    }
+
+    ///Points an existing FileVersion at the path it has been renamed to, if the file
+    ///hasn't been processed yet the queued path is replaced instead.
+    ///Returns false if nothing was tracked at the original path
+    pub fn rename_file(&mut self, from: &Path, to: &Path) -> bool {
+        if is_ignore_file(from) {
+            self.forget_ignore_file(from);
+            return self.import_file(to.to_path_buf());
+        }
+        if let Some(index) = self.new_files_queue.iter().position(|path| path == from) {
+            self.existing_files_hashset.remove(from);
+            self.new_files_queue.remove(index);
+            return self.import_file(to.to_path_buf());
+        }
+
+        let connection = establish_connection();
+        if let Some(file_version) = self.get_file_version_by_path_mut(from) {
+            file_version.full_path = to.to_path_buf();
+            file_version.missing = false;
+            file_version.update_file_version(&connection);
+            info!(
+                "Tracked file was renamed from: {} to: {}",
+                pathbuf_to_string(from),
+                pathbuf_to_string(to)
+            );
+            self.existing_files_hashset.remove(from);
+            self.existing_files_hashset.insert(to.to_path_buf());
+            return true;
+        }
+        false
+    }
+
+    ///Points everything tracked under a renamed directory at the new directory, files that
+    ///haven't been processed yet have their queued paths replaced instead
+    pub fn rename_directory(&mut self, from: &Path, to: &Path) {
+        let connection = establish_connection();
+        let mut renamed_file_versions = 0;
+        for file_version in self.file_versions_mut() {
+            if let Some(new_path) = rebase_path(&file_version.full_path, from, to) {
+                file_version.full_path = new_path;
+                file_version.missing = false;
+                file_version.update_file_version(&connection);
+                renamed_file_versions += 1;
+            }
+        }
+        for generic in self.generics_mut() {
+            for sidecar in generic.sidecars.iter_mut() {
+                if let Some(new_path) = rebase_path(&sidecar.full_path, from, to) {
+                    update_sidecar_path(&connection, sidecar.id, &new_path);
+                    sidecar.full_path = new_path;
+                }
+            }
+        }
+        for full_path in self
+            .new_files_queue
+            .iter_mut()
+            .chain(self.new_sidecars_queue.iter_mut())
+        {
+            if let Some(new_path) = rebase_path(full_path, from, to) {
+                *full_path = new_path;
+            }
+        }
+        self.existing_files_hashset = self
+            .existing_files_hashset
+            .drain()
+            .map(|full_path| rebase_path(&full_path, from, to).unwrap_or(full_path))
+            .collect();
+        info!(
+            "Tracked directory was renamed from: {} to: {}, relinked {} files",
+            pathbuf_to_string(from),
+            pathbuf_to_string(to),
+            renamed_file_versions
+        );
+    }
+
+    ///Handles a directory that no longer exists on disk the same way as remove_file does for
+    ///every file that was tracked inside it
+    pub fn remove_directory(&mut self, directory: &Path) {
+        let full_paths: Vec<PathBuf> = self
+            .existing_files_hashset
+            .iter()
+            .chain(self.new_files_queue.iter())
+            .filter(|full_path| full_path.starts_with(directory) && *full_path != directory)
+            .cloned()
+            .collect();
+        for full_path in full_paths {
+            self.remove_file(&full_path);
+        }
+    }
+
+    ///Handles a file that no longer exists on disk. Files that haven't been processed
+    ///yet are dropped from the queue, processed files are marked as missing so they
+    ///can be relinked by reconcile if they show up somewhere else
+    pub fn remove_file(&mut self, full_path: &Path) {
+        if is_ignore_file(full_path) {
+            self.forget_ignore_file(full_path);
+            return;
+        }
+        if let Some(index) = self
+            .new_files_queue
+            .iter()
+            .position(|path| path == full_path)
+        {
+            self.new_files_queue.remove(index);
+            self.existing_files_hashset.remove(full_path);
+        } else if let Some(file_version) = self.get_file_version_by_path_mut(full_path) {
+            warn!(
+                "Tracked file has been removed from disk: {}",
+                pathbuf_to_string(full_path)
+            );
+            file_version.missing = true;
+            file_version.update_file_version(&establish_connection());
+        }
+    }
+
+    ///Takes a copy of what reconcile needs, so files can be crawled, checked and hashed without
+    ///holding the FileManager's lock
+    pub fn get_reconciler(&self) -> Reconciler {
+        Reconciler {
+            root_directories: self
+                .config
+                .read()
+                .unwrap()
+                .tracked_directories
+                .root_directories
+                .clone(),
+            existing_files_hashset: self.existing_files_hashset.clone(),
+            file_versions: self
+                .generics()
+                .into_iter()
+                .flat_map(|generic| generic.file_versions.iter().cloned())
+                .collect(),
+        }
+    }
+
+    ///Relinks and marks FileVersions as missing from what reconcile found. FileVersions whose
+    ///path changed and new files that were processed since the reconciler was made are left alone
+    pub fn apply_reconciliation(&mut self, reconciler: &Reconciler, report: &ReconciliationReport) {
+        let checked_paths: HashMap<i32, &PathBuf> = reconciler
+            .file_versions
+            .iter()
+            .map(|file_version| (file_version.id, &file_version.full_path))
+            .collect();
+        let relinked: HashMap<&PathBuf, &PathBuf> = report
+            .relinked
+            .iter()
+            .filter(|(previous_path, new_path)| {
+                let is_queued = self.new_files_queue.contains(new_path);
+                if !is_queued {
+                    warn!(
+                        "Not relinking '{}' to '{}', it's no longer a new file",
+                        pathbuf_to_string(previous_path),
+                        pathbuf_to_string(new_path)
+                    );
+                }
+                is_queued
+            })
+            .map(|(previous_path, new_path)| (previous_path, new_path))
+            .collect();
+        let orphaned: HashSet<&PathBuf> = report.orphaned.iter().collect();
+
+        let connection = establish_connection();
+        let mut applied_relinks: Vec<(PathBuf, PathBuf)> = Vec::new();
+        for file_version in self.file_versions_mut() {
+            if checked_paths.get(&file_version.id) != Some(&&file_version.full_path) {
+                continue;
+            }
+            if let Some(new_path) = relinked.get(&file_version.full_path) {
+                applied_relinks.push((file_version.full_path.clone(), (*new_path).clone()));
+                file_version.full_path = (*new_path).clone();
+                file_version.missing = false;
+                file_version.update_file_version(&connection);
+            } else if orphaned.contains(&file_version.full_path) != file_version.missing {
+                file_version.missing = !file_version.missing;
+                file_version.update_file_version(&connection);
+            }
+        }
+        for (previous_path, new_path) in applied_relinks {
+            self.new_files_queue.retain(|path| path != &new_path);
+            self.existing_files_hashset.remove(&previous_path);
+        }
+    }
+
+    ///Groups files that are stored more than once, the report is kept so the WebUI can request
+    ///it and pick which copy of each group to keep
+    pub fn find_duplicates(&mut self) -> &DuplicateReport {
+        self.duplicate_report = DuplicateReport::find(&self.generics(), &self.shows, &self.movies);
+        &self.duplicate_report
+    }
+
+    ///Reads the tvshow.nfo and episode nfo files of every show again, updating the titles, air
+    ///dates and plots of shows and episodes that have them
+    pub fn read_nfo_files(&mut self) {
+        let connection = establish_connection();
+        let tracked_directories = self.config.read().unwrap().tracked_directories.clone();
+        let mut updated_show_count = 0;
+        let mut updated_episode_count = 0;
+        //Shows are only renamed if no other show has the same title and year
+        let mut show_names: HashSet<(String, Option<i32>)> = self
+            .shows
+            .iter()
+            .map(|show| (show.show_title.clone(), show.show_year))
+            .collect();
+        for show in self.shows.iter_mut() {
+            let show_directory = show
+                .seasons
+                .iter()
+                .flat_map(|season| season.episodes.iter())
+                .find_map(|episode| episode.generic.file_versions.first())
+                .and_then(|file_version| {
+                    tracked_directories.get_show_directory(&file_version.full_path)
+                });
+            if let Some(nfo_show) = show_directory.and_then(|directory| read_show_nfo(&directory)) {
+                if let Some(title) = nfo_show.title {
+                    //Keep the year from the folder name if the nfo doesn't have one
+                    let show_name = (title, nfo_show.year.or(show.show_year));
+                    let current_show_name = (show.show_title.clone(), show.show_year);
+                    if show_name != current_show_name && show_names.contains(&show_name) {
+                        warn!(
+                            "Not renaming show: {} to: {}, a show with that title and year already exists",
+                            show.show_title, show_name.0
+                        );
+                    } else {
+                        show_names.remove(&current_show_name);
+                        show_names.insert(show_name.clone());
+                        (show.show_title, show.show_year) = show_name;
+                    }
+                }
+                show.plot = nfo_show.plot;
+                update_show(&connection, show);
+                updated_show_count += 1;
+            }
+
+            for season in show.seasons.iter_mut() {
+                for episode in season.episodes.iter_mut() {
+                    episode.show_title = show.show_title.clone();
+                    let nfo_episodes = match episode.generic.file_versions.first() {
+                        Some(file_version) => read_episode_nfo(&file_version.full_path),
+                        None => continue,
+                    };
+                    if nfo_episodes.is_empty() {
+                        continue;
+                    }
+                    let episode_numbers = episode.show_episode.clone();
+                    for (i, episode_number) in episode_numbers.into_iter().enumerate() {
+                        let nfo_episode = match find_nfo_episode(
+                            &nfo_episodes,
+                            episode.show_season,
+                            episode_number,
+                        ) {
+                            Some(nfo_episode) => nfo_episode,
+                            None => continue,
+                        };
+                        let episode_title =
+                            nfo_episode.title.as_ref().unwrap_or(&episode.episode_title);
+                        update_episode_metadata(
+                            &connection,
+                            episode,
+                            episode_number,
+                            episode_title,
+                            nfo_episode.aired.as_deref(),
+                            nfo_episode.plot.as_deref(),
+                        );
+                        //Episodes in memory hold the metadata of their first episode number
+                        if i == 0 {
+                            episode.episode_title = episode_title.clone();
+                            episode.air_date = nfo_episode.aired.clone();
+                            episode.plot = nfo_episode.plot.clone();
+                        }
+                    }
+                    updated_episode_count += 1;
+                }
+            }
+        }
+        info!(
+            "Read nfo metadata for {} shows and {} episodes",
+            updated_show_count, updated_episode_count
+        );
+    }
+
+    ///Keeps one FileVersion of a group from the duplicate report and deletes every other copy in
+    ///the group from disk and the database. Copies that have changed since the report was made
+    ///are left alone. Returns false if the FileVersion isn't in the group or has changed
+    pub fn keep_duplicate(&mut self, group_id: usize, generic_uid: i32, id: i32) -> bool {
+        let group = match self.duplicate_report.get_group(group_id) {
+            Some(group) if group.contains(generic_uid, id) => group.clone(),
+            _ => return false,
+        };
+
+        //The report may be stale, so nothing is deleted unless every file is still the one that
+        //was found to be a duplicate
+        let is_unchanged = |file_manager: &Self, file_version: &DuplicateFileVersion| {
+            let is_tracked = file_manager.generics().into_iter().any(|generic| {
+                generic.get_generic_uid() == file_version.generic_uid
+                    && generic.file_versions.iter().any(|tracked_file_version| {
+                        tracked_file_version.id == file_version.id
+                            && tracked_file_version.full_path == file_version.full_path
+                    })
+            });
+            if !is_tracked || !file_version.is_unchanged() {
+                warn!(
+                    "File has changed since duplicates were found, run FindDuplicates again: {}",
+                    pathbuf_to_string(&file_version.full_path)
+                );
+                return false;
+            }
+            true
+        };
+        match group
+            .file_versions
+            .iter()
+            .find(|file_version| file_version.generic_uid == generic_uid && file_version.id == id)
+        {
+            Some(kept_file_version) if is_unchanged(self, kept_file_version) => {}
+            _ => return false,
+        }
+
+        let connection = establish_connection();
+        let mut removed_file_versions: Vec<(i32, i32)> = Vec::new();
+        for file_version in group.file_versions {
+            //Other FileVersions of the kept generic are its encodes rather than duplicates
+            if file_version.generic_uid == generic_uid || !is_unchanged(self, &file_version) {
+                continue;
+            }
+            if let Err(err) = fs::remove_file(&file_version.full_path) {
+                error!(
+                    "Failed to remove duplicate: {}. Err: {}",
+                    pathbuf_to_string(&file_version.full_path),
+                    err
+                );
+                continue;
+            }
+            info!(
+                "Removed duplicate: {}",
+                pathbuf_to_string(&file_version.full_path)
+            );
+            self.remove_file_version(&connection, file_version.generic_uid, file_version.id);
+            removed_file_versions.push((file_version.generic_uid, file_version.id));
+        }
+        self.duplicate_report
+            .remove_file_versions(&removed_file_versions);
+        true
+    }
+
+    ///Removes a FileVersion from ram and the database. When it was the last FileVersion of its
+    ///generic, the generic is removed as well along with its sidecars on disk
+    fn remove_file_version(&mut self, connection: &PgConnection, generic_uid: i32, id: i32) {
+        let mut removed_paths: Vec<PathBuf> = Vec::new();
+        let mut is_generic_empty = false;
+        if let Some(generic) = self
+            .generics_mut()
+            .into_iter()
+            .find(|generic| generic.get_generic_uid() == generic_uid)
+        {
+            if let Some(index) = generic
+                .file_versions
+                .iter()
+                .position(|file_version| file_version.id == id)
+            {
+                let file_version = generic.file_versions.remove(index);
+                delete_file_version(connection, id);
+                if file_version.master_file {
+                    if let Some(new_master_file) = generic.file_versions.first_mut() {
+                        new_master_file.master_file = true;
+                        new_master_file.update_file_version(connection);
+                    }
+                }
+                removed_paths.push(file_version.full_path);
+            }
+
+            if generic.file_versions.is_empty() {
+                for sidecar in &generic.sidecars {
+                    if let Err(err) = fs::remove_file(&sidecar.full_path) {
+                        warn!(
+                            "Failed to remove sidecar: {}. Err: {}",
+                            pathbuf_to_string(&sidecar.full_path),
+                            err
+                        );
+                    }
+                    removed_paths.push(sidecar.full_path.clone());
+                }
+                is_generic_empty = true;
+            }
+        }
+
+        if is_generic_empty {
+            delete_generic(connection, generic_uid);
+            self.generic_files
+                .retain(|generic| generic.get_generic_uid() != generic_uid);
+            for show in self.shows.iter_mut() {
+                for season in show.seasons.iter_mut() {
+                    season
+                        .episodes
+                        .retain(|episode| episode.generic.get_generic_uid() != generic_uid);
+                }
+            }
+            self.movies
+                .retain(|movie| movie.generic.get_generic_uid() != generic_uid);
+            self.extras
+                .retain(|extra| extra.generic.get_generic_uid() != generic_uid);
+            for extra in self.extras.iter_mut() {
+                if extra.movie_generic_uid == Some(generic_uid) {
+                    extra.movie_generic_uid = None;
+                }
+            }
+        }
+
+        for path in removed_paths {
+            self.existing_files_hashset.remove(&path);
+        }
+    }
+
+    ///Every FileVersion stored in ram, whether it belongs to a generic, an episode, a movie or an extra
+    pub fn file_versions_mut(&mut self) -> Vec<&mut FileVersion> {
+        let mut file_versions: Vec<&mut FileVersion> = Vec::new();
+        for generic in self.generic_files.iter_mut() {
+            file_versions.extend(generic.file_versions.iter_mut());
+        }
+        for show in self.shows.iter_mut() {
+            for season in show.seasons.iter_mut() {
+                for episode in season.episodes.iter_mut() {
+                    file_versions.extend(episode.generic.file_versions.iter_mut());
+                }
+            }
+        }
+        for movie in self.movies.iter_mut() {
+            file_versions.extend(movie.generic.file_versions.iter_mut());
+        }
+        for extra in self.extras.iter_mut() {
+            file_versions.extend(extra.generic.file_versions.iter_mut());
+        }
+        file_versions
+    }
+
+    fn get_file_version_by_path_mut(&mut self, full_path: &Path) -> Option<&mut FileVersion> {
+        self.file_versions_mut()
+            .into_iter()
+            .find(|file_version| file_version.full_path == full_path)
+    }
+
+    ///Import all files in the list of tracked root directories
+    ///into a queue for later processing. Uses a Hash set to
+    ///guarantee no duplicates in O(1) time
+    pub fn import_files(&mut self) {
+        //If we crawl first we can max out IO without waiting
+        //for accept_or_reject files. Will increase memory overhead obviously
+        let crawl = self.crawl_root_directories();
+        self.import_crawl(crawl);
+    }
+
+    ///Imports the files found by crawling every root directory into the new files queue
+    pub fn import_crawl(&mut self, crawl: Crawl) {
+        //Rejections are rebuilt every crawl so anything no longer rejected gets cleared
+        let previous_rejected_files = std::mem::take(&mut self.rejected_files);
+        self.ignore_files.clear();
+
+        for full_path in crawl.files {
+            self.accept_or_reject_file(full_path, true);
+        }
+        for (full_path, reason, matched_rule) in crawl.skipped {
+            self.reject_file(full_path, reason, matched_rule);
+        }
+
+        let mut cleared_rejected_files: Vec<String> = Vec::new();
+        for previous_rejected_file in previous_rejected_files.iter() {
+            if !self
+                .rejected_files
+                .contains(previous_rejected_file.pathbuf.as_path())
+            {
+                cleared_rejected_files.push(pathbuf_to_string(&previous_rejected_file.pathbuf));
+            }
+        }
+
+        let mut new_rejected_files: Vec<NewRejectedFile> = Vec::new();
+        self.rejected_files = std::mem::take(&mut self.rejected_files)
+            .into_iter()
+            .map(|mut rejected_file| {
+                let previous = previous_rejected_files.get(rejected_file.pathbuf.as_path());
+                keep_rejection_time(&mut rejected_file, previous);
+                new_rejected_files.push(NewRejectedFile::from_path_buf_reason(&rejected_file));
+                rejected_file
+            })
+            .collect();
+
+        let connection = establish_connection();
+        if !cleared_rejected_files.is_empty() {
+            delete_rejected_files(&connection, cleared_rejected_files);
+        }
+        if !new_rejected_files.is_empty() {
+            create_or_update_rejected_files(&connection, new_rejected_files);
+        }
+    }
+
+    ///Specials, extras and trailers are skipped unless the profile is listed in
+    ///bulk_encode_extras_profiles in the config
+    pub fn generate_encodes_for_all(&self, encode_profile: &EncodeProfile) -> Vec<Encode> {
+        let include_extras = self
+            .config
+            .read()
+            .unwrap()
+            .bulk_encode_extras_profiles
+            .contains(encode_profile);
+        let mut encodes: Vec<Encode> = Vec::new();
+        for generic in &self.generic_files {
+            for file_version in generic.file_versions.iter().filter(|f| is_encode_source(f)) {
+                encodes.push(Encode::new(file_version, encode_profile, &self.config));
+            }
+        }
+        for show in &self.shows {
+            for season in &show.seasons {
+                for episode in &season.episodes {
+                    if episode.generic.designation.is_bonus_content() && !include_extras {
+                        continue;
+                    }
+                    for file_version in episode
+                        .generic
+                        .file_versions
+                        .iter()
+                        .filter(|f| is_encode_source(f))
+                    {
+                        encodes.push(Encode::new(file_version, encode_profile, &self.config));
+                    }
+                }
+            }
+        }
+        for movie in &self.movies {
+            for file_version in movie
+                .generic
+                .file_versions
+                .iter()
+                .filter(|f| is_encode_source(f))
+            {
+                encodes.push(Encode::new(file_version, encode_profile, &self.config));
+            }
+        }
+        if include_extras {
+            for extra in &self.extras {
+                for file_version in extra
+                    .generic
+                    .file_versions
+                    .iter()
+                    .filter(|f| is_encode_source(f))
+                {
+                    encodes.push(Encode::new(file_version, encode_profile, &self.config));
+                }
+            }
+        }
+        encodes
+    }
+
+    ///Encodes every generic that has no version the device can direct play with the device's
+    ///encode profile, from the master file if it can be used. Bonus content is skipped the same
+    ///as when encoding everything, as are generics in queued_generics which already have an
+    ///encode with the profile waiting
+    pub fn generate_encodes_for_device(
+        &self,
+        device: &DeviceProfile,
+        queued_generics: &HashSet<i32>,
+    ) -> Vec<Encode> {
+        let include_extras = self
+            .config
+            .read()
+            .unwrap()
+            .bulk_encode_extras_profiles
+            .contains(&device.encode_profile);
+        let mut encodes: Vec<Encode> = Vec::new();
+        for generic in self.generics() {
+            if generic.designation.is_bonus_content() && !include_extras {
+                continue;
+            }
+            if queued_generics.contains(&generic.get_generic_uid()) {
+                continue;
+            }
+            if let Some(file_version) = find_device_encode_source(generic, device) {
+                encodes.push(Encode::new(
+                    file_version,
+                    &device.encode_profile,
+                    &self.config,
+                ));
+            }
+        }
+        encodes
+    }
+
+    ///Uses the default encode profile of the file's root directory if no profile is given
+    pub fn generate_encode_for_file(
+        &self,
+        encode_profile: Option<&EncodeProfile>,
+        generic_uid: i32,
+        id: i32,
+    ) -> Option<Encode> {
+        let new_encode = |file_version: &FileVersion| {
+            let encode_profile = match encode_profile {
+                Some(encode_profile) => encode_profile.clone(),
+                None => self
+                    .config
+                    .read()
+                    .unwrap()
+                    .tracked_directories
+                    .get_encode_profile(&file_version.full_path),
+            };
+            if !is_encode_source(file_version) {
+                return None;
+            }
+            Some(Encode::new(file_version, &encode_profile, &self.config))
+        };
+        for generic in &self.generic_files {
+            if generic.get_generic_uid() == generic_uid {
+                for file_version in &generic.file_versions {
+                    if file_version.id == id {
+                        return new_encode(file_version);
+                    }
+                }
+            }
+        }
+        for show in &self.shows {
+            for season in &show.seasons {
+                for episode in &season.episodes {
+                    if episode.generic.get_generic_uid() == generic_uid {
+                        for file_version in &episode.generic.file_versions {
+                            return new_encode(file_version);
+                        }
+                    }
+                }
+            }
+        }
+        for movie in &self.movies {
+            if movie.generic.get_generic_uid() == generic_uid {
+                for file_version in &movie.generic.file_versions {
+                    if file_version.id == id {
+                        return new_encode(file_version);
+                    }
+                }
+            }
+        }
+        for extra in &self.extras {
+            if extra.generic.get_generic_uid() == generic_uid {
+                for file_version in &extra.generic.file_versions {
+                    if file_version.id == id {
+                        return new_encode(file_version);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    ///Insert a vector of episodes into an existing show
+    pub fn insert_episodes(&mut self, episodes: Vec<Episode>) {
+        //find the associated show
+        //insert episode into that show
+        for episode in episodes {
+            let show_uid = episode.show_uid;
+            for show in &mut self.shows {
+                if show.show_uid == show_uid {
+                    show.insert_episode(episode);
+                    break;
+                }
+            }
+        }
+    }
+
+    ///Check if a show exists in ram, shows with the same title are told apart by their year
+    fn show_exists(&self, show_title: &str, show_year: Option<i32>) -> Option<i32> {
+        for show in &self.shows {
+            if show.show_title == show_title && show.show_year == show_year {
+                return Some(show.show_uid);
+            }
+        }
+        None
+    }
+
+    ///Make sure a show exists by checking for it in ram and inserting it into
+    ///the database if it doesn't exist yet
+    fn ensure_show_exists(&mut self, show_info: ShowInfo, connection: &PgConnection) -> i32 {
+        let show_uid = self.show_exists(&show_info.title, show_info.year);
         match show_uid {
             Some(uid) => uid,
             None => {
-                let show_model = create_show(connection, show_title.clone());
-
+                let show_model = create_show(
+                    connection,
+                    NewShow {
+                        show_title: show_info.title,
+                        show_year: show_info.year,
+                        plot: show_info.plot,
+                    },
+                );
                 let show_uid = show_model.show_uid;
-                let new_show = Show {
-                    show_uid,
-                    show_title,
-                    seasons: Vec::new(),
-                };
-                self.shows.push(new_show);
+                self.shows.push(Show::from_show_model(show_model));
 
                 show_uid
             }
@@ -647,3 +2166,687 @@ impl FileManager {
         }
     }
 }
+
+///The path moved from one directory to another, none if it isn't inside the first directory
+pub fn rebase_path(full_path: &Path, from: &Path, to: &Path) -> Option<PathBuf> {
+    full_path
+        .strip_prefix(from)
+        .ok()
+        .map(|relative_path| to.join(relative_path))
+}
+
+///Works out what reconcile would do without changing anything. FileVersions that no longer exist
+///at their full_path are matched to new files by the fast hash and then confirmed with the full
+///hash, the new files that are left over are reported as added
+pub fn match_missing_files<'a>(
+    file_versions: impl IntoIterator<Item = &'a FileVersion>,
+    new_files: &[PathBuf],
+) -> ReconciliationReport {
+    let missing_file_versions: Vec<&FileVersion> = file_versions
+        .into_iter()
+        .filter(|file_version| !file_version.full_path.exists())
+        .collect();
+
+    //Fast hashes of new files are only needed if there is something to relink, they're made
+    //with each hash version the missing files were hashed with so they can be compared
+    let mut new_files_by_fast_hash: HashMap<(i32, String), Vec<PathBuf>> = HashMap::new();
+    let hash_versions: HashSet<i32> = missing_file_versions
+        .iter()
+        .map(|file_version| file_version.hash_version)
+        .collect();
+    for hash_version in hash_versions {
+        for full_path in new_files {
+            match fast_hash_with_version(full_path, hash_version) {
+                Ok(fast_file_hash) => new_files_by_fast_hash
+                    .entry((hash_version, fast_file_hash))
+                    .or_default()
+                    .push(full_path.clone()),
+                Err(err) => warn!(
+                    "Failed to hash new file, it won't be relinked: {}. Err: {}",
+                    pathbuf_to_string(full_path),
+                    err
+                ),
+            }
+        }
+    }
+
+    let mut report = ReconciliationReport::default();
+    for missing_file_version in missing_file_versions {
+        let hash_version = missing_file_version.hash_version;
+        let mut new_path: Option<PathBuf> = None;
+        if let (Some(fast_file_hash), Some(file_hash)) =
+            (&missing_file_version.fast_hash, &missing_file_version.hash)
+        {
+            if let Some(candidates) =
+                new_files_by_fast_hash.get_mut(&(hash_version, fast_file_hash.clone()))
+            {
+                if let Some(index) = candidates.iter().position(|candidate| {
+                    hash_with_version(candidate, hash_version)
+                        .is_ok_and(|candidate_hash| &candidate_hash == file_hash)
+                }) {
+                    new_path = Some(candidates.remove(index));
+                }
+            }
+        }
+
+        let previous_path = missing_file_version.full_path.clone();
+        match new_path {
+            Some(new_path) => {
+                //Files are listed once per hash version, so take it out of the others too
+                for candidates in new_files_by_fast_hash.values_mut() {
+                    candidates.retain(|path| path != &new_path);
+                }
+                report.relinked.push((previous_path, new_path));
+            }
+            None => report.orphaned.push(previous_path),
+        }
+    }
+
+    report.added = new_files
+        .iter()
+        .filter(|full_path| {
+            !report
+                .relinked
+                .iter()
+                .any(|(_, new_path)| new_path == *full_path)
+        })
+        .cloned()
+        .collect();
+    report
+}
+
+///The version of a generic to encode for a device, none if a version already direct plays on it,
+///was encoded with the device's profile or hasn't been profiled, since then it isn't known
+///whether it would play. The master file is used if it can be
+pub fn find_device_encode_source<'a>(
+    generic: &'a Generic,
+    device: &DeviceProfile,
+) -> Option<&'a FileVersion> {
+    let file_versions: Vec<&FileVersion> = generic
+        .file_versions
+        .iter()
+        .filter(|file_version| !file_version.missing)
+        .collect();
+    //Encodes are named after their source with the profile added to the end
+    let suffix = format!("_{}", device.encode_profile);
+    if file_versions.iter().any(|file_version| {
+        file_version.profile_is_none()
+            || device.can_direct_play(file_version)
+            || get_file_stem(&file_version.full_path).ends_with(&suffix)
+    }) {
+        return None;
+    }
+    file_versions
+        .into_iter()
+        .filter(|file_version| is_encode_source(file_version))
+        .max_by_key(|file_version| file_version.master_file)
+}
+
+///Corrupted files are never used as the source of an encode, and neither are files that haven't
+///been profiled since the encode depends on whether the source is HDR
+fn is_encode_source(file_version: &FileVersion) -> bool {
+    if file_version.corrupted {
+        warn!(
+            "Not encoding from corrupted file: {}",
+            pathbuf_to_string(&file_version.full_path)
+        );
+        return false;
+    }
+    if file_version.profile_is_none() {
+        warn!(
+            "Not encoding from file that hasn't been profiled, run generate_profiles first: {}",
+            pathbuf_to_string(&file_version.full_path)
+        );
+        return false;
+    }
+    true
+}
+
+///The title, year and plot of the show a file belongs to, from the show's tvshow.nfo when it has
+///one, otherwise from the name of the show's folder
+struct ShowInfo {
+    title: String,
+    year: Option<i32>,
+    plot: Option<String>,
+}
+
+impl fmt::Display for ShowInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.year {
+            Some(year) => write!(f, "{} ({})", self.title, year),
+            None => write!(f, "{}", self.title),
+        }
+    }
+}
+
+fn get_show_info(tracked_directories: &TrackedDirectories, full_path: &Path) -> ShowInfo {
+    let (title, year) = parse_show_title(&tracked_directories.get_show_title(full_path));
+    let mut show_info = ShowInfo {
+        title,
+        year,
+        plot: None,
+    };
+    if let Some(nfo_show) = tracked_directories
+        .get_show_directory(full_path)
+        .and_then(|show_directory| read_show_nfo(&show_directory))
+    {
+        if let Some(title) = nfo_show.title {
+            show_info.title = title;
+            //Keep the year from the folder name if the nfo doesn't have one
+            show_info.year = nfo_show.year.or(show_info.year);
+        }
+        show_info.plot = nfo_show.plot;
+    }
+    show_info
+}
+
+fn get_nfo_episode_title(
+    full_path: &Path,
+    season_number: i32,
+    episode_number: i32,
+) -> Option<String> {
+    find_nfo_episode(&read_episode_nfo(full_path), season_number, episode_number)
+        .and_then(|nfo_episode| nfo_episode.title.clone())
+}
+
+///What a new file was recognised as, based on its name and the library type of its root directory
+enum ParsedFile {
+    Episode(ParsedEpisode),
+    //An episode in season 0
+    Special(ParsedEpisode),
+    Movie(ParsedMovie),
+    //The designation is either extra or trailer, along with the path used to find its show or movie
+    Extra(Designation, PathBuf),
+    //In a generic library so it wasn't parsed
+    Generic,
+    //Didn't match the naming expected by its root directory
+    Unmatched,
+}
+
+///Identifies a file regardless of the path used to reach it, (device, inode)
+type FileId = (u64, u64);
+
+#[cfg(unix)]
+fn get_file_id(metadata: &fs::Metadata) -> Option<FileId> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+//Device and inode numbers aren't available elsewhere, so hardlinks and mounts aren't detected
+#[cfg(not(unix))]
+fn get_file_id(_metadata: &fs::Metadata) -> Option<FileId> {
+    None
+}
+
+fn get_device(full_path: &Path) -> Option<u64> {
+    fs::metadata(full_path)
+        .ok()
+        .and_then(|metadata| get_file_id(&metadata))
+        .map(|(device, _)| device)
+}
+
+fn is_ignore_file(full_path: &Path) -> bool {
+    full_path
+        .file_name()
+        .is_some_and(|file_name| file_name == IGNORE_FILE_NAME)
+}
+
+///A copy of what a dry run of importing needs from the FileManager, so the root directories can
+///be crawled without holding the FileManager's lock
+pub struct ImportPlanner {
+    config: ServerConfig,
+    existing_files_hashset: HashSet<PathBuf>,
+    //Files already waiting in the FileManager's new files queue
+    queued_files: Vec<PathBuf>,
+    //(title, year) of the existing shows and movies
+    shows: Vec<(String, Option<i32>)>,
+    movies: Vec<(String, i32)>,
+    ignore_files: HashMap<PathBuf, Option<IgnoreRules>>,
+}
+
+impl ImportPlanner {
+    pub fn new(
+        config: ServerConfig,
+        existing_files_hashset: HashSet<PathBuf>,
+        queued_files: Vec<PathBuf>,
+        shows: Vec<(String, Option<i32>)>,
+        movies: Vec<(String, i32)>,
+    ) -> Self {
+        Self {
+            config,
+            existing_files_hashset,
+            queued_files,
+            shows,
+            movies,
+            ignore_files: HashMap::new(),
+        }
+    }
+
+    ///Dry run of import_files followed by process_new_files that doesn't write to the database
+    ///or change the library in ram
+    pub fn plan(mut self) -> ImportPlan {
+        let mut plan = ImportPlan::default();
+        let config = &self.config;
+        let tracked_directories = &config.tracked_directories;
+
+        //Files already waiting in the queue would be processed along with anything new
+        let queued_files: HashSet<&PathBuf> = self.queued_files.iter().collect();
+        let mut new_files: Vec<PathBuf> = self.queued_files.clone();
+        let crawl = crawl_root_directories(
+            &tracked_directories.root_directories,
+            &self.existing_files_hashset,
+        );
+        for full_path in crawl.files {
+            if queued_files.contains(&full_path) {
+                continue;
+            }
+            if self.existing_files_hashset.contains(&full_path) {
+                plan.already_tracked_count += 1;
+                continue;
+            }
+            load_ignore_files(config, &mut self.ignore_files, &full_path);
+            match check_file(config, &self.ignore_files, &full_path) {
+                Some((Reason::ExtensionDisallowed, _))
+                    if SidecarKind::from_path(&full_path).is_some() =>
+                {
+                    plan.sidecars.push(pathbuf_to_string(&full_path));
+                }
+                Some((reason, matched_rule)) => plan.rejected.push(PlannedRejection {
+                    full_path: pathbuf_to_string(&full_path),
+                    reason,
+                    matched_rule,
+                }),
+                None => new_files.push(full_path),
+            }
+        }
+        for (full_path, reason, matched_rule) in crawl.skipped {
+            plan.rejected.push(PlannedRejection {
+                full_path: pathbuf_to_string(&full_path),
+                reason,
+                matched_rule,
+            });
+        }
+
+        let parsed_files: Vec<(PathBuf, ParsedFile)> = new_files
+            .into_par_iter()
+            .map(|full_path| {
+                let parsed_file =
+                    parse_new_file(tracked_directories, &config.episode_parse_rules, &full_path);
+                (full_path, parsed_file)
+            })
+            .collect();
+
+        let mut new_shows: BTreeSet<String> = BTreeSet::new();
+        let mut planned_movies: Vec<ParsedMovie> = Vec::new();
+        let mut extras: Vec<(PathBuf, Designation, PathBuf)> = Vec::new();
+        for (full_path, parsed_file) in parsed_files {
+            let import = match parsed_file {
+                ParsedFile::Episode(parsed_episode) => PlannedImport::Episode {
+                    show_title: self.plan_show(
+                        get_show_info(tracked_directories, &full_path),
+                        &mut new_shows,
+                    ),
+                    season: parsed_episode.season,
+                    episode_title: get_nfo_episode_title(
+                        &full_path,
+                        parsed_episode.season,
+                        parsed_episode.episodes[0],
+                    )
+                    .or(parsed_episode.title),
+                    episodes: parsed_episode.episodes,
+                    release_source: parsed_episode.release_source,
+                    rule_name: parsed_episode.rule_name,
+                },
+                ParsedFile::Special(parsed_episode) => PlannedImport::Special {
+                    show_title: self.plan_show(
+                        get_show_info(tracked_directories, &full_path),
+                        &mut new_shows,
+                    ),
+                    episode_title: get_nfo_episode_title(
+                        &full_path,
+                        parsed_episode.season,
+                        parsed_episode.episodes[0],
+                    )
+                    .or(parsed_episode.title),
+                    episodes: parsed_episode.episodes,
+                    release_source: parsed_episode.release_source,
+                    rule_name: parsed_episode.rule_name,
+                },
+                ParsedFile::Movie(parsed_movie) => {
+                    planned_movies.push(parsed_movie.clone());
+                    PlannedImport::Movie {
+                        title: parsed_movie.title,
+                        year: parsed_movie.year,
+                    }
+                }
+                //Extras are linked last, the same as when processing
+                ParsedFile::Extra(designation, owner_path) => {
+                    extras.push((full_path, designation, owner_path));
+                    continue;
+                }
+                ParsedFile::Generic => PlannedImport::Generic,
+                ParsedFile::Unmatched => PlannedImport::Unmatched,
+            };
+            plan.accepted.push(PlannedFile {
+                full_path: pathbuf_to_string(&full_path),
+                import,
+            });
+        }
+
+        for (full_path, designation, owner_path) in extras {
+            let library_type = tracked_directories.get_library_type(&owner_path);
+            let mut movie: Option<ParsedMovie> = None;
+            if library_type != LibraryType::Tv {
+                movie = parse_movie(&owner_path).filter(|parsed_movie| {
+                    planned_movies.contains(parsed_movie)
+                        || self.movies.iter().any(|(title, year)| {
+                            *title == parsed_movie.title && *year == parsed_movie.year
+                        })
+                });
+            }
+            let show_title = match (&movie, library_type) {
+                (None, LibraryType::Tv) => Some(self.plan_show(
+                    get_show_info(tracked_directories, &owner_path),
+                    &mut new_shows,
+                )),
+                (None, LibraryType::Mixed) => tracked_directories
+                    .get_root_directory_for_path(&owner_path)
+                    .and_then(|root_directory| root_directory.get_show_title(&owner_path))
+                    .map(|_| get_show_info(tracked_directories, &owner_path))
+                    .filter(|show_info| {
+                        new_shows.contains(&show_info.to_string())
+                            || self.show_exists(&show_info.title, show_info.year)
+                    })
+                    .map(|show_info| show_info.to_string()),
+                _ => None,
+            };
+            plan.accepted.push(PlannedFile {
+                full_path: pathbuf_to_string(&full_path),
+                import: PlannedImport::Extra {
+                    is_trailer: designation == Designation::Trailer,
+                    show_title,
+                    movie_title: movie.as_ref().map(|movie| movie.title.clone()),
+                    movie_year: movie.map(|movie| movie.year),
+                },
+            });
+        }
+
+        plan.new_shows = new_shows.into_iter().collect();
+        plan
+    }
+
+    ///Records the show as one a dry run would create if it doesn't exist yet
+    fn plan_show(&self, show_info: ShowInfo, new_shows: &mut BTreeSet<String>) -> String {
+        let show_title = show_info.to_string();
+        if !self.show_exists(&show_info.title, show_info.year) {
+            new_shows.insert(show_title.clone());
+        }
+        show_title
+    }
+
+    fn show_exists(&self, show_title: &str, show_year: Option<i32>) -> bool {
+        self.shows
+            .iter()
+            .any(|(title, year)| title == show_title && *year == show_year)
+    }
+}
+
+///A copy of the library taken by reconcile, so the root directories can be crawled and missing
+///files found and hashed without holding the FileManager's lock
+pub struct Reconciler {
+    root_directories: Vec<RootDirectory>,
+    existing_files_hashset: HashSet<PathBuf>,
+    file_versions: Vec<FileVersion>,
+}
+
+impl Reconciler {
+    pub fn crawl(&self) -> Crawl {
+        crawl_root_directories(&self.root_directories, &self.existing_files_hashset)
+    }
+
+    ///new_files is the new files queue once the crawl has been imported
+    pub fn reconcile(&self, new_files: &[PathBuf]) -> ReconciliationReport {
+        match_missing_files(&self.file_versions, new_files)
+    }
+}
+
+///Entries found by walking root directories, the paths of each hardlinked file are held back until
+///it's known which of them is already in the library
+#[derive(Default)]
+pub struct Crawl {
+    pub files: Vec<PathBuf>,
+    //(path, reason, matched rule)
+    pub skipped: Vec<(PathBuf, Reason, Option<String>)>,
+    hardlinks: HashMap<FileId, Vec<PathBuf>>,
+}
+
+impl Crawl {
+    ///Walks a directory in a root directory with the root directory's symlink, file system and
+    ///hardlink options, the directory is the root directory itself unless only part of it changed
+    pub fn walk(&mut self, root_directory: &RootDirectory, directory: &Path) {
+        let root_device = if root_directory.same_file_system {
+            get_device(&root_directory.path)
+        } else {
+            None
+        };
+        //Directories on another file system are still yielded, marked by their client state,
+        //but their contents aren't read
+        let walk_dir = WalkDirGeneric::<((), bool)>::new(directory)
+            .follow_links(root_directory.follow_symlinks)
+            .process_read_dir(move |_, _, _, children| {
+                if let Some(root_device) = root_device {
+                    for dir_entry in children.iter_mut().flatten() {
+                        if dir_entry.file_type.is_dir()
+                            && get_device(&dir_entry.path()) != Some(root_device)
+                        {
+                            dir_entry.read_children_path = None;
+                            dir_entry.client_state = true;
+                        }
+                    }
+                }
+            });
+
+        for entry in walk_dir {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    match (err.path(), err.loop_ancestor()) {
+                        (Some(full_path), Some(ancestor)) => self.skipped.push((
+                            full_path.to_path_buf(),
+                            Reason::SymlinkLoop,
+                            Some(pathbuf_to_string(ancestor)),
+                        )),
+                        _ => warn!("Failed to read entry while crawling. Err: {}", err),
+                    }
+                    continue;
+                }
+            };
+            let full_path = entry.path();
+            if entry.client_state {
+                self.skipped.push((full_path, Reason::MountBoundary, None));
+                continue;
+            }
+            //The root directory itself is always followed
+            if full_path != root_directory.path
+                && entry.path_is_symlink()
+                && !root_directory.follow_symlinks
+            {
+                self.skipped.push((full_path, Reason::Symlink, None));
+                continue;
+            }
+            //.tlmignore files are read when the files they apply to are checked
+            if !entry.file_type().is_file() || entry.file_name == IGNORE_FILE_NAME {
+                continue;
+            }
+
+            if root_directory.deduplicate_hardlinks {
+                if let Some(file_id) = entry
+                    .metadata()
+                    .ok()
+                    .and_then(|metadata| get_file_id(&metadata))
+                {
+                    self.hardlinks.entry(file_id).or_default().push(full_path);
+                    continue;
+                }
+            }
+            self.files.push(full_path);
+        }
+    }
+
+    ///Keeps one path of each hardlinked file, preferring a path that is already in the library so
+    ///nothing tracked gets replaced
+    pub fn deduplicate_hardlinks(&mut self, existing_files_hashset: &HashSet<PathBuf>) {
+        for (_, mut full_paths) in self.hardlinks.drain() {
+            full_paths.sort();
+            let kept_index = full_paths
+                .iter()
+                .position(|full_path| existing_files_hashset.contains(full_path))
+                .unwrap_or(0);
+            let kept_path = full_paths.remove(kept_index);
+            for full_path in full_paths {
+                if !existing_files_hashset.contains(&full_path) {
+                    self.skipped.push((
+                        full_path,
+                        Reason::Hardlink,
+                        Some(pathbuf_to_string(&kept_path)),
+                    ));
+                }
+            }
+            self.files.push(kept_path);
+        }
+    }
+}
+
+///Returns the reason a file should be rejected and the rule that matched, or none if the file is allowed
+fn check_file(
+    config: &ServerConfig,
+    ignore_files: &HashMap<PathBuf, Option<IgnoreRules>>,
+    full_path: &Path,
+) -> Option<(Reason, Option<String>)> {
+    let root_directory = config
+        .tracked_directories
+        .get_root_directory_for_path(full_path);
+
+    //rejects if the path is ignored by the config, the root directory or a .tlmignore file,
+    //the last matching rule wins so later rules can re-include a path
+    let base = root_directory.map_or(Path::new(""), |root_directory| &root_directory.path);
+    let mut ignore_rules: Vec<(&Path, &IgnoreRules)> = vec![(base, &config.ignore_rules)];
+    if let Some(root_directory) = root_directory {
+        ignore_rules.push((base, &root_directory.ignore_rules));
+        for directory in get_ignore_file_directories(base, full_path) {
+            if let Some((directory, Some(rules))) = ignore_files.get_key_value(&directory) {
+                ignore_rules.push((directory, rules));
+            }
+        }
+    }
+    if let Some(matched_rule) = check_ignore_rules(ignore_rules, full_path) {
+        return Some((Reason::PathContainsIgnoredPath, Some(matched_rule)));
+    }
+
+    //rejects if the path doesn't have an extension
+    if full_path.extension().is_none() {
+        return Some((Reason::ExtensionMissing, None));
+    }
+
+    //rejects if the file doesn't have an allowed extension, the root directory's list replaces the config's
+    let allowed_extensions = root_directory
+        .and_then(|root_directory| root_directory.allowed_extensions.as_ref())
+        .unwrap_or(&config.allowed_extensions);
+    let extension = get_extension(full_path).to_lowercase();
+    if !allowed_extensions.contains(&extension) {
+        return Some((Reason::ExtensionDisallowed, Some(extension)));
+    }
+    None
+}
+
+///Reads any .tlmignore files between the file's root directory and the file that haven't been
+///read since the last crawl
+fn load_ignore_files(
+    config: &ServerConfig,
+    ignore_files: &mut HashMap<PathBuf, Option<IgnoreRules>>,
+    full_path: &Path,
+) {
+    let directories = match config
+        .tracked_directories
+        .get_root_directory_for_path(full_path)
+    {
+        Some(root_directory) => get_ignore_file_directories(&root_directory.path, full_path),
+        None => return,
+    };
+    for directory in directories {
+        ignore_files
+            .entry(directory)
+            .or_insert_with_key(|directory| {
+                IgnoreRules::from_ignore_file(&directory.join(IGNORE_FILE_NAME))
+            });
+    }
+}
+
+///Walks every root directory with its symlink, file system and hardlink options
+fn crawl_root_directories(
+    root_directories: &[RootDirectory],
+    existing_files_hashset: &HashSet<PathBuf>,
+) -> Crawl {
+    let mut crawl = Crawl::default();
+    for root_directory in root_directories {
+        crawl.walk(root_directory, &root_directory.path);
+    }
+    crawl.deduplicate_hardlinks(existing_files_hashset);
+    crawl
+}
+
+///Works out what a new file is, the root directory's library type decides what the file can be
+fn parse_new_file(
+    tracked_directories: &TrackedDirectories,
+    episode_parse_rules: &[EpisodeParseRule],
+    full_path: &Path,
+) -> ParsedFile {
+    let as_episode =
+        || parse_episode(episode_parse_rules, &get_file_stem(full_path)).map(ParsedFile::Episode);
+    let as_movie = || parse_movie(full_path).map(ParsedFile::Movie);
+    //Specials that can't be parsed as an episode are kept as extras of the show
+    let root_path = tracked_directories
+        .get_root_directory_for_path(full_path)
+        .map(|root_directory| root_directory.path.clone())
+        .unwrap_or_default();
+    let as_extra = || match parse_extra(full_path, &root_path)? {
+        (Designation::Special, owner_path) => match as_episode() {
+            Some(ParsedFile::Episode(mut parsed_episode)) => {
+                parsed_episode.season = 0;
+                Some(ParsedFile::Special(parsed_episode))
+            }
+            _ => Some(ParsedFile::Extra(Designation::Extra, owner_path)),
+        },
+        (designation, owner_path) => Some(ParsedFile::Extra(designation, owner_path)),
+    };
+    match tracked_directories.get_library_type(full_path) {
+        LibraryType::Tv => as_extra().or_else(as_episode),
+        LibraryType::Movies => as_extra().or_else(as_movie),
+        LibraryType::Generic => Some(ParsedFile::Generic),
+        LibraryType::Mixed => as_extra().or_else(as_episode).or_else(as_movie),
+    }
+    .unwrap_or(ParsedFile::Unmatched)
+}
+
+impl ParsedFile {
+    fn designation(&self) -> Designation {
+        match self {
+            Self::Episode(_) => Designation::Episode,
+            Self::Special(_) => Designation::Special,
+            Self::Movie(_) => Designation::Movie,
+            Self::Extra(designation, _) => *designation,
+            Self::Generic | Self::Unmatched => Designation::Generic,
+        }
+    }
+}
+
+///Keeps the time a file was first rejected if it is still rejected by the same rule
+fn keep_rejection_time(rejected_file: &mut PathBufReason, previous: Option<&PathBufReason>) {
+    if let Some(previous) = previous {
+        if previous.reason == rejected_file.reason
+            && previous.matched_rule == rejected_file.matched_rule
+        {
+            rejected_file.rejected_at = previous.rejected_at;
+        }
+    }
+}
@@ -4,15 +4,26 @@
 use crate::database::create_file_version;
 use {
     crate::{
-        database::update_file_version,
+        database::{replace_streams, update_file_version},
         designation::{from_i32, Designation},
         get_file_name,
         model::*,
         pathbuf_to_string,
-        profile::{Container, Profile, ResolutionStandard},
+        prober::MediaProber,
+        profile::{Container, DynamicRange, ResolutionStandard},
+        sidecar::Sidecar,
+        stream::{Stream, StreamType},
     },
     diesel::PgConnection,
-    std::{fmt, fs::File, hash::Hasher, io::prelude::*, path::Path, path::PathBuf},
+    std::{
+        fmt,
+        fs::File,
+        hash::Hasher,
+        io::{self, prelude::*, ErrorKind, SeekFrom},
+        path::Path,
+        path::PathBuf,
+        time::SystemTime,
+    },
     tracing::{error, warn},
 };
 
@@ -30,6 +41,15 @@ pub struct FileVersion {
     pub length_time: Option<f64>,
     pub resolution_standard: Option<ResolutionStandard>,
     pub container: Option<Container>,
+    //The file couldn't be found on disk the last time the library was reconciled
+    pub missing: bool,
+    //The algorithm the hashes were made with, see CURRENT_HASH_VERSION
+    pub hash_version: i32,
+    //The file didn't match its hash the last time it was verified, it's never encoded from
+    pub corrupted: bool,
+    pub verified_at: Option<SystemTime>,
+    //Every video, audio and subtitle stream, empty until the file has been profiled
+    pub streams: Vec<Stream>,
 }
 
 impl FileVersion {
@@ -53,6 +73,11 @@ impl FileVersion {
                 model.resolution_standard,
             ),
             container: Container::from_wrapped(model.container),
+            missing: model.missing,
+            hash_version: model.hash_version,
+            corrupted: model.corrupted,
+            verified_at: model.verified_at,
+            streams: Vec::new(),
         }
     }
 
@@ -72,6 +97,11 @@ impl FileVersion {
                 model_ref.resolution_standard,
             ),
             container: Container::from_wrapped(model_ref.container),
+            missing: model_ref.missing,
+            hash_version: model_ref.hash_version,
+            corrupted: model_ref.corrupted,
+            verified_at: model_ref.verified_at,
+            streams: Vec::new(),
         }
     }
 
@@ -86,58 +116,114 @@ impl FileVersion {
             || self.length_time.is_none()
             || self.resolution_standard.is_none()
             || self.container.is_none()
+            || self.get_video_stream().is_none()
+    }
+
+    ///The first video stream, cover art isn't stored so this is the main video
+    pub fn get_video_stream(&self) -> Option<&Stream> {
+        self.streams
+            .iter()
+            .find(|stream| stream.stream_type == StreamType::Video)
+    }
+
+    ///Files that haven't been profiled yet are assumed to be SDR, they're never used as the source
+    ///of an encode so this can't wash out an HDR source
+    pub fn get_dynamic_range(&self) -> DynamicRange {
+        self.get_video_stream()
+            .and_then(Stream::get_dynamic_range)
+            .unwrap_or(DynamicRange::SDR)
     }
 
-    //TODO: Add reporting
     //Destructive operation, will overwrite previous values
-    pub fn generate_profile_if_none(&mut self, connection: &PgConnection) {
+    pub fn generate_profile_if_none(
+        &mut self,
+        connection: &PgConnection,
+        prober: &dyn MediaProber,
+    ) {
         if self.profile_is_none() {
-            self.generate_profile(connection);
+            self.generate_profile(connection, prober);
         }
     }
 
     //Destructive operation, will overwrite previous values
-    pub fn generate_profile(&mut self, connection: &PgConnection) {
-        if let Some(profile) = Profile::from_file(&self.full_path) {
-            self.width = profile.width;
-            self.height = profile.height;
-            self.framerate = profile.framerate;
-            self.length_time = profile.length_time;
-            self.resolution_standard = profile.resolution_standard;
-            self.container = profile.container;
-            self.update_file_version(connection);
+    pub fn generate_profile(&mut self, connection: &PgConnection, prober: &dyn MediaProber) {
+        match prober.probe(&self.full_path) {
+            Ok(profile) => {
+                self.width = profile.width;
+                self.height = profile.height;
+                self.framerate = profile.framerate;
+                self.length_time = profile.length_time;
+                self.resolution_standard = profile.resolution_standard;
+                self.container = profile.container;
+                self.update_file_version(connection);
+                replace_streams(connection, self.id, &profile.streams);
+                self.streams = profile.streams;
+            }
+            Err(err) => {
+                warn!(
+                    "Failed to profile file: {}. Err: {}",
+                    pathbuf_to_string(&self.full_path),
+                    err
+                );
+            }
         }
     }
 
     ///Hash the file with seahash for data integrity purposes so we
     /// know if a file has been replaced and may need to be reprocessed
-    pub fn hash(&mut self) {
-        self.hash = Some(hash(&self.full_path));
+    pub fn hash(&mut self) -> io::Result<()> {
+        self.upgrade_hash_version();
+        self.hash = Some(hash(&self.full_path)?);
+        Ok(())
+    }
+
+    ///Hashes made with an older algorithm can't be mixed with new ones, so both are cleared to
+    ///be remade once either is
+    pub fn upgrade_hash_version(&mut self) {
+        if self.hash_version != CURRENT_HASH_VERSION {
+            self.hash = None;
+            self.fast_hash = None;
+            self.hash_version = CURRENT_HASH_VERSION;
+        }
+    }
+
+    ///Has a hash made with an algorithm older than CURRENT_HASH_VERSION
+    pub fn has_outdated_hash(&self) -> bool {
+        self.hash_version != CURRENT_HASH_VERSION
+            && (self.hash.is_some() || self.fast_hash.is_some())
     }
 
     pub fn verify_own_hash(&mut self) -> bool {
-        if self.fast_hash.is_some() {
-            return self.fast_hash.as_ref().unwrap().as_str() == hash(&self.full_path).as_str();
-        } else {
-            warn!("This file has nothing to verify it's hash against. Hashing file instead, this will become the hash and will return true.");
-            self.hash();
-            true
+        match &self.hash {
+            Some(own_hash) => is_same_hash(
+                own_hash,
+                hash_with_version(&self.full_path, self.hash_version),
+                &self.full_path,
+            ),
+            None => {
+                warn!("This file has nothing to verify it's hash against. Hashing file instead, this will become the hash and will return true.");
+                log_hash_error(self.hash(), &self.full_path)
+            }
         }
     }
 
-    ///Returns true if hashes match, false if not
+    ///Returns true if hashes match, false if not or if either file can't be read
     pub fn verify_hash_against(&mut self, path: &Path) -> bool {
-        //Note: This could loop infinitely if self.hash isn't set
-        if self.hash.is_some() {
-            return self.hash.as_ref().unwrap().as_str() == hash(path).as_str();
-        } else {
+        if self.hash.is_none() {
             warn!("Fast hash verification was run on a file without a hash. Hashing file instead, then comparing.");
-            self.hash();
-            self.verify_hash_against(path)
+            if !log_hash_error(self.hash(), &self.full_path) {
+                return false;
+            }
+        }
+        match &self.hash {
+            Some(own_hash) => {
+                is_same_hash(own_hash, hash_with_version(path, self.hash_version), path)
+            }
+            None => false,
         }
     }
 
-    ///Hash the first 32MB of the file with seahash so we can quickly know
+    ///Hash the head, middle and tail of the file with seahash so we can quickly know
     ///if a file is likely to have changed or is likely to be the same as
     ///an existing file.
     ///
@@ -145,29 +231,41 @@ impl FileVersion {
     ///renamed to something that doesn't make sense we can quickly search for
     ///files that tlm knows about to restore by calculating the fast hash and
     ///then calculating full hashes of matching hashes to save time
-    pub fn fast_hash(&mut self) {
-        self.fast_hash = Some(fast_hash(&self.full_path));
+    pub fn fast_hash(&mut self) -> io::Result<()> {
+        self.upgrade_hash_version();
+        self.fast_hash = Some(fast_hash(&self.full_path)?);
+        Ok(())
     }
 
     pub fn verify_own_fast_hash(&mut self) -> bool {
-        if self.fast_hash.is_some() {
-            return self.fast_hash.as_ref().unwrap().as_str()
-                == fast_hash(&self.full_path).as_str();
-        } else {
-            warn!("This file has nothing to verify it's hash against. Hashing file instead, this will become the hash and will return true.");
-            self.fast_hash();
-            true
+        match &self.fast_hash {
+            Some(own_fast_hash) => is_same_hash(
+                own_fast_hash,
+                fast_hash_with_version(&self.full_path, self.hash_version),
+                &self.full_path,
+            ),
+            None => {
+                warn!("This file has nothing to verify it's hash against. Hashing file instead, this will become the hash and will return true.");
+                log_hash_error(self.fast_hash(), &self.full_path)
+            }
         }
     }
 
-    ///Returns true if hashes match, false if not
+    ///Returns true if hashes match, false if not or if either file can't be read
     pub fn verify_fast_hash_against(&mut self, path: &Path) -> bool {
-        if self.fast_hash.is_some() {
-            return self.fast_hash.as_ref().unwrap().as_str() == fast_hash(path).as_str();
-        } else {
+        if self.fast_hash.is_none() {
             warn!("Fast hash verification was run on a file without a hash. Hashing file instead, then comparing.");
-            self.fast_hash();
-            self.verify_fast_hash_against(path)
+            if !log_hash_error(self.fast_hash(), &self.full_path) {
+                return false;
+            }
+        }
+        match &self.fast_hash {
+            Some(own_fast_hash) => is_same_hash(
+                own_fast_hash,
+                fast_hash_with_version(path, self.hash_version),
+                path,
+            ),
+            None => false,
         }
     }
 
@@ -187,6 +285,8 @@ pub struct Generic {
     pub generic_uid: Option<i32>,
     pub designation: Designation,
     pub file_versions: Vec<FileVersion>,
+    //Subtitles, nfo files and artwork that belong to this generic
+    pub sidecars: Vec<Sidecar>,
 }
 
 impl Generic {
@@ -195,12 +295,17 @@ impl Generic {
             generic_uid: None,
             designation: Designation::Generic,
             file_versions: Vec::new(),
+            sidecars: Vec::new(),
         }
     }
 
-    pub fn generate_file_version_profiles_if_none(&mut self, connection: &PgConnection) {
+    pub fn generate_file_version_profiles_if_none(
+        &mut self,
+        connection: &PgConnection,
+        prober: &dyn MediaProber,
+    ) {
         for file_version in self.file_versions.iter_mut() {
-            file_version.generate_profile_if_none(connection)
+            file_version.generate_profile_if_none(connection, prober)
         }
     }
 
@@ -256,6 +361,7 @@ impl Generic {
             generic_uid: Some(generic_model.generic_uid),
             designation: from_i32(generic_model.designation),
             file_versions: Vec::new(),
+            sidecars: Vec::new(),
         }
     }
 
@@ -268,44 +374,157 @@ impl Generic {
         }
     }
 }
+
+///Hashes made before hash_version was stored. The whole 4096 byte buffer was hashed even after a
+///short read and the fast hash only covered the first 32MB
+pub const LEGACY_HASH_VERSION: i32 = 1;
+///Streams exactly the bytes read and the fast hash samples the head, middle and tail of the file
+pub const CURRENT_HASH_VERSION: i32 = 2;
+
+//Bytes read from each of the head, middle and tail of a file for the fast hash
+const FAST_HASH_SAMPLE_SIZE: u64 = 4 * 1024 * 1024;
+
 ///Hash the file with seahash for data integrity purposes so we
 /// know if a file has been replaced and may need to be reprocessed
-pub fn hash(full_path: &Path) -> String {
-    let mut buffer = Box::new(vec![0; 4096]);
-    let mut hasher = seahash::SeaHasher::new();
-    let mut file = File::open(pathbuf_to_string(full_path)).unwrap_or_else(|err| {
-        error!("Error opening file for hashing. Err: {}", err);
-        panic!();
-    });
-    while file.read(&mut buffer).unwrap() != 0 {
-        hasher.write(&buffer);
-    }
-    hasher.finish().to_string()
+pub fn hash(full_path: &Path) -> io::Result<String> {
+    hash_reader(File::open(full_path)?)
 }
 
-///Hash the first 32MB of the file with seahash so we can quickly know
-///if a file is likely to have changed or is likely to be the same as
-///an existing file.
+///Hashes the head, middle and tail of the file along with its size with seahash so we can
+///quickly know if a file is likely to have changed or is likely to be the same as an existing
+///file. Files smaller than the three samples are hashed whole.
 ///
 ///For example if we backup all of tlm's information and all files get
 ///renamed to something that doesn't make sense we can quickly search for
 ///files that tlm knows about to restore by calculating the fast hash and
 ///then calculating full hashes of matching hashes to save time
-pub fn fast_hash(full_path: &Path) -> String {
+pub fn fast_hash(full_path: &Path) -> io::Result<String> {
+    let file = File::open(full_path)?;
+    let size = file.metadata()?.len();
+    fast_hash_reader(file, size)
+}
+
+///Hashes a file with the algorithm a stored hash was made with so the two can be compared
+pub fn hash_with_version(full_path: &Path, hash_version: i32) -> io::Result<String> {
+    match hash_version {
+        LEGACY_HASH_VERSION => legacy_hash(full_path),
+        _ => hash(full_path),
+    }
+}
+
+pub fn fast_hash_with_version(full_path: &Path, hash_version: i32) -> io::Result<String> {
+    match hash_version {
+        LEGACY_HASH_VERSION => legacy_fast_hash(full_path),
+        _ => fast_hash(full_path),
+    }
+}
+
+///Hashes a reader with the algorithm a stored hash was made with
+pub(crate) fn hash_reader_with_version(reader: impl Read, hash_version: i32) -> io::Result<String> {
+    match hash_version {
+        LEGACY_HASH_VERSION => legacy_hash_reader(reader),
+        _ => hash_reader(reader),
+    }
+}
+
+pub(crate) fn hash_reader(mut reader: impl Read) -> io::Result<String> {
+    let mut hasher = seahash::SeaHasher::new();
+    hash_into(&mut reader, &mut hasher, u64::MAX)?;
+    Ok(hasher.finish().to_string())
+}
+
+pub(crate) fn fast_hash_reader(mut reader: impl Read + Seek, size: u64) -> io::Result<String> {
+    let mut hasher = seahash::SeaHasher::new();
+    hasher.write_u64(size);
+    if size <= FAST_HASH_SAMPLE_SIZE * 3 {
+        hash_into(&mut reader, &mut hasher, u64::MAX)?;
+    } else {
+        for offset in [
+            0,
+            size / 2 - FAST_HASH_SAMPLE_SIZE / 2,
+            size - FAST_HASH_SAMPLE_SIZE,
+        ] {
+            reader.seek(SeekFrom::Start(offset))?;
+            hash_into(&mut reader, &mut hasher, FAST_HASH_SAMPLE_SIZE)?;
+        }
+    }
+    Ok(hasher.finish().to_string())
+}
+
+///Feeds up to limit bytes to the hasher, only ever hashing the bytes that were actually read
+fn hash_into(reader: &mut impl Read, hasher: &mut impl Hasher, limit: u64) -> io::Result<()> {
+    let mut buffer = vec![0; 64 * 1024];
+    let mut remaining = limit;
+    while remaining > 0 {
+        let length = buffer
+            .len()
+            .min(usize::try_from(remaining).unwrap_or(usize::MAX));
+        match reader.read(&mut buffer[..length]) {
+            Ok(0) => break,
+            Ok(read) => {
+                hasher.write(&buffer[..read]);
+                remaining -= read as u64;
+            }
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+///Compares a stored hash with one that was just made, a file that couldn't be read never matches
+fn is_same_hash(stored_hash: &str, hash: io::Result<String>, full_path: &Path) -> bool {
+    match hash {
+        Ok(hash) => stored_hash == hash,
+        Err(err) => {
+            warn!(
+                "Failed to hash file: {}. Err: {}",
+                pathbuf_to_string(full_path),
+                err
+            );
+            false
+        }
+    }
+}
+
+///Logs the error if hashing failed, returns true if it succeeded
+fn log_hash_error(result: io::Result<()>, full_path: &Path) -> bool {
+    if let Err(err) = &result {
+        warn!(
+            "Failed to hash file: {}. Err: {}",
+            pathbuf_to_string(full_path),
+            err
+        );
+    }
+    result.is_ok()
+}
+
+//Kept so hashes from LEGACY_HASH_VERSION can still be compared until they're rehashed
+fn legacy_hash(full_path: &Path) -> io::Result<String> {
+    legacy_hash_reader(File::open(full_path)?)
+}
+
+fn legacy_hash_reader(mut reader: impl Read) -> io::Result<String> {
+    let mut buffer = Box::new(vec![0; 4096]);
+    let mut hasher = seahash::SeaHasher::new();
+    while reader.read(&mut buffer)? != 0 {
+        hasher.write(&buffer);
+    }
+    Ok(hasher.finish().to_string())
+}
+
+fn legacy_fast_hash(full_path: &Path) -> io::Result<String> {
     let mut buffer = Box::new(vec![0; 4096]);
     let mut hasher = seahash::SeaHasher::default();
-    let mut file = File::open(pathbuf_to_string(full_path)).unwrap_or_else(|err| {
-        error!("Error opening file for hashing. Err: {}", err);
-        panic!();
-    });
+    let mut file = File::open(full_path)?;
     for _ in 0..8192 {
-        if file.read(&mut buffer).unwrap() != 0 {
+        if file.read(&mut buffer)? != 0 {
             hasher.write(&buffer);
         } else {
             break;
         }
     }
-    hasher.finish().to_string()
+    Ok(hasher.finish().to_string())
 }
 
 impl fmt::Display for Generic {
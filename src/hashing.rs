@@ -0,0 +1,363 @@
+//!Hashes FileVersions on a bounded pool of threads, limiting how many files are read at once from
+//!each device so several disks can be read in parallel without thrashing any one of them.
+//!Progress is stored in the database so a run stopped by a restart carries on where it stopped
+use {
+    crate::{
+        database::{
+            create_hash_run, establish_connection, get_unfinished_hash_run,
+            update_file_version_hashes, update_hash_run,
+        },
+        file_manager::FileManager,
+        generic::FileVersion,
+        model::NewHashRun,
+        pathbuf_to_string,
+    },
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{HashMap, VecDeque},
+        fs::{self, Metadata},
+        io,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc, Arc, Condvar, Mutex, RwLock,
+        },
+        thread,
+        time::{Duration, Instant},
+    },
+    tracing::{debug, info, warn},
+};
+
+//How many hashed FileVersions are collected before they're updated in the FileManager, so that
+//its lock isn't taken for every file
+const FILE_MANAGER_BATCH_SIZE: usize = 32;
+
+const PROGRESS_LOG_INTERVAL: Duration = Duration::from_secs(30);
+
+///A FileVersion that is missing its hash or fast hash, or has hashes from an older algorithm
+#[derive(Clone, Debug)]
+pub struct HashJob {
+    pub file_version: FileVersion,
+    //Bytes
+    pub size: u64,
+    //Files on the same device share its concurrency limit
+    pub device: u64,
+}
+
+impl HashJob {
+    ///None if the file can't be read, such as when it's missing
+    pub fn new(file_version: FileVersion) -> Option<Self> {
+        match fs::metadata(&file_version.full_path) {
+            Ok(metadata) => Some(Self {
+                size: metadata.len(),
+                device: get_device_id(&metadata),
+                file_version,
+            }),
+            Err(err) => {
+                warn!(
+                    "Skipping hashing file that can't be read: {}. Err: {}",
+                    pathbuf_to_string(&file_version.full_path),
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    fn run(&mut self) -> io::Result<()> {
+        self.file_version.upgrade_hash_version();
+        if self.file_version.hash.is_none() {
+            self.file_version.hash()?;
+        }
+        if self.file_version.fast_hash.is_none() {
+            self.file_version.fast_hash()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn get_device_id(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    metadata.dev()
+}
+
+//Every file is treated as being on the same device where device ids aren't available
+#[cfg(not(unix))]
+fn get_device_id(_metadata: &Metadata) -> u64 {
+    0
+}
+
+///Progress of the current or last hashing run, counts include files hashed before the run was
+///resumed
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct HashProgress {
+    pub is_running: bool,
+    pub total_files: usize,
+    pub total_bytes: u64,
+    pub hashed_files: usize,
+    pub hashed_bytes: u64,
+    //Since the run was started or resumed
+    pub bytes_per_second: u64,
+    //Estimated seconds until every file is hashed
+    pub eta_seconds: Option<u64>,
+    #[serde(skip)]
+    started_at: Option<Instant>,
+    #[serde(skip)]
+    bytes_at_start: u64,
+}
+
+impl HashProgress {
+    pub fn start(
+        &mut self,
+        total_files: usize,
+        total_bytes: u64,
+        hashed_files: usize,
+        hashed_bytes: u64,
+    ) {
+        *self = Self {
+            is_running: true,
+            total_files,
+            total_bytes,
+            hashed_files,
+            hashed_bytes,
+            bytes_per_second: 0,
+            eta_seconds: None,
+            started_at: Some(Instant::now()),
+            bytes_at_start: hashed_bytes,
+        };
+    }
+
+    pub fn add_hashed(&mut self, bytes: u64) {
+        self.hashed_files += 1;
+        self.hashed_bytes += bytes;
+        if let Some(started_at) = self.started_at {
+            let elapsed = started_at.elapsed().as_secs_f64();
+            if elapsed > 0.0 {
+                self.bytes_per_second =
+                    ((self.hashed_bytes - self.bytes_at_start) as f64 / elapsed) as u64;
+            }
+        }
+        self.eta_seconds = match self.bytes_per_second {
+            0 => None,
+            bytes_per_second => {
+                Some(self.total_bytes.saturating_sub(self.hashed_bytes) / bytes_per_second)
+            }
+        };
+    }
+
+    pub fn print(&self) {
+        info!(
+            "Hashed {} of {} files ({} of {} MB) at {} MB/s, ETA: {}",
+            self.hashed_files,
+            self.total_files,
+            self.hashed_bytes / 1_000_000,
+            self.total_bytes / 1_000_000,
+            self.bytes_per_second / 1_000_000,
+            match self.eta_seconds {
+                Some(eta_seconds) => format!("{}s", eta_seconds),
+                None => "unknown".to_string(),
+            }
+        );
+    }
+}
+
+struct QueueState {
+    jobs: VecDeque<HashJob>,
+    active_per_device: HashMap<u64, usize>,
+}
+
+///Hands out jobs to the hashing threads, skipping jobs on devices that are already being read
+///by as many threads as they're allowed
+pub(crate) struct HashQueue {
+    state: Mutex<QueueState>,
+    condvar: Condvar,
+    threads_per_device: usize,
+}
+
+impl HashQueue {
+    pub(crate) fn new(jobs: Vec<HashJob>, threads_per_device: usize) -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                jobs: jobs.into(),
+                active_per_device: HashMap::new(),
+            }),
+            condvar: Condvar::new(),
+            threads_per_device: threads_per_device.max(1),
+        }
+    }
+
+    ///Blocks until a job on a device with a free slot is available, none once the queue is
+    ///empty or the run is being stopped
+    pub(crate) fn next_job(&self, stop: &AtomicBool) -> Option<HashJob> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if stop.load(Ordering::Relaxed) || state.jobs.is_empty() {
+                return None;
+            }
+            let state_ref = &mut *state;
+            let available = state_ref.jobs.iter().position(|job| {
+                state_ref
+                    .active_per_device
+                    .get(&job.device)
+                    .map_or(true, |active| *active < self.threads_per_device)
+            });
+            if let Some(index) = available {
+                let job = state_ref.jobs.remove(index).unwrap();
+                *state_ref.active_per_device.entry(job.device).or_default() += 1;
+                return Some(job);
+            }
+            //Times out so the stop flag is still checked while every device is busy
+            state = self
+                .condvar
+                .wait_timeout(state, Duration::from_millis(500))
+                .unwrap()
+                .0;
+        }
+    }
+
+    pub(crate) fn finish_job(&self, device: u64) {
+        if let Some(active) = self
+            .state
+            .lock()
+            .unwrap()
+            .active_per_device
+            .get_mut(&device)
+        {
+            *active -= 1;
+        }
+        self.condvar.notify_all();
+    }
+}
+
+///Hashes every job, resuming the last unfinished run in the database if there is one.
+///rehash_outdated is stored with the run so it is resumed with the same jobs.
+///Returns false if the run was stopped before every file was hashed
+pub fn hash_file_versions(
+    jobs: Vec<HashJob>,
+    rehash_outdated: bool,
+    thread_count: usize,
+    threads_per_device: usize,
+    file_manager: Arc<Mutex<FileManager>>,
+    hash_progress: Arc<RwLock<HashProgress>>,
+    stop: Arc<AtomicBool>,
+) -> bool {
+    let connection = establish_connection();
+    let remaining_files = jobs.len();
+    let remaining_bytes: u64 = jobs.iter().map(|job| job.size).sum();
+
+    let (hash_run_id, hashed_files, hashed_bytes) = match get_unfinished_hash_run(&connection) {
+        Some(hash_run) => {
+            info!(
+                "Resuming hashing run started with {} files, {} were already hashed",
+                hash_run.total_files, hash_run.hashed_files
+            );
+            (
+                hash_run.id,
+                hash_run.hashed_files as usize,
+                hash_run.hashed_bytes as u64,
+            )
+        }
+        None => {
+            let hash_run = create_hash_run(
+                &connection,
+                NewHashRun {
+                    total_files: remaining_files as i32,
+                    total_bytes: remaining_bytes as i64,
+                    rehash_outdated,
+                },
+            );
+            (hash_run.id, 0, 0)
+        }
+    };
+    hash_progress.write().unwrap().start(
+        hashed_files + remaining_files,
+        hashed_bytes + remaining_bytes,
+        hashed_files,
+        hashed_bytes,
+    );
+
+    let queue = HashQueue::new(jobs, threads_per_device);
+    let (sender, receiver) = mpsc::channel::<HashJob>();
+    thread::scope(|scope| {
+        for _ in 0..thread_count.max(1) {
+            let sender = sender.clone();
+            let queue = &queue;
+            let stop = &stop;
+            scope.spawn(move || {
+                while let Some(mut job) = queue.next_job(stop) {
+                    let result = job.run();
+                    queue.finish_job(job.device);
+                    //Files that can't be read are left unhashed to be tried again next run
+                    if let Err(err) = result {
+                        warn!(
+                            "Failed to hash file: {}. Err: {}",
+                            pathbuf_to_string(&job.file_version.full_path),
+                            err
+                        );
+                        continue;
+                    }
+                    if sender.send(job).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        //Only the threads hold senders now, so the receiver finishes once they're all done
+        drop(sender);
+
+        //Database and FileManager updates are made from this thread so the hashing threads
+        //only ever wait on the disk
+        let mut hashed_file_versions: Vec<FileVersion> = Vec::new();
+        let mut last_log = Instant::now();
+        loop {
+            let job = match receiver.recv_timeout(Duration::from_secs(1)) {
+                Ok(job) => Some(job),
+                Err(mpsc::RecvTimeoutError::Timeout) => None,
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            };
+            //Pending updates are also flushed whenever no file finished in the last second
+            let timed_out = job.is_none();
+            if let Some(job) = job {
+                debug!("Hashed: {}", pathbuf_to_string(&job.file_version.full_path));
+                update_file_version_hashes(&connection, &job.file_version);
+                let mut hash_progress = hash_progress.write().unwrap();
+                hash_progress.add_hashed(job.size);
+                update_hash_run(&connection, hash_run_id, &hash_progress, false);
+                hashed_file_versions.push(job.file_version);
+            }
+            if hashed_file_versions.len() >= FILE_MANAGER_BATCH_SIZE
+                || (timed_out && !hashed_file_versions.is_empty())
+            {
+                file_manager
+                    .lock()
+                    .unwrap()
+                    .update_hashes(&hashed_file_versions);
+                hashed_file_versions.clear();
+            }
+            if last_log.elapsed() >= PROGRESS_LOG_INTERVAL {
+                hash_progress.read().unwrap().print();
+                last_log = Instant::now();
+            }
+        }
+        if !hashed_file_versions.is_empty() {
+            file_manager
+                .lock()
+                .unwrap()
+                .update_hashes(&hashed_file_versions);
+        }
+    });
+
+    let did_finish = queue.state.lock().unwrap().jobs.is_empty();
+    let mut hash_progress = hash_progress.write().unwrap();
+    hash_progress.is_running = false;
+    //Stopped runs are left unfinished so they're resumed when the server starts again
+    update_hash_run(&connection, hash_run_id, &hash_progress, did_finish);
+    hash_progress.print();
+    did_finish
+}
+
+///Whether a hashing run was stopped before it finished, such as by the server shutting down,
+///and if so whether it was rehashing outdated hashes
+pub fn get_interrupted_hash_run() -> Option<bool> {
+    get_unfinished_hash_run(&establish_connection()).map(|hash_run| hash_run.rehash_outdated)
+}
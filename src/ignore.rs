@@ -0,0 +1,242 @@
+//!Gitignore-style rules for paths that shouldn't be imported, from the config, root directories
+//!and .tlmignore files inside the media tree
+use {
+    crate::pathbuf_to_string,
+    fancy_regex::Regex,
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
+    tracing::{error, warn},
+};
+
+///Files with this name apply their rules to the directory they're in and everything under it
+pub const IGNORE_FILE_NAME: &str = ".tlmignore";
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    //Matched against the path relative to the rule's base directory and each of its parent
+    //directories
+    Glob { regex: Regex, directory_only: bool },
+    //Matched against the full path
+    Regex(Regex),
+}
+
+#[derive(Debug, Clone)]
+pub struct IgnoreRule {
+    //What is stored as the matched rule when a file is rejected by this rule
+    pub rule: String,
+    //Rules starting with ! re-include paths ignored by an earlier rule
+    pub negated: bool,
+    matcher: Matcher,
+}
+
+impl IgnoreRule {
+    ///Follows gitignore: rules containing a / are relative to the base directory, other rules
+    ///match a name at any depth, a trailing / only matches directories, * and ? don't match /,
+    ///** matches any number of directories and ! negates the rule. Matching is case-insensitive.
+    ///Returns none for blank lines, comments and invalid globs
+    pub fn from_glob(glob: &str) -> Option<Self> {
+        let rule = glob.trim();
+        if rule.is_empty() || rule.starts_with('#') {
+            return None;
+        }
+        let (negated, glob) = match rule.strip_prefix('!') {
+            Some(glob) => (true, glob),
+            None => (false, rule.strip_prefix('\\').unwrap_or(rule)),
+        };
+        let directory_only = glob.ends_with('/');
+        let glob = glob.trim_end_matches('/');
+        let anchored = glob.contains('/');
+        let glob = glob.trim_start_matches('/');
+        if glob.is_empty() {
+            return None;
+        }
+
+        let pattern = format!(
+            "(?i){}{}$",
+            if anchored { "^" } else { "(?:^|/)" },
+            glob_to_regex(glob)
+        );
+        match Regex::new(&pattern) {
+            Ok(regex) => Some(Self {
+                rule: rule.to_string(),
+                negated,
+                matcher: Matcher::Glob {
+                    regex,
+                    directory_only,
+                },
+            }),
+            Err(err) => {
+                warn!("Ignoring invalid ignore rule: {}. Err: {}", rule, err);
+                None
+            }
+        }
+    }
+
+    pub fn from_regex(pattern: &str) -> Self {
+        match Regex::new(pattern) {
+            Ok(regex) => Self {
+                rule: pattern.to_string(),
+                negated: false,
+                matcher: Matcher::Regex(regex),
+            },
+            Err(err) => {
+                error!("Invalid ignored regex: {}. Err: {}", pattern, err);
+                panic!();
+            }
+        }
+    }
+
+    ///base is the directory that relative rules start from
+    pub fn is_match(&self, base: &Path, full_path: &Path) -> bool {
+        match &self.matcher {
+            Matcher::Glob {
+                regex,
+                directory_only,
+            } => {
+                let relative_path =
+                    pathbuf_to_string(full_path.strip_prefix(base).unwrap_or(full_path))
+                        .replace('\\', "/");
+                let relative_path = relative_path.trim_start_matches('/');
+                //A rule matching a directory ignores everything under it
+                let mut candidates: Vec<&str> = relative_path
+                    .match_indices('/')
+                    .map(|(index, _)| &relative_path[..index])
+                    .collect();
+                if !directory_only {
+                    candidates.push(relative_path);
+                }
+                candidates
+                    .into_iter()
+                    .any(|candidate| regex.is_match(candidate).unwrap_or(false))
+            }
+            Matcher::Regex(regex) => regex
+                .is_match(&pathbuf_to_string(full_path))
+                .unwrap_or(false),
+        }
+    }
+}
+
+///An ordered list of rules, later rules override earlier ones
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    pub rules: Vec<IgnoreRule>,
+}
+
+impl IgnoreRules {
+    pub fn new(globs: &[String], regexes: &[String]) -> Self {
+        Self {
+            rules: globs
+                .iter()
+                .filter_map(|glob| IgnoreRule::from_glob(glob))
+                .chain(regexes.iter().map(|regex| IgnoreRule::from_regex(regex)))
+                .collect(),
+        }
+    }
+
+    ///Reads the rules from a .tlmignore file, one glob per line. The path of the file is added
+    ///to each rule so the rejection shows where it came from
+    pub fn from_ignore_file(ignore_file_path: &Path) -> Option<Self> {
+        let contents = fs::read_to_string(ignore_file_path).ok()?;
+        Some(Self {
+            rules: contents
+                .lines()
+                .filter_map(IgnoreRule::from_glob)
+                .map(|mut rule| {
+                    rule.rule = format!("{}: {}", pathbuf_to_string(ignore_file_path), rule.rule);
+                    rule
+                })
+                .collect(),
+        })
+    }
+
+    ///The last rule that matches the path, which decides whether it's ignored
+    pub fn last_match(&self, base: &Path, full_path: &Path) -> Option<&IgnoreRule> {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.is_match(base, full_path))
+    }
+}
+
+///Goes through each set of rules in order with the directory its relative rules start from,
+///returning the rule that ignores the path or none if the last matching rule was negated
+pub fn check_ignore_rules<'a>(
+    ignore_rules: impl IntoIterator<Item = (&'a Path, &'a IgnoreRules)>,
+    full_path: &Path,
+) -> Option<String> {
+    let mut last_match: Option<&IgnoreRule> = None;
+    for (base, rules) in ignore_rules {
+        if let Some(rule) = rules.last_match(base, full_path) {
+            last_match = Some(rule);
+        }
+    }
+    last_match
+        .filter(|rule| !rule.negated)
+        .map(|rule| rule.rule.clone())
+}
+
+///The directories from base down to the file's parent that could contain a .tlmignore file
+pub fn get_ignore_file_directories(base: &Path, full_path: &Path) -> Vec<PathBuf> {
+    let mut directories: Vec<PathBuf> = full_path
+        .ancestors()
+        .skip(1)
+        .take_while(|directory| directory.starts_with(base))
+        .map(Path::to_path_buf)
+        .collect();
+    directories.reverse();
+    directories
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let characters: Vec<char> = glob.chars().collect();
+    let mut pattern = String::new();
+    let mut index = 0;
+    while index < characters.len() {
+        match characters[index] {
+            '*' if characters.get(index + 1) == Some(&'*') => {
+                let starts_component = index == 0 || characters[index - 1] == '/';
+                match characters.get(index + 2) {
+                    //**/ matches zero or more directories
+                    Some('/') if starts_component => {
+                        pattern.push_str("(?:.*/)?");
+                        index += 3;
+                    }
+                    //A trailing ** matches everything inside
+                    None if starts_component => {
+                        pattern.push_str(".*");
+                        index += 2;
+                    }
+                    _ => {
+                        pattern.push_str("[^/]*");
+                        index += 2;
+                    }
+                }
+                continue;
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            '[' => match characters[index..].iter().position(|c| *c == ']') {
+                Some(length) if length > 1 => {
+                    let class: String = characters[index + 1..index + length].iter().collect();
+                    let class = match class.strip_prefix('!') {
+                        Some(class) => format!("^{}", class),
+                        None => class,
+                    };
+                    pattern.push_str(&format!("[{}]", class.replace('\\', "\\\\")));
+                    index += length + 1;
+                    continue;
+                }
+                _ => pattern.push_str(r"\["),
+            },
+            '\\' if index + 1 < characters.len() => {
+                index += 1;
+                pattern.push_str(&regex::escape(&characters[index].to_string()));
+            }
+            character => pattern.push_str(&regex::escape(&character.to_string())),
+        }
+        index += 1;
+    }
+    pattern
+}
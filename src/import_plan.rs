@@ -0,0 +1,96 @@
+//!Structures describing what importing and processing the tracked root directories would do,
+//!built by a dry run that doesn't write anything to the database
+use {
+    crate::file_manager::Reason,
+    serde::{Deserialize, Serialize},
+    tracing::info,
+};
+
+///What a new file would be imported as
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "designation")]
+pub enum PlannedImport {
+    Episode {
+        show_title: String,
+        season: i32,
+        episodes: Vec<i32>,
+        episode_title: Option<String>,
+        release_source: Option<String>,
+        rule_name: String,
+    },
+    //An episode in season 0
+    Special {
+        show_title: String,
+        episodes: Vec<i32>,
+        episode_title: Option<String>,
+        release_source: Option<String>,
+        rule_name: String,
+    },
+    Movie {
+        title: String,
+        year: i32,
+    },
+    //Extras and trailers along with the show or movie they would be linked to, if any
+    Extra {
+        is_trailer: bool,
+        show_title: Option<String>,
+        movie_title: Option<String>,
+        movie_year: Option<i32>,
+    },
+    //Files in a generic library
+    Generic,
+    //Files that didn't match the naming expected by their root directory and fall back to generic
+    Unmatched,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlannedFile {
+    pub full_path: String,
+    pub import: PlannedImport,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlannedRejection {
+    pub full_path: String,
+    pub reason: Reason,
+    pub matched_rule: Option<String>,
+}
+
+///Everything a dry run of import_files followed by process_new_files found
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ImportPlan {
+    pub accepted: Vec<PlannedFile>,
+    pub rejected: Vec<PlannedRejection>,
+    //Subtitles, nfo files and artwork that would be attached to a video
+    pub sidecars: Vec<String>,
+    //Shows that don't exist yet and would be created
+    pub new_shows: Vec<String>,
+    //Files that are already in the library and would be skipped
+    pub already_tracked_count: usize,
+}
+
+impl ImportPlan {
+    ///Paths of every accepted file that would fall back to a generic
+    pub fn get_unmatched_files(&self) -> Vec<&String> {
+        self.accepted
+            .iter()
+            .filter(|planned_file| planned_file.import == PlannedImport::Unmatched)
+            .map(|planned_file| &planned_file.full_path)
+            .collect()
+    }
+
+    pub fn print(&self) {
+        for show_title in &self.new_shows {
+            info!("Would create show: {}", show_title);
+        }
+        info!(
+            "Dry run would import {} files ({} unmatched), reject {} files, attach {} sidecars and create {} shows. {} files are already tracked",
+            self.accepted.len(),
+            self.get_unmatched_files().len(),
+            self.rejected.len(),
+            self.sidecars.len(),
+            self.new_shows.len(),
+            self.already_tracked_count
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//!Re-reads files at a limited rate and compares them with their stored full hash to find files
+//!that have silently rotted on disk. Files that no longer match are recorded as corruptions and
+//!flagged so they're never used as the source of an encode
+use {
+    crate::{
+        database::{create_corruption, establish_connection, update_file_version_verification},
+        file_manager::FileManager,
+        generic::{hash_reader_with_version, FileVersion},
+        model::{CorruptionModel, NewCorruption},
+        pathbuf_to_string,
+    },
+    serde::{Deserialize, Serialize},
+    std::{
+        fs::File,
+        io::{self, Read},
+        path::PathBuf,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+        thread,
+        time::{Duration, Instant, SystemTime},
+    },
+    tracing::{error, info, warn},
+};
+
+///A file that no longer matched its stored hash when it was verified
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Corruption {
+    pub id: i32,
+    pub file_version_id: i32,
+    pub generic_uid: i32,
+    pub full_path: PathBuf,
+    pub expected_hash: String,
+    pub actual_hash: String,
+    pub detected_at: SystemTime,
+}
+
+impl Corruption {
+    pub fn from_model(model: CorruptionModel) -> Self {
+        Self {
+            id: model.id,
+            file_version_id: model.file_version_id,
+            generic_uid: model.generic_uid,
+            full_path: PathBuf::from(model.full_path),
+            expected_hash: model.expected_hash,
+            actual_hash: model.actual_hash,
+            detected_at: model.detected_at,
+        }
+    }
+}
+
+///Sleeps between reads so the average rate stays under bytes_per_second, so that verifying
+///doesn't starve encodes and playback of disk time. A rate of 0 isn't limited.
+///Reads end early once stop is set, so anything read then is incomplete
+pub struct RateLimitedReader<'a, R> {
+    reader: R,
+    bytes_per_second: u64,
+    bytes_read: u64,
+    started_at: Instant,
+    stop: &'a AtomicBool,
+}
+
+impl<'a, R: Read> RateLimitedReader<'a, R> {
+    pub fn new(reader: R, bytes_per_second: u64, stop: &'a AtomicBool) -> Self {
+        Self {
+            reader,
+            bytes_per_second,
+            bytes_read: 0,
+            started_at: Instant::now(),
+            stop,
+        }
+    }
+}
+
+impl<R: Read> Read for RateLimitedReader<'_, R> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        if self.stop.load(Ordering::Relaxed) {
+            return Ok(0);
+        }
+        let read = self.reader.read(buffer)?;
+        self.bytes_read += read as u64;
+        if self.bytes_per_second > 0 {
+            let expected_time =
+                Duration::from_secs_f64(self.bytes_read as f64 / self.bytes_per_second as f64);
+            let elapsed = self.started_at.elapsed();
+            if expected_time > elapsed {
+                thread::sleep(expected_time - elapsed);
+            }
+        }
+        Ok(read)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct VerificationReport {
+    pub verified: usize,
+    pub corrupted: usize,
+    //Files without a full hash or that couldn't be read, they're verified again on the next run
+    pub skipped: usize,
+}
+
+impl VerificationReport {
+    pub fn print(&self) {
+        info!(
+            "Verified {} files, {} were corrupted and {} were skipped",
+            self.verified, self.corrupted, self.skipped
+        );
+    }
+}
+
+///Whether the file still matches its stored full hash, none if it has no full hash, can't be
+///read or stop was set before it was read in full. On a mismatch the hash the file has now is
+///returned
+pub fn verify_file_version(
+    file_version: &FileVersion,
+    bytes_per_second: u64,
+    stop: &AtomicBool,
+) -> Option<Result<(), String>> {
+    let expected_hash = file_version.hash.as_ref()?;
+    let file = match File::open(&file_version.full_path) {
+        Ok(file) => file,
+        Err(err) => {
+            warn!(
+                "Couldn't open file for verification: {}. Err: {}",
+                pathbuf_to_string(&file_version.full_path),
+                err
+            );
+            return None;
+        }
+    };
+    let actual_hash = match hash_reader_with_version(
+        RateLimitedReader::new(file, bytes_per_second, stop),
+        file_version.hash_version,
+    ) {
+        Ok(actual_hash) => actual_hash,
+        Err(err) => {
+            warn!(
+                "Couldn't read file for verification: {}. Err: {}",
+                pathbuf_to_string(&file_version.full_path),
+                err
+            );
+            return None;
+        }
+    };
+    if stop.load(Ordering::Relaxed) {
+        return None;
+    }
+    if &actual_hash == expected_hash {
+        Some(Ok(()))
+    } else {
+        Some(Err(actual_hash))
+    }
+}
+
+///Verifies each FileVersion in order, recording corruptions and updating when each was verified
+///in the database and the FileManager. Stops early once stop is set
+pub fn verify_file_versions(
+    file_versions: Vec<FileVersion>,
+    bytes_per_second: u64,
+    file_manager: Arc<Mutex<FileManager>>,
+    stop: &AtomicBool,
+) -> VerificationReport {
+    let connection = establish_connection();
+    let mut report = VerificationReport::default();
+    for mut file_version in file_versions {
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+        let result = match verify_file_version(&file_version, bytes_per_second, stop) {
+            Some(result) => result,
+            None if stop.load(Ordering::Relaxed) => break,
+            //Unreadable files aren't marked as verified so they're tried again next time
+            None => {
+                report.skipped += 1;
+                continue;
+            }
+        };
+        let was_corrupted = file_version.corrupted;
+        match result {
+            Ok(()) => {
+                if was_corrupted {
+                    info!(
+                        "File matches its hash again and is no longer marked as corrupted: {}",
+                        pathbuf_to_string(&file_version.full_path)
+                    );
+                }
+                file_version.corrupted = false;
+            }
+            Err(actual_hash) => {
+                error!(
+                    "{} is corrupted: {}. Expected hash: {}, actual hash: {}",
+                    if file_version.master_file {
+                        "Master file"
+                    } else {
+                        "File"
+                    },
+                    pathbuf_to_string(&file_version.full_path),
+                    file_version.hash.as_deref().unwrap_or_default(),
+                    actual_hash
+                );
+                create_corruption(
+                    &connection,
+                    NewCorruption {
+                        file_version_id: file_version.id,
+                        generic_uid: file_version.generic_uid,
+                        full_path: pathbuf_to_string(&file_version.full_path),
+                        expected_hash: file_version.hash.clone().unwrap_or_default(),
+                        actual_hash,
+                    },
+                );
+                file_version.corrupted = true;
+                report.corrupted += 1;
+            }
+        }
+        file_version.verified_at = Some(SystemTime::now());
+        update_file_version_verification(&connection, &file_version);
+        file_manager
+            .lock()
+            .unwrap()
+            .update_verification(&file_version);
+        report.verified += 1;
+    }
+    report
+}
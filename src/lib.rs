@@ -1,8 +1,20 @@
 #![doc = include_str!("../README.md")]
 
+use device::DirectPlay;
+use duplicate::DuplicateGroup;
+use encode::EncodeProfile;
+use file_manager::{PathBufReason, Reason};
 use generic::FileVersion;
-use show::Show;
+use hashing::HashProgress;
+use import_plan::ImportPlan;
+use integrity::Corruption;
+use movie::Movie;
+use profile::{AudioCodec, DynamicRange, VideoCodec};
+use show::{Episode, Show};
+use statistics::LibraryStatistics;
+use stream::{Stream, StreamFilter};
 use tracing::warn;
+use validation::FailedEncode;
 use {
     serde::{Deserialize, Serialize},
     std::{
@@ -12,6 +24,7 @@ use {
         io::{Error, Write},
         net::SocketAddr,
         path::{Path, PathBuf},
+        time::UNIX_EPOCH,
     },
     futures_channel::mpsc::UnboundedSender,
     tracing::error,
@@ -23,15 +36,32 @@ pub mod config;
 pub mod database;
 pub mod debug;
 pub mod designation;
+pub mod device;
+pub mod duplicate;
 pub mod encode;
+pub mod extra;
 pub mod file_manager;
 pub mod generic;
+pub mod hashing;
+pub mod ignore;
+pub mod import_plan;
+pub mod integrity;
 pub mod model;
+pub mod movie;
+pub mod nfo;
+pub mod parser;
+pub mod path_mapping;
+pub mod prober;
 pub mod profile;
 pub mod scheduler;
 pub mod schema;
 pub mod show;
+pub mod sidecar;
+pub mod statistics;
+pub mod stream;
 pub mod testing;
+pub mod validation;
+pub mod watcher;
 pub mod worker;
 pub mod worker_manager;
 pub mod ws;
@@ -178,6 +208,9 @@ pub struct WebUIFileVersion {
     pub generic_uid: i32,
     pub id: i32,
     pub file_name: String,
+    //Didn't match its hash when it was last verified
+    pub corrupted: bool,
+    pub streams: Vec<WebUIStream>,
 }
 
 impl WebUIFileVersion {
@@ -186,6 +219,34 @@ impl WebUIFileVersion {
             generic_uid: file_version.generic_uid,
             id: file_version.id,
             file_name: file_version.get_file_name(),
+            corrupted: file_version.corrupted,
+            streams: file_version
+                .streams
+                .iter()
+                .map(WebUIStream::from_stream)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WebUIStream {
+    #[serde(flatten)]
+    pub stream: Stream,
+    //Set for codecs that device support is known for
+    pub video_codec: Option<VideoCodec>,
+    pub audio_codec: Option<AudioCodec>,
+    //Only set for video streams
+    pub dynamic_range: Option<DynamicRange>,
+}
+
+impl WebUIStream {
+    pub fn from_stream(stream: &Stream) -> Self {
+        Self {
+            stream: stream.clone(),
+            video_codec: stream.get_video_codec(),
+            audio_codec: stream.get_audio_codec(),
+            dynamic_range: stream.get_dynamic_range(),
         }
     }
 }
@@ -194,21 +255,109 @@ impl WebUIFileVersion {
 pub struct WebUIShow {
     pub show_uid: i32,
     pub show_title: String,
+    pub show_year: Option<i32>,
+    pub plot: Option<String>,
     pub episode_count: usize,
+    pub seasons: Vec<WebUISeason>,
 }
 
 impl WebUIShow {
     pub fn from_show(show: &Show) -> Self {
+        let mut seasons: Vec<WebUISeason> = show
+            .seasons
+            .iter()
+            .map(|season| WebUISeason {
+                number: season.number,
+                episodes: season
+                    .episodes
+                    .iter()
+                    .map(WebUIEpisode::from_episode)
+                    .collect(),
+            })
+            .collect();
+        seasons.sort_by_key(|season| season.number);
         Self {
             show_uid: show.show_uid,
             show_title: show.show_title.clone(),
-            episode_count: {
-                let mut episode_count: usize = 0;
-                for season in show.seasons.iter() {
-                    episode_count += season.episodes.len();
-                }
-                episode_count
-            }
+            show_year: show.show_year,
+            plot: show.plot.clone(),
+            episode_count: show.episode_count(),
+            seasons,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WebUISeason {
+    pub number: i32,
+    pub episodes: Vec<WebUIEpisode>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WebUIEpisode {
+    pub generic_uid: i32,
+    //More than one for multi-episode files
+    pub episode_numbers: Vec<i32>,
+    pub episode_title: String,
+    pub release_source: Option<String>,
+    //YYYY-MM-DD
+    pub air_date: Option<String>,
+    pub plot: Option<String>,
+    pub file_version_count: usize,
+}
+
+impl WebUIEpisode {
+    pub fn from_episode(episode: &Episode) -> Self {
+        Self {
+            generic_uid: episode.generic.get_generic_uid(),
+            episode_numbers: episode.show_episode.clone(),
+            episode_title: episode.episode_title.clone(),
+            release_source: episode.release_source.clone(),
+            air_date: episode.air_date.clone(),
+            plot: episode.plot.clone(),
+            file_version_count: episode.generic.file_versions.len(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WebUIMovie {
+    pub generic_uid: i32,
+    pub movie_title: String,
+    pub movie_year: i32,
+    pub file_version_count: usize,
+}
+
+impl WebUIMovie {
+    pub fn from_movie(movie: &Movie) -> Self {
+        Self {
+            generic_uid: movie.generic.get_generic_uid(),
+            movie_title: movie.movie_title.clone(),
+            movie_year: movie.movie_year,
+            file_version_count: movie.generic.file_versions.len(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WebUIRejectedFile {
+    pub full_path: String,
+    pub reason: Reason,
+    pub matched_rule: Option<String>,
+    //Seconds since the unix epoch
+    pub rejected_at: u64,
+}
+
+impl WebUIRejectedFile {
+    pub fn from_path_buf_reason(path_buf_reason: &PathBufReason) -> Self {
+        Self {
+            full_path: pathbuf_to_string(&path_buf_reason.pathbuf),
+            reason: path_buf_reason.reason,
+            matched_rule: path_buf_reason.matched_rule.clone(),
+            rejected_at: path_buf_reason
+                .rejected_at
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs()),
         }
     }
 }
@@ -324,6 +473,27 @@ impl MessageSource {
 pub enum RequestType {
     AllFileVersions,
     AllShows,
+    AllMovies,
+    //Optionally filtered by reason
+    RejectedFiles(Option<Reason>),
+    //The report from the last FindDuplicates task
+    Duplicates,
+    //The plan from the last PlanImport task
+    ImportPlan,
+    //Progress of the current or last Hash task
+    HashProgress,
+    //Every file that has failed verification
+    Corruptions,
+    //Every encode whose output was rejected
+    FailedEncodes,
+    //File versions with a stream matching the filter, such as every file with Japanese audio
+    FilteredFileVersions(StreamFilter),
+    //Whether each file version will direct play on each device
+    DirectPlay,
+    //Totals of the library, with projected savings when given a profile
+    Statistics(Option<EncodeProfile>),
+    //The same as Statistics as CSV
+    StatisticsCsv(Option<EncodeProfile>),
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -332,11 +502,25 @@ pub enum WebUIMessage {
     Request(RequestType),
     //EncodeGeneric(i32, i32, AddEncodeMode, EncodeProfile),
     Encode(i32, i32),
+    //Group id, generic_uid and id of the copy to keep, every other copy in the group is deleted
+    KeepDuplicate(usize, i32, i32),
+    //Device name, encodes every file with no version the device can direct play
+    EncodeForDevice(String),
 
     //Server -> WebUI
     Shows(Vec<WebUIShow>),
+    Movies(Vec<WebUIMovie>),
     FileVersion(i32, i32, String),
     FileVersions(Vec<WebUIFileVersion>),
+    RejectedFiles(Vec<WebUIRejectedFile>),
+    Duplicates(Vec<DuplicateGroup>),
+    ImportPlan(ImportPlan),
+    HashProgress(HashProgress),
+    Corruptions(Vec<Corruption>),
+    FailedEncodes(Vec<FailedEncode>),
+    DirectPlay(Vec<DirectPlay>),
+    Statistics(LibraryStatistics),
+    StatisticsCsv(String),
     //Generics(Vec<WebUIGeneric>),
 }
 
@@ -1,9 +1,20 @@
 use {
     super::{
         generic::Generic,
-        schema::{episode, file_version, generic, show, worker},
+        schema::{
+            completed_encode, corruption, episode, extra, failed_encode, file_version, generic,
+            hash_run, movie, rejected_file, show, sidecar, stream, worker,
+        },
     },
-    crate::{generic::FileVersion, worker::Worker},
+    crate::{
+        file_manager::PathBufReason,
+        generic::{FileVersion, CURRENT_HASH_VERSION},
+        pathbuf_to_string,
+        sidecar::SidecarKind,
+        stream::Stream,
+        worker::Worker,
+    },
+    std::{path::Path, time::SystemTime},
 };
 
 //Workers
@@ -84,6 +95,10 @@ pub struct NewFileVersion {
     length_time: Option<f64>,
     resolution_standard: Option<i32>,
     container: Option<i32>,
+    missing: bool,
+    hash_version: i32,
+    corrupted: bool,
+    verified_at: Option<SystemTime>,
 }
 
 impl NewFileVersion {
@@ -100,6 +115,10 @@ impl NewFileVersion {
             length_time: None,
             resolution_standard: None,
             container: None,
+            missing: false,
+            hash_version: CURRENT_HASH_VERSION,
+            corrupted: false,
+            verified_at: None,
         }
     }
 }
@@ -119,6 +138,10 @@ pub struct FileVersionModel {
     pub length_time: Option<f64>,
     pub resolution_standard: Option<i32>,
     pub container: Option<i32>,
+    pub missing: bool,
+    pub hash_version: i32,
+    pub corrupted: bool,
+    pub verified_at: Option<SystemTime>,
 }
 
 impl FileVersionModel {
@@ -146,6 +169,10 @@ impl FileVersionModel {
             length_time: file_version.length_time,
             resolution_standard,
             container,
+            missing: file_version.missing,
+            hash_version: file_version.hash_version,
+            corrupted: file_version.corrupted,
+            verified_at: file_version.verified_at,
         }
     }
 }
@@ -160,24 +187,10 @@ pub struct NewEpisode {
     pub episode_title: String,
     pub season_number: i32,
     pub episode_number: i32,
-}
-
-impl NewEpisode {
-    pub fn new(
-        generic_uid: i32,
-        show_uid: i32,
-        episode_title: String,
-        season_number: i32,
-        episode_number: i32,
-    ) -> Self {
-        Self {
-            generic_uid,
-            show_uid,
-            episode_title,
-            season_number,
-            episode_number,
-        }
-    }
+    pub release_source: Option<String>,
+    //YYYY-MM-DD
+    pub air_date: Option<String>,
+    pub plot: Option<String>,
 }
 
 ///Structure to select Episodes from the database
@@ -188,6 +201,9 @@ pub struct EpisodeModel {
     pub episode_title: String,
     pub season_number: i32,
     pub episode_number: i32,
+    pub release_source: Option<String>,
+    pub air_date: Option<String>,
+    pub plot: Option<String>,
 }
 
 //Show
@@ -196,6 +212,8 @@ pub struct EpisodeModel {
 #[table_name = "show"]
 pub struct NewShow {
     pub show_title: String,
+    pub show_year: Option<i32>,
+    pub plot: Option<String>,
 }
 
 ///Struct to select shows from the database
@@ -203,4 +221,296 @@ pub struct NewShow {
 pub struct ShowModel {
     pub show_uid: i32,
     pub show_title: String,
+    pub show_year: Option<i32>,
+    pub plot: Option<String>,
+}
+
+//Movie
+///Struct to insert movies into the database
+#[derive(Insertable)]
+#[table_name = "movie"]
+pub struct NewMovie {
+    pub generic_uid: i32,
+    pub movie_title: String,
+    pub movie_year: i32,
+}
+
+impl NewMovie {
+    pub fn new(generic_uid: i32, movie_title: String, movie_year: i32) -> Self {
+        Self {
+            generic_uid,
+            movie_title,
+            movie_year,
+        }
+    }
+}
+
+///Struct to select movies from the database
+#[derive(Queryable)]
+pub struct MovieModel {
+    pub generic_uid: i32,
+    pub movie_title: String,
+    pub movie_year: i32,
+}
+
+//Extra
+///Struct to insert extras into the database
+#[derive(Insertable)]
+#[table_name = "extra"]
+pub struct NewExtra {
+    pub generic_uid: i32,
+    pub show_uid: Option<i32>,
+    pub movie_generic_uid: Option<i32>,
+}
+
+impl NewExtra {
+    pub fn new(generic_uid: i32, show_uid: Option<i32>, movie_generic_uid: Option<i32>) -> Self {
+        Self {
+            generic_uid,
+            show_uid,
+            movie_generic_uid,
+        }
+    }
+}
+
+///Struct to select extras from the database
+#[derive(Queryable)]
+pub struct ExtraModel {
+    pub generic_uid: i32,
+    pub show_uid: Option<i32>,
+    pub movie_generic_uid: Option<i32>,
+}
+
+//Sidecar
+///Struct to insert sidecar files into the database
+#[derive(Insertable)]
+#[table_name = "sidecar"]
+pub struct NewSidecar {
+    pub generic_uid: i32,
+    pub full_path: String,
+    pub kind: i32,
+    pub language: Option<String>,
+}
+
+impl NewSidecar {
+    pub fn new(
+        generic_uid: i32,
+        full_path: &Path,
+        kind: SidecarKind,
+        language: Option<String>,
+    ) -> Self {
+        Self {
+            generic_uid,
+            full_path: pathbuf_to_string(full_path),
+            kind: kind as i32,
+            language,
+        }
+    }
+}
+
+///Struct to select sidecar files from the database
+#[derive(Queryable)]
+pub struct SidecarModel {
+    pub id: i32,
+    pub generic_uid: i32,
+    pub full_path: String,
+    pub kind: i32,
+    pub language: Option<String>,
+}
+
+//RejectedFile
+///Struct to insert or update rejected files in the database
+#[derive(Insertable)]
+#[table_name = "rejected_file"]
+pub struct NewRejectedFile {
+    pub full_path: String,
+    pub reason: i32,
+    pub matched_rule: Option<String>,
+    pub rejected_at: SystemTime,
+}
+
+impl NewRejectedFile {
+    pub fn from_path_buf_reason(path_buf_reason: &PathBufReason) -> Self {
+        Self {
+            full_path: pathbuf_to_string(&path_buf_reason.pathbuf),
+            reason: path_buf_reason.reason as i32,
+            matched_rule: path_buf_reason.matched_rule.clone(),
+            rejected_at: path_buf_reason.rejected_at,
+        }
+    }
+}
+
+///Struct to select rejected files from the database
+#[derive(Queryable)]
+pub struct RejectedFileModel {
+    pub id: i32,
+    pub full_path: String,
+    pub reason: i32,
+    pub matched_rule: Option<String>,
+    pub rejected_at: SystemTime,
+}
+
+//HashRun
+///Struct to insert a hashing run so it can be resumed if the server stops before it finishes
+#[derive(Insertable)]
+#[table_name = "hash_run"]
+pub struct NewHashRun {
+    pub total_files: i32,
+    pub total_bytes: i64,
+    pub rehash_outdated: bool,
+}
+
+///Struct to select hashing runs from the database
+#[derive(Queryable)]
+pub struct HashRunModel {
+    pub id: i32,
+    pub total_files: i32,
+    pub total_bytes: i64,
+    pub hashed_files: i32,
+    pub hashed_bytes: i64,
+    pub finished: bool,
+    pub started_at: SystemTime,
+    pub rehash_outdated: bool,
+}
+
+//Corruption
+///Struct to record a file that no longer matched its stored hash
+#[derive(Insertable)]
+#[table_name = "corruption"]
+pub struct NewCorruption {
+    pub file_version_id: i32,
+    pub generic_uid: i32,
+    pub full_path: String,
+    pub expected_hash: String,
+    pub actual_hash: String,
+}
+
+///Struct to select corruptions from the database
+#[derive(Queryable)]
+pub struct CorruptionModel {
+    pub id: i32,
+    pub file_version_id: i32,
+    pub generic_uid: i32,
+    pub full_path: String,
+    pub expected_hash: String,
+    pub actual_hash: String,
+    pub detected_at: SystemTime,
+}
+
+//FailedEncode
+///Struct to record an encode whose output was rejected instead of being added to the library
+#[derive(Insertable)]
+#[table_name = "failed_encode"]
+pub struct NewFailedEncode {
+    pub generic_uid: i32,
+    pub source_path: String,
+    pub target_path: String,
+    pub quarantine_path: Option<String>,
+    pub reason: String,
+}
+
+///Struct to select failed encodes from the database
+#[derive(Queryable)]
+pub struct FailedEncodeModel {
+    pub id: i32,
+    pub generic_uid: i32,
+    pub source_path: String,
+    pub target_path: String,
+    pub quarantine_path: Option<String>,
+    pub reason: String,
+    pub failed_at: SystemTime,
+}
+
+//CompletedEncode
+///Struct to record the sizes of an encode that was added to the library, used to project how much
+///space encoding with the same profile saves
+#[derive(Insertable)]
+#[table_name = "completed_encode"]
+pub struct NewCompletedEncode {
+    pub generic_uid: i32,
+    pub encode_profile: String,
+    pub source_size: i64,
+    pub output_size: i64,
+}
+
+///Struct to select completed encodes from the database
+#[derive(Queryable)]
+pub struct CompletedEncodeModel {
+    pub id: i32,
+    pub generic_uid: i32,
+    pub encode_profile: String,
+    pub source_size: i64,
+    pub output_size: i64,
+    pub finished_at: SystemTime,
+}
+
+//Stream
+///Struct to insert the streams of a file version into the database
+#[derive(Insertable)]
+#[table_name = "stream"]
+pub struct NewStream {
+    pub file_version_id: i32,
+    pub stream_index: i32,
+    pub stream_type: i32,
+    pub codec: String,
+    pub profile: Option<String>,
+    pub level: Option<String>,
+    pub bit_depth: Option<i32>,
+    pub bitrate: Option<i64>,
+    pub channels: Option<i32>,
+    pub language: Option<String>,
+    pub is_default: bool,
+    pub is_forced: bool,
+    pub transfer_characteristics: Option<String>,
+    pub colour_primaries: Option<String>,
+    pub matrix_coefficients: Option<String>,
+    pub mastering_display: Option<String>,
+    pub content_light_level: Option<String>,
+}
+
+impl NewStream {
+    pub fn new(file_version_id: i32, stream: &Stream) -> Self {
+        Self {
+            file_version_id,
+            stream_index: stream.stream_index,
+            stream_type: stream.stream_type as i32,
+            codec: stream.codec.clone(),
+            profile: stream.profile.clone(),
+            level: stream.level.clone(),
+            bit_depth: stream.bit_depth,
+            bitrate: stream.bitrate,
+            channels: stream.channels,
+            language: stream.language.clone(),
+            is_default: stream.is_default,
+            is_forced: stream.is_forced,
+            transfer_characteristics: stream.transfer_characteristics.clone(),
+            colour_primaries: stream.colour_primaries.clone(),
+            matrix_coefficients: stream.matrix_coefficients.clone(),
+            mastering_display: stream.mastering_display.clone(),
+            content_light_level: stream.content_light_level.clone(),
+        }
+    }
+}
+
+///Struct to select streams from the database
+#[derive(Queryable)]
+pub struct StreamModel {
+    pub id: i32,
+    pub file_version_id: i32,
+    pub stream_index: i32,
+    pub stream_type: i32,
+    pub codec: String,
+    pub profile: Option<String>,
+    pub level: Option<String>,
+    pub bit_depth: Option<i32>,
+    pub bitrate: Option<i64>,
+    pub channels: Option<i32>,
+    pub language: Option<String>,
+    pub is_default: bool,
+    pub is_forced: bool,
+    pub transfer_characteristics: Option<String>,
+    pub colour_primaries: Option<String>,
+    pub matrix_coefficients: Option<String>,
+    pub mastering_display: Option<String>,
+    pub content_light_level: Option<String>,
 }
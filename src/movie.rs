@@ -0,0 +1,42 @@
+//!Module containing all structures used to represent a movie
+use crate::{
+    generic::{FileVersion, Generic},
+    model::*,
+};
+
+///Structure contains all movie specific data as well as the underlying
+///generic file data
+#[derive(Clone, Debug)]
+pub struct Movie {
+    pub generic: Generic,
+    pub movie_title: String,
+    pub movie_year: i32,
+}
+
+impl Movie {
+    pub fn new(generic: Generic, movie_title: String, movie_year: i32) -> Self {
+        Movie {
+            generic,
+            movie_title,
+            movie_year,
+        }
+    }
+
+    ///Create Movie from MovieModel generated by database and the generic it belongs to
+    pub fn from_movie_model(movie_model: MovieModel, generic: Generic) -> Self {
+        Movie {
+            generic,
+            movie_title: movie_model.movie_title,
+            movie_year: movie_model.movie_year,
+        }
+    }
+
+    //Returns true if successful
+    pub fn insert_file_version(&mut self, file_version: &FileVersion) -> bool {
+        if self.generic.get_generic_uid() == file_version.generic_uid {
+            self.generic.file_versions.push(file_version.to_owned());
+            return true;
+        }
+        false
+    }
+}
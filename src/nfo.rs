@@ -0,0 +1,152 @@
+//!Reads Kodi-style nfo files kept next to a library by other tools, tvshow.nfo in a show's folder
+//!and an nfo named after each episode, so their metadata can be used instead of the file names
+use {
+    crate::pathbuf_to_string,
+    roxmltree::{Document, Node},
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
+    tracing::warn,
+};
+
+pub const SHOW_NFO_FILE_NAME: &str = "tvshow.nfo";
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NfoShow {
+    pub title: Option<String>,
+    pub year: Option<i32>,
+    pub plot: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct NfoEpisode {
+    pub title: Option<String>,
+    pub season: Option<i32>,
+    pub episode: Option<i32>,
+    //YYYY-MM-DD
+    pub aired: Option<String>,
+    pub plot: Option<String>,
+}
+
+///Reads tvshow.nfo from the show's folder, none if there isn't one or it can't be parsed
+pub fn read_show_nfo(show_directory: &Path) -> Option<NfoShow> {
+    let nfo_path = show_directory.join(SHOW_NFO_FILE_NAME);
+    let contents = fs::read_to_string(&nfo_path).ok()?;
+    parse_show_nfo(&contents, &nfo_path)
+}
+
+pub fn parse_show_nfo(contents: &str, nfo_path: &Path) -> Option<NfoShow> {
+    let wrapped_contents = wrap_nfo(contents)?;
+    let document = parse_document(&wrapped_contents, nfo_path)?;
+    let tvshow = document
+        .root_element()
+        .children()
+        .find(|node| node.has_tag_name("tvshow"))?;
+    //The year is often left out in favour of the premiere date
+    let year = child_text(tvshow, "year")
+        .or_else(|| child_text(tvshow, "premiered"))
+        .and_then(|year| year.get(..4).and_then(|year| year.parse::<i32>().ok()));
+    Some(NfoShow {
+        title: child_text(tvshow, "title"),
+        year,
+        plot: child_text(tvshow, "plot"),
+    })
+}
+
+///The nfo file named after the video, such as "Show - S01E01.nfo" for "Show - S01E01.mkv"
+pub fn get_episode_nfo_path(video_path: &Path) -> PathBuf {
+    video_path.with_extension("nfo")
+}
+
+///Reads the nfo named after an episode, multi-episode files have an episodedetails for
+///each episode in the one file. Empty if there isn't one or it can't be parsed
+pub fn read_episode_nfo(video_path: &Path) -> Vec<NfoEpisode> {
+    let nfo_path = get_episode_nfo_path(video_path);
+    match fs::read_to_string(&nfo_path) {
+        Ok(contents) => parse_episode_nfo(&contents, &nfo_path),
+        Err(_) => Vec::new(),
+    }
+}
+
+pub fn parse_episode_nfo(contents: &str, nfo_path: &Path) -> Vec<NfoEpisode> {
+    let wrapped_contents = match wrap_nfo(contents) {
+        Some(wrapped_contents) => wrapped_contents,
+        None => return Vec::new(),
+    };
+    let document = match parse_document(&wrapped_contents, nfo_path) {
+        Some(document) => document,
+        None => return Vec::new(),
+    };
+    document
+        .root_element()
+        .children()
+        .filter(|node| node.has_tag_name("episodedetails"))
+        .map(|episodedetails| NfoEpisode {
+            title: child_text(episodedetails, "title"),
+            season: child_text(episodedetails, "season")
+                .and_then(|season| season.parse::<i32>().ok()),
+            episode: child_text(episodedetails, "episode")
+                .and_then(|episode| episode.parse::<i32>().ok()),
+            aired: child_text(episodedetails, "aired"),
+            plot: child_text(episodedetails, "plot"),
+        })
+        .collect()
+}
+
+///The details for one episode of a file. An nfo with a single episodedetails that leaves out the
+///episode number is taken to be for the file's only episode, otherwise none unless an entry has
+///the same number. Entries with a different season never match
+pub fn find_nfo_episode(
+    nfo_episodes: &[NfoEpisode],
+    season_number: i32,
+    episode_number: i32,
+) -> Option<&NfoEpisode> {
+    let same_season = |nfo_episode: &&NfoEpisode| {
+        nfo_episode
+            .season
+            .map_or(true, |season| season == season_number)
+    };
+    match nfo_episodes {
+        [nfo_episode] if nfo_episode.episode.is_none() => Some(nfo_episode).filter(same_season),
+        _ => nfo_episodes
+            .iter()
+            .filter(same_season)
+            .find(|nfo_episode| nfo_episode.episode == Some(episode_number)),
+    }
+}
+
+///Multi-episode nfo files have more than one root element, so everything after the xml
+///declaration is wrapped in a single root element. Some tools add a url after the xml, which is
+///dropped along with anything else after the last tag
+fn wrap_nfo(contents: &str) -> Option<String> {
+    let contents = contents.trim_start_matches('\u{feff}').trim_start();
+    let contents = match contents.strip_prefix("<?xml") {
+        Some(declaration) => &declaration[declaration.find("?>")? + 2..],
+        None => contents,
+    };
+    let contents = &contents[..contents.rfind('>')? + 1];
+    Some(format!("<nfo>{}</nfo>", contents))
+}
+
+fn parse_document<'a>(wrapped_contents: &'a str, nfo_path: &Path) -> Option<Document<'a>> {
+    match Document::parse(wrapped_contents) {
+        Ok(document) => Some(document),
+        Err(err) => {
+            warn!(
+                "Failed to parse nfo file: {}. Err: {}",
+                pathbuf_to_string(nfo_path),
+                err
+            );
+            None
+        }
+    }
+}
+
+fn child_text(node: Node, tag_name: &str) -> Option<String> {
+    node.children()
+        .find(|child| child.has_tag_name(tag_name))
+        .and_then(|child| child.text())
+        .map(|text| text.trim().to_string())
+        .filter(|text| !text.is_empty())
+}
@@ -0,0 +1,349 @@
+//!Configurable rules for parsing season and episode information out of file names
+//!as well as recognising movies, specials, extras and trailers from their file or folder name
+//!and release tags such as HDTV or WEB-DL
+use {
+    crate::{designation::Designation, get_file_stem},
+    fancy_regex::Regex,
+    lazy_static::lazy_static,
+    serde::{Deserialize, Serialize},
+    std::path::{Path, PathBuf},
+    tracing::{error, warn},
+};
+
+///A named regex used to recognise episodes. Rules can use the named captures
+///season, episode, last_episode (for multi-episode files), date and title
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EpisodeParseRule {
+    pub name: String,
+    pub regex: String,
+    #[serde(skip)]
+    compiled_regex: Option<Regex>,
+}
+
+impl EpisodeParseRule {
+    pub fn new(name: &str, regex: &str) -> Self {
+        let mut rule = Self {
+            name: name.to_string(),
+            regex: regex.to_string(),
+            compiled_regex: None,
+        };
+        rule.compile();
+        rule
+    }
+
+    ///Compiles the regex, rules loaded from the config file need to be compiled before use
+    pub fn compile(&mut self) {
+        match Regex::new(&self.regex) {
+            Ok(regex) => self.compiled_regex = Some(regex),
+            Err(err) => {
+                error!(
+                    "Episode parse rule \"{}\" has an invalid regex: {}. Err: {}",
+                    self.name, self.regex, err
+                );
+                panic!();
+            }
+        }
+    }
+
+    ///The default rules in the order they are tried
+    pub fn default_rules() -> Vec<Self> {
+        vec![
+            //Show - S01E02 - Title, S01E01-E03, S01E01E02
+            Self::new(
+                "season_episode",
+                r"(?i)(?<![a-z0-9])S(?P<season>\d{1,4})[\s._]?E(?P<episode>\d{1,4})(?:(?:-?E|-)(?P<last_episode>\d{1,4})(?![\dp]))?(?:[\s._]*-[\s._]*(?P<title>[^\[]+))?",
+            ),
+            //Show - 1x02 - Title
+            Self::new(
+                "cross",
+                r"(?i)(?<![a-z0-9])(?P<season>\d{1,2})x(?P<episode>\d{2,3})(?:-(?:\d{1,2}x)?(?P<last_episode>\d{2,3}))?(?!\d)(?:[\s._]*-[\s._]*(?P<title>[^\[]+))?",
+            ),
+            //Show Season 1 Episode 2
+            Self::new(
+                "season_episode_words",
+                r"(?i)Season[\s._-]*(?P<season>\d{1,4})[\s._-]*Episode[\s._-]*(?P<episode>\d{1,4})(?:[\s._]*-[\s._]*(?P<title>[^\[]+))?",
+            ),
+            //Show - 2012-03-05 - Title
+            Self::new(
+                "daily",
+                r"(?<!\d)(?P<date>(?:19|20)\d{2}[.\-_ ](?:0[1-9]|1[0-2])[.\-_ ](?:0[1-9]|[12]\d|3[01]))(?!\d)(?:[\s._]*-[\s._]*(?P<title>[^\[]+))?",
+            ),
+            //[Group] Show - 1012 (1080p), Show - 102. Without a release group only 3 digits are
+            //allowed and a year in brackets afterwards means it's a movie, so titles such as
+            //Blade Runner - 2049 aren't taken as episodes
+            Self::new(
+                "absolute",
+                r"^(?:\[[^\]]*\][\s._]*.+?[\s._]-[\s._](?=\d{1,4}(?!\d))|(?!\[).+?[\s._]-[\s._](?=\d{1,3}(?!\d)))(?P<episode>\d{1,4})(?:v\d)?(?:[\s._]*[\[(](?!(?:19|20)\d{2}\)).*)?$",
+            ),
+        ]
+    }
+
+    ///Attempts to parse the file stem with this rule
+    pub fn parse(&self, file_stem: &str) -> Option<ParsedEpisode> {
+        let regex = match &self.compiled_regex {
+            Some(regex) => regex,
+            None => {
+                error!(
+                    "Episode parse rule \"{}\" was used before being compiled",
+                    self.name
+                );
+                panic!();
+            }
+        };
+        let captures = match regex.captures(file_stem) {
+            Ok(Some(captures)) => captures,
+            Ok(None) => return None,
+            Err(err) => {
+                warn!(
+                    "Episode parse rule \"{}\" failed on: {}. Err: {}",
+                    self.name, file_stem, err
+                );
+                return None;
+            }
+        };
+        let capture = |name: &str| captures.name(name).map(|capture| capture.as_str());
+
+        let mut date: Option<String> = None;
+        let season: i32;
+        let first_episode: i32;
+        if let Some(raw_date) = capture("date") {
+            //Daily shows use the year as the season and MMDD as the episode
+            let parts: Vec<&str> = raw_date
+                .split(|c: char| !c.is_ascii_digit())
+                .filter(|part| !part.is_empty())
+                .collect();
+            if parts.len() != 3 {
+                return self.invalid(file_stem, "date");
+            }
+            let (year, month, day) = match (
+                parts[0].parse::<i32>(),
+                parts[1].parse::<i32>(),
+                parts[2].parse::<i32>(),
+            ) {
+                (Ok(year), Ok(month), Ok(day)) => (year, month, day),
+                _ => return self.invalid(file_stem, "date"),
+            };
+            date = Some(format!("{:04}-{:02}-{:02}", year, month, day));
+            season = year;
+            first_episode = month * 100 + day;
+        } else {
+            //Absolute numbering has no season, treat it as the first season
+            season = match capture("season") {
+                Some(season) => match season.parse::<i32>() {
+                    Ok(season) => season,
+                    Err(_) => return self.invalid(file_stem, "season"),
+                },
+                None => 1,
+            };
+            first_episode = match capture("episode").map(|episode| episode.parse::<i32>()) {
+                Some(Ok(episode)) => episode,
+                _ => return self.invalid(file_stem, "episode"),
+            };
+        }
+
+        let mut episodes: Vec<i32> = vec![first_episode];
+        if let Some(last_episode) = capture("last_episode") {
+            match last_episode.parse::<i32>() {
+                Ok(last_episode) if last_episode > first_episode => {
+                    episodes.extend(first_episode + 1..=last_episode);
+                }
+                _ => return self.invalid(file_stem, "last_episode"),
+            }
+        }
+
+        //Release tags after the title, like "Title HDTV-720p", aren't part of it
+        let title = capture("title")
+            .map(strip_release_tags)
+            .filter(|title| !title.is_empty());
+
+        Some(ParsedEpisode {
+            rule_name: self.name.clone(),
+            season,
+            episodes,
+            date,
+            title,
+            release_source: parse_release_source(file_stem),
+        })
+    }
+
+    fn invalid(&self, file_stem: &str, capture_name: &str) -> Option<ParsedEpisode> {
+        warn!(
+            "Episode parse rule \"{}\" matched \"{}\" but the {} couldn't be parsed",
+            self.name, file_stem, capture_name
+        );
+        None
+    }
+}
+
+///Information pulled out of the file name of an episode
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParsedEpisode {
+    pub rule_name: String,
+    pub season: i32,
+    pub episodes: Vec<i32>,
+    //YYYY-MM-DD
+    pub date: Option<String>,
+    pub title: Option<String>,
+    //Such as HDTV, WEB-DL or BluRay
+    pub release_source: Option<String>,
+}
+
+///Tries each rule in order and returns the result of the first one that matches
+pub fn parse_episode(rules: &[EpisodeParseRule], file_stem: &str) -> Option<ParsedEpisode> {
+    rules.iter().find_map(|rule| rule.parse(file_stem))
+}
+
+lazy_static! {
+    //WEB on its own is only recognised in capitals so it isn't confused with a word in a title
+    static ref RELEASE_SOURCE: regex::Regex = regex::Regex::new(
+        r"(?i)(?:^|[\s._\-\[(])(?P<source>web[\s._-]?dl|web[\s._-]?rip|(?-i:WEB)|hdtv|pdtv|sdtv|blu[\s._-]?ray|b[dr][\s._-]?rip|bdremux|remux|dvd[\s._-]?rip|dvd)(?:$|[\s._\-\])])"
+    )
+    .unwrap();
+    static ref RELEASE_TAG: regex::Regex = regex::Regex::new(
+        r"(?i)(?:^|[\s._\-\[(])(?:web[\s._-]?dl|web[\s._-]?rip|(?-i:WEB)|hdtv|pdtv|sdtv|blu[\s._-]?ray|b[dr][\s._-]?rip|bdremux|remux|dvd[\s._-]?rip|dvd|\d{3,4}[pi]|4k|uhd|[xh]\.?26[45]|hevc|avc|proper|repack)(?:$|[\s._\-\])])"
+    )
+    .unwrap();
+}
+
+///The source of a release from a tag in the file name, named the same way whichever spelling is
+///used, such as WEBDL or Web-DL becoming WEB-DL
+pub fn parse_release_source(file_stem: &str) -> Option<String> {
+    let source = RELEASE_SOURCE.captures(file_stem)?["source"]
+        .to_lowercase()
+        .replace([' ', '.', '_', '-'], "");
+    let release_source = match source.as_str() {
+        "webdl" => "WEB-DL",
+        "webrip" => "WEBRip",
+        "web" => "WEB",
+        "hdtv" => "HDTV",
+        "pdtv" => "PDTV",
+        "sdtv" => "SDTV",
+        "bluray" | "bdrip" | "brrip" | "bdremux" | "remux" => "BluRay",
+        _ => "DVD",
+    };
+    Some(release_source.to_string())
+}
+
+///Cuts a title off at the first release tag, such as the source, resolution or codec
+pub fn strip_release_tags(title: &str) -> String {
+    let title = match RELEASE_TAG.find(title) {
+        Some(release_tag) => &title[..release_tag.start()],
+        None => title,
+    };
+    title
+        .trim_end_matches(|c: char| c.is_whitespace() || matches!(c, '.' | '_' | '-'))
+        .trim()
+        .to_string()
+}
+
+///Splits a show's folder name like "Alcatraz (2012)" into its title and year
+pub fn parse_show_title(show_directory: &str) -> (String, Option<i32>) {
+    lazy_static! {
+        static ref REGEX: regex::Regex = regex::Regex::new(
+            r"^(?P<title>.*?[^\s._-])[\s._-]*\((?P<year>(?:18|19|20)\d{2})\)\s*$"
+        )
+        .unwrap();
+    }
+
+    match REGEX.captures(show_directory.trim()) {
+        Some(captures) => (
+            captures["title"].to_string(),
+            captures["year"].parse::<i32>().ok(),
+        ),
+        None => (show_directory.trim().to_string(), None),
+    }
+}
+
+///Title and year of a movie pulled out of its file or folder name
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParsedMovie {
+    pub title: String,
+    pub year: i32,
+}
+
+///Recognises movies named "Title (Year)", checking the file name first and then
+///the name of the folder the file is in
+pub fn parse_movie(full_path: &Path) -> Option<ParsedMovie> {
+    lazy_static! {
+        static ref REGEX: regex::Regex =
+            regex::Regex::new(r"^(?P<title>.*?[^\s._-])[\s._-]*\((?P<year>(?:18|19|20)\d{2})\)")
+                .unwrap();
+    }
+
+    let parse = |name: &str| -> Option<ParsedMovie> {
+        let captures = REGEX.captures(name)?;
+        Some(ParsedMovie {
+            title: captures["title"].trim().to_string(),
+            year: captures["year"].parse::<i32>().ok()?,
+        })
+    };
+
+    parse(&get_file_stem(full_path)).or_else(|| {
+        full_path
+            .parent()
+            .and_then(|parent| parent.file_name())
+            .and_then(|directory_name| parse(&directory_name.to_string_lossy()))
+    })
+}
+
+///Recognises specials, extras and trailers from the folder they are in, such as Specials, Season 0,
+///Extras, Featurettes or Trailers, or from a suffix like "-trailer" on the file name. Returns the
+///designation and the path the file would have without the folder or suffix, which is used to find
+///the show or movie it belongs to. Only folders below the show or movie folder are checked, so
+///a root directory or show that happens to be called Extras doesn't make all of its files extras
+pub fn parse_extra(full_path: &Path, root_path: &Path) -> Option<(Designation, PathBuf)> {
+    lazy_static! {
+        static ref SEASON_ZERO: regex::Regex =
+            regex::Regex::new(r"(?i)^season[\s._-]*0+$").unwrap();
+    }
+
+    //The folder closest to the file wins, folders directly in the root directory are the show or
+    //movie folder
+    for directory in full_path
+        .ancestors()
+        .skip(1)
+        .take_while(|directory| *directory != root_path && directory.parent() != Some(root_path))
+    {
+        let directory_name = match directory.file_name() {
+            Some(directory_name) => directory_name.to_string_lossy().to_lowercase(),
+            None => break,
+        };
+        let designation = match directory_name.as_str() {
+            "specials" => Designation::Special,
+            "extras" | "featurettes" | "behind the scenes" | "deleted scenes" | "interviews"
+            | "scenes" | "shorts" => Designation::Extra,
+            "trailers" => Designation::Trailer,
+            _ if SEASON_ZERO.is_match(&directory_name) => Designation::Special,
+            _ => continue,
+        };
+        return Some((
+            designation,
+            directory.parent()?.join(full_path.file_name()?),
+        ));
+    }
+
+    let file_stem = get_file_stem(full_path);
+    for (suffix, designation) in [
+        ("-trailer", Designation::Trailer),
+        ("-featurette", Designation::Extra),
+        ("-behindthescenes", Designation::Extra),
+        ("-deleted", Designation::Extra),
+        ("-interview", Designation::Extra),
+        ("-scene", Designation::Extra),
+        ("-short", Designation::Extra),
+    ] {
+        let split_index = match file_stem.len().checked_sub(suffix.len()) {
+            Some(split_index) if file_stem.is_char_boundary(split_index) => split_index,
+            _ => continue,
+        };
+        if file_stem[split_index..].eq_ignore_ascii_case(suffix) {
+            let owner_file_stem = file_stem[..split_index].trim_end();
+            let owner_file_name = match full_path.extension() {
+                Some(extension) => format!("{}.{}", owner_file_stem, extension.to_string_lossy()),
+                None => owner_file_stem.to_string(),
+            };
+            return Some((designation, full_path.with_file_name(owner_file_name)));
+        }
+    }
+    None
+}
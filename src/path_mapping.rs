@@ -0,0 +1,143 @@
+//!Translates paths between how the server sees them and how a worker sees them, so a library at
+//!`/mnt/media` on the server can be reached at `\\nas\media` from a Windows worker
+use {
+    crate::{encode::Encode, pathbuf_to_string},
+    serde::{Deserialize, Serialize},
+    std::path::{Path, PathBuf},
+};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PathMapping {
+    pub server_prefix: String,
+    pub worker_prefix: String,
+}
+
+impl PathMapping {
+    pub fn new(server_prefix: &str, worker_prefix: &str) -> Self {
+        Self {
+            server_prefix: server_prefix.to_string(),
+            worker_prefix: worker_prefix.to_string(),
+        }
+    }
+}
+
+///Mappings for a single worker, these are tried before the global mappings
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WorkerPathMappings {
+    pub worker_uid: i32,
+    pub path_mappings: Vec<PathMapping>,
+}
+
+///The mappings that apply to one worker
+#[derive(Clone, Debug, Default)]
+pub struct PathMapper {
+    worker_mappings: Vec<PathMapping>,
+    global_mappings: Vec<PathMapping>,
+}
+
+impl PathMapper {
+    pub fn new(
+        global_mappings: &[PathMapping],
+        worker_path_mappings: &[WorkerPathMappings],
+        worker_uid: Option<i32>,
+    ) -> Self {
+        Self {
+            worker_mappings: worker_path_mappings
+                .iter()
+                .filter(|worker_path_mappings| Some(worker_path_mappings.worker_uid) == worker_uid)
+                .flat_map(|worker_path_mappings| worker_path_mappings.path_mappings.clone())
+                .collect(),
+            global_mappings: global_mappings.to_vec(),
+        }
+    }
+
+    pub fn to_worker_path(&self, path: &Path) -> PathBuf {
+        self.translate(path, true)
+    }
+
+    pub fn to_server_path(&self, path: &Path) -> PathBuf {
+        self.translate(path, false)
+    }
+
+    ///Translates every path the worker reads from or writes to before an Encode is sent to it
+    pub fn encode_to_worker(&self, mut encode: Encode) -> Encode {
+        encode.source_path = self.to_worker_path(&encode.source_path);
+        encode.target_path = self.to_worker_path(&encode.target_path);
+        encode.temp_target_path = self.to_worker_path(&encode.temp_target_path);
+        encode
+    }
+
+    ///Reverses encode_to_worker for an Encode sent back by the worker
+    pub fn encode_to_server(&self, mut encode: Encode) -> Encode {
+        encode.source_path = self.to_server_path(&encode.source_path);
+        encode.target_path = self.to_server_path(&encode.target_path);
+        encode.temp_target_path = self.to_server_path(&encode.temp_target_path);
+        encode
+    }
+
+    ///The longest matching prefix from the worker's own mappings is used, falling back to the
+    ///global mappings. Paths that don't match anything are returned unchanged
+    fn translate(&self, path: &Path, to_worker: bool) -> PathBuf {
+        let path_string = pathbuf_to_string(path);
+        for mappings in [&self.worker_mappings, &self.global_mappings] {
+            let best_match = mappings
+                .iter()
+                .map(|mapping| {
+                    if to_worker {
+                        (&mapping.server_prefix, &mapping.worker_prefix)
+                    } else {
+                        (&mapping.worker_prefix, &mapping.server_prefix)
+                    }
+                })
+                .filter_map(|(from, to)| {
+                    strip_path_prefix(&path_string, from)
+                        .map(|remainder| (from.len(), to, remainder))
+                })
+                .max_by_key(|(from_length, _, _)| *from_length);
+            if let Some((_, to, remainder)) = best_match {
+                return join_with_separator(to, &remainder);
+            }
+        }
+        path.to_path_buf()
+    }
+}
+
+fn split_path(path: &str) -> Vec<&str> {
+    path.split(['/', '\\']).collect()
+}
+
+///Returns the components of path after prefix, only matching whole components so that
+///`/mnt/media` doesn't match `/mnt/media2`
+fn strip_path_prefix<'a>(path: &'a str, prefix: &str) -> Option<Vec<&'a str>> {
+    let prefix = prefix.trim_end_matches(['/', '\\']);
+    if prefix.is_empty() {
+        return None;
+    }
+    let path_components = split_path(path);
+    let prefix_components = split_path(prefix);
+    if path_components.len() < prefix_components.len()
+        || path_components[..prefix_components.len()] != prefix_components[..]
+    {
+        return None;
+    }
+    Some(path_components[prefix_components.len()..].to_vec())
+}
+
+///Uses the separator style of the prefix being mapped to for the rest of the path
+fn join_with_separator(prefix: &str, remainder: &[&str]) -> PathBuf {
+    let separator = if prefix.contains('\\') && !prefix.contains('/') {
+        "\\"
+    } else {
+        "/"
+    };
+    let prefix = prefix.trim_end_matches(['/', '\\']);
+    if remainder.is_empty() {
+        return PathBuf::from(prefix);
+    }
+    PathBuf::from(format!(
+        "{}{}{}",
+        prefix,
+        separator,
+        remainder.join(separator)
+    ))
+}
@@ -0,0 +1,552 @@
+//!Reads the width, height, frame rate, length and streams of media files with an external tool.
+//!Each tool is a MediaProber so the one used can be picked in the config, streams are found by
+//!their type rather than their position so files with the audio first are read correctly
+use {
+    crate::{
+        pathbuf_to_string,
+        profile::{Container, Profile, ResolutionStandard},
+        stream::{normalise_language, normalise_mediainfo_codec, Stream, StreamType},
+    },
+    serde::{Deserialize, Serialize},
+    serde_json::Value,
+    std::{fmt, path::Path, process::Command, str::FromStr},
+};
+
+///Why a file couldn't be profiled
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProbeError {
+    //The prober couldn't be run, such as when it isn't installed
+    Command(String),
+    //The prober ran but couldn't read the file
+    Failed(String),
+    InvalidOutput(String),
+    NoVideoStream,
+    InvalidField { field: String, value: String },
+}
+
+impl fmt::Display for ProbeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Command(err) => write!(f, "Failed to run prober: {}", err),
+            Self::Failed(err) => write!(f, "Prober couldn't read the file: {}", err),
+            Self::InvalidOutput(err) => write!(f, "Prober output couldn't be parsed: {}", err),
+            Self::NoVideoStream => write!(f, "File has no video stream"),
+            Self::InvalidField { field, value } => {
+                write!(f, "Invalid value for {}: {}", field, value)
+            }
+        }
+    }
+}
+
+pub trait MediaProber: Send + Sync {
+    fn probe(&self, full_path: &Path) -> Result<Profile, ProbeError>;
+}
+
+///Which MediaProber is used to profile files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProberBackend {
+    #[default]
+    Ffprobe,
+    Mediainfo,
+}
+
+impl ProberBackend {
+    pub fn get_prober(&self) -> Box<dyn MediaProber> {
+        match self {
+            Self::Ffprobe => Box::new(FfprobeProber),
+            Self::Mediainfo => Box::new(MediainfoProber),
+        }
+    }
+}
+
+///Runs the command and returns what it printed, an error if it couldn't be run or failed
+fn run_prober(command: &mut Command) -> Result<String, ProbeError> {
+    let output = command
+        .output()
+        .map_err(|err| ProbeError::Command(err.to_string()))?;
+    if !output.status.success() {
+        return Err(ProbeError::Failed(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+///The format and streams of a file as ffprobe JSON
+pub fn run_ffprobe(full_path: &Path) -> Result<String, ProbeError> {
+    run_prober(Command::new("ffprobe").args([
+        "-v",
+        "error",
+        "-print_format",
+        "json",
+        "-show_format",
+        "-show_streams",
+        &pathbuf_to_string(full_path),
+    ]))
+}
+
+fn parse_json(json: &str) -> Result<Value, ProbeError> {
+    serde_json::from_str(json).map_err(|err| ProbeError::InvalidOutput(err.to_string()))
+}
+
+///Container from the file's extension, none if it doesn't have one
+fn get_container(extension: Option<&str>) -> Option<Container> {
+    extension.map(|extension| Container::from_extension(extension.to_string()))
+}
+
+fn build_profile(
+    width: Option<i32>,
+    height: Option<i32>,
+    framerate: Option<f64>,
+    length_time: Option<f64>,
+    container: Option<Container>,
+    streams: Vec<Stream>,
+) -> Profile {
+    Profile {
+        width,
+        height,
+        framerate,
+        length_time,
+        resolution_standard: width.map(ResolutionStandard::get_resolution_standard_from_width),
+        container,
+        streams,
+    }
+}
+
+fn parse_str<T: FromStr>(value: Option<&str>) -> Option<T> {
+    value.and_then(|value| value.parse::<T>().ok())
+}
+
+pub struct FfprobeProber;
+
+impl MediaProber for FfprobeProber {
+    fn probe(&self, full_path: &Path) -> Result<Profile, ProbeError> {
+        parse_ffprobe_json(&run_ffprobe(full_path)?, full_path)
+    }
+}
+
+fn parse_fraction(fraction: &str) -> Option<f64> {
+    let (numerator, denominator) = fraction.split_once('/')?;
+    let numerator = numerator.parse::<f64>().ok()?;
+    let denominator = denominator.parse::<f64>().ok()?;
+    if denominator == 0.0 {
+        None
+    } else {
+        Some(numerator / denominator)
+    }
+}
+
+///Frame rates are given as a fraction such as 24000/1001, 0/0 when unknown
+fn parse_frame_rate(frame_rate: &str) -> Option<f64> {
+    parse_fraction(frame_rate).filter(|frame_rate| *frame_rate != 0.0)
+}
+
+pub fn parse_ffprobe_json(json: &str, full_path: &Path) -> Result<Profile, ProbeError> {
+    let value = parse_json(json)?;
+    let streams = value["streams"]
+        .as_array()
+        .ok_or_else(|| ProbeError::InvalidOutput("Missing streams".to_string()))?;
+    let video_stream = streams
+        .iter()
+        .find(|stream| {
+            //Cover art is reported as a video stream
+            stream["codec_type"] == "video" && stream["disposition"]["attached_pic"] != 1
+        })
+        .ok_or(ProbeError::NoVideoStream)?;
+
+    let dimension = |field: &str| -> Result<Option<i32>, ProbeError> {
+        match &video_stream[field] {
+            Value::Null => Ok(None),
+            value => value
+                .as_i64()
+                .and_then(|value| i32::try_from(value).ok())
+                .map(Some)
+                .ok_or_else(|| ProbeError::InvalidField {
+                    field: field.to_string(),
+                    value: value.to_string(),
+                }),
+        }
+    };
+    //The average frame rate is preferred as the base frame rate of variable frame rate files is
+    //often far higher than the rate they play at
+    let framerate = ["avg_frame_rate", "r_frame_rate"]
+        .iter()
+        .find_map(|field| video_stream[field].as_str().and_then(parse_frame_rate));
+    let length_time = match value["format"]["duration"]
+        .as_str()
+        .or_else(|| video_stream["duration"].as_str())
+    {
+        Some(duration) => Some(
+            duration
+                .parse::<f64>()
+                .map_err(|_| ProbeError::InvalidField {
+                    field: "duration".to_string(),
+                    value: duration.to_string(),
+                })?,
+        ),
+        None => None,
+    };
+    Ok(build_profile(
+        dimension("width")?,
+        dimension("height")?,
+        framerate,
+        length_time,
+        get_container(
+            full_path
+                .extension()
+                .and_then(|extension| extension.to_str()),
+        ),
+        streams.iter().filter_map(parse_ffprobe_stream).collect(),
+    ))
+}
+
+///Values that can't be read are left out rather than failing the whole profile as they only
+///describe the stream
+fn parse_ffprobe_stream(stream: &Value) -> Option<Stream> {
+    if stream["disposition"]["attached_pic"] == 1 {
+        return None;
+    }
+    let stream_type = StreamType::from_name(stream["codec_type"].as_str()?)?;
+    let codec = stream["codec_name"]
+        .as_str()
+        .unwrap_or("unknown")
+        .to_string();
+    let tags = &stream["tags"];
+    //Matroska keeps the bitrate in the tags instead
+    let bitrate = parse_str(
+        stream["bit_rate"]
+            .as_str()
+            .or_else(|| tags["BPS"].as_str())
+            .or_else(|| tags["BPS-eng"].as_str()),
+    );
+    let bit_depth = parse_str(stream["bits_per_raw_sample"].as_str())
+        .or_else(|| {
+            stream["bits_per_sample"]
+                .as_i64()
+                .filter(|bit_depth| *bit_depth > 0)
+                .map(|bit_depth| bit_depth as i32)
+        })
+        .or_else(|| {
+            stream["pix_fmt"]
+                .as_str()
+                .and_then(get_pixel_format_bit_depth)
+        });
+    let mut parsed = Stream {
+        stream_index: stream["index"].as_i64()? as i32,
+        stream_type,
+        profile: stream["profile"]
+            .as_str()
+            .filter(|profile| *profile != "unknown")
+            .map(str::to_string),
+        level: stream["level"]
+            .as_i64()
+            .and_then(|level| format_ffprobe_level(&codec, level)),
+        codec,
+        bit_depth,
+        bitrate,
+        channels: stream["channels"].as_i64().map(|channels| channels as i32),
+        language: tags["language"].as_str().and_then(normalise_language),
+        is_default: stream["disposition"]["default"] == 1,
+        is_forced: stream["disposition"]["forced"] == 1,
+        transfer_characteristics: None,
+        colour_primaries: None,
+        matrix_coefficients: None,
+        mastering_display: None,
+        content_light_level: None,
+    };
+    if stream_type == StreamType::Video {
+        read_ffprobe_colour(stream, &mut parsed);
+    }
+    Some(parsed)
+}
+
+///HDR10 metadata is in the stream's side data, which only some containers such as Matroska
+///carry at the stream level
+fn read_ffprobe_colour(stream: &Value, video_stream: &mut Stream) {
+    let field = |name: &str| {
+        stream[name]
+            .as_str()
+            .filter(|value| *value != "unknown" && *value != "reserved")
+            .map(str::to_string)
+    };
+    video_stream.transfer_characteristics = field("color_transfer");
+    video_stream.colour_primaries = field("color_primaries");
+    video_stream.matrix_coefficients = field("color_space");
+    for side_data in stream["side_data_list"].as_array().into_iter().flatten() {
+        match side_data["side_data_type"].as_str() {
+            Some("Mastering display metadata") => {
+                let coordinate = |name: &str| side_data[name].as_str().and_then(parse_fraction);
+                let point = |colour: &str| {
+                    Some((
+                        coordinate(&format!("{}_x", colour))?,
+                        coordinate(&format!("{}_y", colour))?,
+                    ))
+                };
+                video_stream.mastering_display = (|| {
+                    Some(format_mastering_display(
+                        point("red")?,
+                        point("green")?,
+                        point("blue")?,
+                        point("white_point")?,
+                        (coordinate("min_luminance")?, coordinate("max_luminance")?),
+                    ))
+                })();
+            }
+            Some("Content light level metadata") => {
+                video_stream.content_light_level = side_data["max_content"]
+                    .as_i64()
+                    .zip(side_data["max_average"].as_i64())
+                    .map(|(max_content, max_average)| format!("{},{}", max_content, max_average));
+            }
+            _ => {}
+        }
+    }
+}
+
+///Takes the chromaticity coordinates of the display's red, green and blue primaries and white
+///point and its minimum and maximum luminance in cd/m2. x265 counts coordinates in steps of
+///0.00002 and luminance in steps of 0.0001 cd/m2
+fn format_mastering_display(
+    red: (f64, f64),
+    green: (f64, f64),
+    blue: (f64, f64),
+    white_point: (f64, f64),
+    (min_luminance, max_luminance): (f64, f64),
+) -> String {
+    let point = |(x, y): (f64, f64)| format!("{},{}", (x * 50000.0).round(), (y * 50000.0).round());
+    format!(
+        "G({})B({})R({})WP({})L({},{})",
+        point(green),
+        point(blue),
+        point(red),
+        point(white_point),
+        (max_luminance * 10000.0).round(),
+        (min_luminance * 10000.0).round()
+    )
+}
+
+///ffprobe gives levels as whole numbers, such as 41 for level 4.1 of h264 and 123 for level
+///4.1 of hevc. They're written the way mediainfo writes them
+fn format_ffprobe_level(codec: &str, level: i64) -> Option<String> {
+    let level = match codec {
+        _ if level <= 0 => return None,
+        "h264" => level,
+        "hevc" => level / 3,
+        _ => return Some(level.to_string()),
+    };
+    if level % 10 == 0 {
+        Some((level / 10).to_string())
+    } else {
+        Some(format!("{}.{}", level / 10, level % 10))
+    }
+}
+
+///Such as 10 for yuv420p10le, other yuv formats are 8 bit
+fn get_pixel_format_bit_depth(pixel_format: &str) -> Option<i32> {
+    for bit_depth in [10, 12, 16] {
+        if pixel_format.contains(&format!("p{}", bit_depth)) {
+            return Some(bit_depth);
+        }
+    }
+    if pixel_format.starts_with("yuv") {
+        Some(8)
+    } else {
+        None
+    }
+}
+
+pub struct MediainfoProber;
+
+impl MediaProber for MediainfoProber {
+    fn probe(&self, full_path: &Path) -> Result<Profile, ProbeError> {
+        let json = run_prober(
+            Command::new("mediainfo").args(["--output=JSON", &pathbuf_to_string(full_path)]),
+        )?;
+        parse_mediainfo_json(&json, full_path)
+    }
+}
+
+pub fn parse_mediainfo_json(json: &str, full_path: &Path) -> Result<Profile, ProbeError> {
+    let value = parse_json(json)?;
+    let tracks = value["media"]["track"]
+        .as_array()
+        .ok_or_else(|| ProbeError::InvalidOutput("Missing tracks".to_string()))?;
+    let find_track = |track_type: &str| tracks.iter().find(|track| track["@type"] == track_type);
+    let general_track = find_track("General");
+    let video_track = find_track("Video").ok_or(ProbeError::NoVideoStream)?;
+
+    //mediainfo gives every value as a string
+    fn parse_field<T: FromStr>(
+        track: Option<&Value>,
+        field: &str,
+    ) -> Result<Option<T>, ProbeError> {
+        match track.and_then(|track| track[field].as_str()) {
+            Some(value) => value
+                .parse::<T>()
+                .map(Some)
+                .map_err(|_| ProbeError::InvalidField {
+                    field: field.to_string(),
+                    value: value.to_string(),
+                }),
+            None => Ok(None),
+        }
+    }
+    let length_time = match parse_field(general_track, "Duration")? {
+        Some(length_time) => Some(length_time),
+        None => parse_field(Some(video_track), "Duration")?,
+    };
+    let extension = general_track
+        .and_then(|track| track["FileExtension"].as_str())
+        .or_else(|| {
+            full_path
+                .extension()
+                .and_then(|extension| extension.to_str())
+        });
+    Ok(build_profile(
+        parse_field(Some(video_track), "Width")?,
+        parse_field(Some(video_track), "Height")?,
+        parse_field(Some(video_track), "FrameRate")?,
+        length_time,
+        get_container(extension),
+        tracks
+            .iter()
+            .filter(|track| track["@type"] != "General")
+            .enumerate()
+            .filter_map(|(position, track)| parse_mediainfo_track(track, position))
+            .collect(),
+    ))
+}
+
+fn parse_mediainfo_track(track: &Value, position: usize) -> Option<Stream> {
+    let stream_type = StreamType::from_name(track["@type"].as_str()?)?;
+    let field = |name: &str| track[name].as_str();
+    let is_video = stream_type == StreamType::Video;
+    let colour_field =
+        |name: &str, normalise: fn(&str) -> String| field(name).filter(|_| is_video).map(normalise);
+    Some(Stream {
+        //Falls back to the track's position when StreamOrder isn't a plain number, such as 0-1
+        //for streams inside a program
+        stream_index: parse_str(field("StreamOrder")).unwrap_or(position as i32),
+        stream_type,
+        codec: field("Format")
+            .map(normalise_mediainfo_codec)
+            .unwrap_or_else(|| "unknown".to_string()),
+        profile: field("Format_Profile").map(str::to_string),
+        level: field("Format_Level").map(str::to_string),
+        bit_depth: parse_str(field("BitDepth")),
+        bitrate: parse_str(field("BitRate")),
+        channels: parse_str(field("Channels")),
+        language: field("Language").and_then(normalise_language),
+        is_default: field("Default") == Some("Yes"),
+        is_forced: field("Forced") == Some("Yes"),
+        transfer_characteristics: colour_field(
+            "transfer_characteristics",
+            normalise_mediainfo_transfer,
+        ),
+        colour_primaries: colour_field("colour_primaries", normalise_mediainfo_primaries),
+        matrix_coefficients: colour_field("matrix_coefficients", normalise_mediainfo_matrix),
+        mastering_display: field("MasteringDisplay_ColorPrimaries")
+            .zip(field("MasteringDisplay_Luminance"))
+            .filter(|_| is_video)
+            .and_then(|(primaries, luminance)| {
+                parse_mediainfo_mastering_display(primaries, luminance)
+            }),
+        content_light_level: field("MaxCLL")
+            .zip(field("MaxFALL"))
+            .filter(|_| is_video)
+            .and_then(|(max_content, max_average)| {
+                Some(format!(
+                    "{},{}",
+                    parse_mediainfo_number(max_content)?.round(),
+                    parse_mediainfo_number(max_average)?.round()
+                ))
+            }),
+    })
+}
+
+//Colour values from mediainfo are changed to the names ffmpeg uses, the same as codecs
+fn normalise_mediainfo_transfer(transfer: &str) -> String {
+    match transfer {
+        "PQ" => "smpte2084",
+        "HLG" => "arib-std-b67",
+        "BT.709" => "bt709",
+        "BT.601" => "smpte170m",
+        "BT.2020 (10-bit)" => "bt2020-10",
+        "BT.2020 (12-bit)" => "bt2020-12",
+        transfer => return transfer.to_lowercase(),
+    }
+    .to_string()
+}
+
+fn normalise_mediainfo_primaries(primaries: &str) -> String {
+    match primaries {
+        "BT.2020" => "bt2020",
+        "BT.709" => "bt709",
+        "BT.601 NTSC" => "smpte170m",
+        "BT.601 PAL" => "bt470bg",
+        "Display P3" => "smpte432",
+        "DCI P3" => "smpte431",
+        primaries => return primaries.to_lowercase(),
+    }
+    .to_string()
+}
+
+fn normalise_mediainfo_matrix(matrix: &str) -> String {
+    match matrix {
+        "BT.2020 non-constant" => "bt2020nc",
+        "BT.2020 constant" => "bt2020c",
+        "BT.709" => "bt709",
+        "BT.601" => "smpte170m",
+        matrix => return matrix.to_lowercase(),
+    }
+    .to_string()
+}
+
+///The first number in a value such as 1000 cd/m2
+fn parse_mediainfo_number(value: &str) -> Option<f64> {
+    parse_str(value.split_whitespace().next())
+}
+
+///mediainfo names the primaries of common mastering displays, such as Display P3, and gives the
+///coordinates of the rest as R: x=0.680000 y=0.320000, G: ..., B: ..., White point: ...
+///Luminance is given as min: 0.0050 cd/m2, max: 1000 cd/m2
+fn parse_mediainfo_mastering_display(primaries: &str, luminance: &str) -> Option<String> {
+    const D65: (f64, f64) = (0.3127, 0.329);
+    let (red, green, blue, white_point) = match primaries {
+        "Display P3" => ((0.68, 0.32), (0.265, 0.69), (0.15, 0.06), D65),
+        "BT.2020" => ((0.708, 0.292), (0.17, 0.797), (0.131, 0.046), D65),
+        primaries => {
+            let point = |name: &str| -> Option<(f64, f64)> {
+                let coordinates = primaries
+                    .split(", ")
+                    .find_map(|part| part.strip_prefix(name))?;
+                let coordinate = |axis: &str| {
+                    coordinates
+                        .split_whitespace()
+                        .find_map(|value| parse_str(value.strip_prefix(axis)))
+                };
+                Some((coordinate("x=")?, coordinate("y=")?))
+            };
+            (
+                point("R: ")?,
+                point("G: ")?,
+                point("B: ")?,
+                point("White point: ")?,
+            )
+        }
+    };
+    let luminance_value = |name: &str| {
+        luminance
+            .split(", ")
+            .find_map(|part| part.strip_prefix(name))
+            .and_then(parse_mediainfo_number)
+    };
+    Some(format_mastering_display(
+        red,
+        green,
+        blue,
+        white_point,
+        (luminance_value("min: ")?, luminance_value("max: ")?),
+    ))
+}
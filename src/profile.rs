@@ -1,8 +1,7 @@
 use {
-    crate::pathbuf_to_string,
+    crate::stream::Stream,
     serde::{Deserialize, Serialize},
-    serde_json::{Error, Value},
-    std::{fmt, path::Path, process::Command, str::from_utf8},
+    std::fmt,
     tracing::error,
 };
 
@@ -71,7 +70,7 @@ pub enum AspectRatio {
     TwentyOneByNine,
 }
 
-#[derive(Clone, Debug, Copy, Serialize, Deserialize)]
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum VideoCodec {
     H264,
     //Chromecast:
@@ -93,7 +92,7 @@ pub enum VideoCodec {
     //  with Google TV:     VP9 Profile-2 up to 4Kx2K@60fps
 }
 
-#[derive(Clone, Debug, Copy, Serialize, Deserialize)]
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum AudioCodec {
     FLAC, //(up to 96kHz/24-bit)
     HEAAC,
@@ -105,7 +104,32 @@ pub enum AudioCodec {
     WebM,
 }
 
-#[derive(Clone, Debug, Copy, Serialize, Deserialize)]
+///Read from the transfer characteristics of a video stream
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DynamicRange {
+    SDR,
+    //PQ transfer, usually with mastering display metadata
+    HDR10,
+    HLG,
+}
+
+impl DynamicRange {
+    pub fn is_hdr(&self) -> bool {
+        *self != Self::SDR
+    }
+}
+
+impl fmt::Display for DynamicRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::SDR => write!(f, "SDR"),
+            Self::HDR10 => write!(f, "HDR10"),
+            Self::HLG => write!(f, "HLG"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Container {
     UNKNOWN = 0,
     MP4 = 1,
@@ -155,7 +179,7 @@ impl fmt::Display for Container {
     }
 }
 
-#[derive(Clone, Debug, Copy, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Profile {
     pub width: Option<i32>,                              //Pixels
     pub height: Option<i32>,                             //Pixels
@@ -164,8 +188,7 @@ pub struct Profile {
     pub resolution_standard: Option<ResolutionStandard>, //Discounts the height difference, based on width
     //pub aspect_ratio: AspectRatio,                            //eg. SixteenByNine is 16:9
     pub container: Option<Container>, //Represents the file extension rather than specifically the container, as this may not be the case
-                                      //TODO: Add current video information
-                                      //TODO: Add current audio information
+    pub streams: Vec<Stream>,         //Every video, audio and subtitle stream in the file
 }
 
 impl Profile {
@@ -198,68 +221,7 @@ impl Profile {
             length_time,
             resolution_standard,
             container,
-        }
-    }
-
-    ///Create profile from a pathbuf
-    pub fn from_file(full_path: &Path) -> Option<Profile> {
-        let buffer;
-        //linux & friends
-        buffer = Command::new("mediainfo")
-            .args(&["--output=JSON", &pathbuf_to_string(full_path)])
-            .output()
-            .unwrap_or_else(|err| {
-                error!("Failed to execute process for mediainfo. Err: {}", err);
-                panic!();
-            });
-        let temp: Result<Value, Error> = serde_json::from_str(from_utf8(&buffer.stdout).unwrap());
-        if let Ok(value) = temp {
-            let width = value["media"]["track"][1]["Width"]
-                .to_string()
-                .strip_prefix('"')?
-                .strip_suffix('"')?
-                .parse::<i32>()
-                .unwrap();
-            return Some(Self {
-                width: Some(width),
-                height: Some(
-                    value["media"]["track"][1]["Height"]
-                        .to_string()
-                        .strip_prefix('"')?
-                        .strip_suffix('"')?
-                        .parse::<i32>()
-                        .unwrap(),
-                ),
-                framerate: Some(
-                    value["media"]["track"][1]["FrameRate"]
-                        .to_string()
-                        .strip_prefix('"')?
-                        .strip_suffix('"')?
-                        .parse::<f64>()
-                        .unwrap(),
-                ),
-                length_time: Some(
-                    value["media"]["track"][0]["Duration"]
-                        .to_string()
-                        .strip_prefix('"')?
-                        .strip_suffix('"')?
-                        .parse::<f64>()
-                        .unwrap(),
-                ),
-                resolution_standard: Some(ResolutionStandard::get_resolution_standard_from_width(
-                    width,
-                )),
-                container: Some(Container::from_extension(
-                    value["media"]["track"][0]["FileExtension"]
-                        .to_string()
-                        .strip_prefix('"')?
-                        .strip_suffix('"')?
-                        .parse::<String>()
-                        .unwrap(),
-                )),
-            });
-        } else {
-            None
+            streams: Vec::new(),
         }
     }
 }
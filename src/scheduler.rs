@@ -1,10 +1,16 @@
 use {
     crate::{
-        config::ServerConfig, database::establish_connection, file_manager::FileManager,
-        generic::FileVersion, pathbuf_to_string,
+        config::ServerConfig,
+        device::DeviceProfile,
+        encode::Encode,
+        file_manager::FileManager,
+        generic::FileVersion,
+        hashing::{get_interrupted_hash_run, hash_file_versions, HashJob},
+        integrity::verify_file_versions,
     },
     std::{
-        collections::VecDeque,
+        collections::{HashSet, VecDeque},
+        path::PathBuf,
         sync::{
             atomic::{AtomicBool, AtomicUsize, Ordering},
             RwLock,
@@ -17,6 +23,9 @@ use {
     tracing::{debug, error, info},
 };
 
+//How often the scheduler checks whether any files are due to be verified
+const VERIFY_CHECK_INTERVAL: time::Duration = time::Duration::from_secs(60 * 60);
+
 static TASK_UID_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
 ///Struct to represent a file import task. This is needed so we can have an enum
@@ -46,148 +55,300 @@ impl ProcessNewFiles {
 }
 
 ///Struct to represent a file processing task. This is needed so we can have an enum
-///that contains all types of task
+///that contains all types of task. Profiles are only generated for the given paths if any are set
 #[derive(Clone, Debug, Default)]
-pub struct GenerateProfiles {}
+pub struct GenerateProfiles {
+    pub paths: Option<HashSet<PathBuf>>,
+}
 
 impl GenerateProfiles {
+    pub fn new(paths: HashSet<PathBuf>) -> Self {
+        Self { paths: Some(paths) }
+    }
+
     pub fn run(&mut self, file_manager: Arc<Mutex<FileManager>>) {
         info!("Started generating profiles");
-        file_manager.lock().unwrap().generate_profiles();
+        match &self.paths {
+            Some(paths) => file_manager
+                .lock()
+                .unwrap()
+                .generate_profiles_for_paths(paths),
+            None => file_manager.lock().unwrap().generate_profiles(),
+        }
         info!("Finished generating profiles");
     }
 }
 
+///Struct to represent a reconciliation task. This is needed so we can have an enum
+///that contains all types of task
+#[derive(Clone, Debug, Default)]
+pub struct Reconcile {}
+
+impl Reconcile {
+    pub fn run(&mut self, file_manager: Arc<Mutex<FileManager>>) {
+        info!("Started reconciling files");
+        //Crawling and hashing can take minutes, so the lock is only held to take a copy of the
+        //library, import what the crawl found and apply the results
+        let reconciler = file_manager.lock().unwrap().get_reconciler();
+        let crawl = reconciler.crawl();
+        let new_files = {
+            let mut file_manager_lock = file_manager.lock().unwrap();
+            file_manager_lock.import_crawl(crawl);
+            file_manager_lock.new_files_queue.clone()
+        };
+        let report = reconciler.reconcile(&new_files);
+        file_manager
+            .lock()
+            .unwrap()
+            .apply_reconciliation(&reconciler, &report);
+        report.print();
+        info!("Finished reconciling files");
+    }
+}
+
+///Struct to represent a duplicate detection task. This is needed so we can have an enum
+///that contains all types of task. The report is kept in the FileManager for the WebUI
+#[derive(Clone, Debug, Default)]
+pub struct FindDuplicates {}
+
+impl FindDuplicates {
+    pub fn run(&mut self, file_manager: Arc<Mutex<FileManager>>) {
+        info!("Started finding duplicates");
+        file_manager.lock().unwrap().find_duplicates().print();
+        info!("Finished finding duplicates");
+    }
+}
+
+///Struct to represent a dry run of importing and processing files. This is needed so we can
+///have an enum that contains all types of task. The plan is kept in the FileManager for the WebUI
+#[derive(Clone, Debug, Default)]
+pub struct PlanImport {}
+
+impl PlanImport {
+    pub fn run(&mut self, file_manager: Arc<Mutex<FileManager>>) {
+        info!("Started planning import");
+        //The crawl can take a while, so it's done without holding the lock
+        let import_planner = file_manager.lock().unwrap().get_import_planner();
+        let import_plan = import_planner.plan();
+        import_plan.print();
+        file_manager.lock().unwrap().import_plan = import_plan;
+        info!("Finished planning import");
+    }
+}
+
+///Struct to represent re-reading nfo metadata for existing shows. This is needed so we can
+///have an enum that contains all types of task
+#[derive(Clone, Debug, Default)]
+pub struct ReadNfo {}
+
+impl ReadNfo {
+    pub fn run(&mut self, file_manager: Arc<Mutex<FileManager>>) {
+        info!("Started reading nfo files");
+        file_manager.lock().unwrap().read_nfo_files();
+        info!("Finished reading nfo files");
+    }
+}
+
 ///Struct to represent a hashing task. This is needed so we can have an enum
-///that contains all types of task.
+///that contains all types of task. Files are hashed on a pool of threads in the background and
+///an interrupted run is resumed the next time the task runs. Files with hashes from an older
+///algorithm are only rehashed if rehash_outdated is set
 #[derive(Clone, Debug, Default)]
-pub struct Hash {}
+pub struct Hash {
+    pub rehash_outdated: bool,
+}
 
 impl Hash {
-    pub fn run(&self, file_manager: Arc<Mutex<FileManager>>) -> TaskReturnAsync {
-        let is_finished = Arc::new(AtomicBool::new(false));
-        let mut generic_file_versions: Vec<(i32, Vec<FileVersion>)> = Vec::new();
-        let mut file_version_count = 0;
-        //Collect FileVersions for hashing from generic_files
-        for generic in file_manager.lock().unwrap().generic_files.iter() {
-            if generic.has_hashing_work() {
-                generic_file_versions
-                    .push((generic.get_generic_uid(), generic.file_versions.clone()));
-                file_version_count += generic.file_versions.len();
-            }
+    pub fn rehash_outdated() -> Self {
+        Self {
+            rehash_outdated: true,
         }
+    }
 
-        //Collect FileVersions for hashing from episodes
-        let mut episode_file_versions: Vec<(i32, Vec<FileVersion>)> = Vec::new();
-        for show in &file_manager.lock().unwrap().shows {
-            for season in &show.seasons {
-                for episode in &season.episodes {
-                    if episode.generic.has_hashing_work() {
-                        episode_file_versions.push((
-                            episode.generic.get_generic_uid(),
-                            episode.generic.file_versions.clone(),
-                        ));
-                        file_version_count += episode.generic.file_versions.len();
-                    }
-                }
-            }
+    pub fn run(&self, file_manager: Arc<Mutex<FileManager>>) -> Option<TaskReturnAsync> {
+        let is_finished = Arc::new(AtomicBool::new(false));
+        //An interrupted rehash is carried on by any hashing task
+        let rehash_outdated = self.rehash_outdated || get_interrupted_hash_run() == Some(true);
+        let (hashing, hash_progress, config, file_versions) = {
+            let file_manager_lock = file_manager.lock().unwrap();
+            let file_versions: Vec<FileVersion> = file_manager_lock
+                .generics()
+                .into_iter()
+                .flat_map(|generic| generic.file_versions.iter())
+                .filter(|file_version| {
+                    !file_version.missing
+                        && (file_version.hash.is_none()
+                            || file_version.fast_hash.is_none()
+                            || (rehash_outdated && file_version.has_outdated_hash()))
+                })
+                .cloned()
+                .collect();
+            (
+                file_manager_lock.hashing.clone(),
+                file_manager_lock.hash_progress.clone(),
+                file_manager_lock.config.clone(),
+                file_versions,
+            )
+        };
+        if hashing.swap(true, Ordering::SeqCst) {
+            info!("Files are already being hashed");
+            return None;
         }
+        let (hash_threads, hash_threads_per_device) = {
+            let config = config.read().unwrap();
+            (config.hash_threads, config.hash_threads_per_device)
+        };
 
         info!("Started hashing in the background");
         let is_finished_inner = is_finished.clone();
-        //Hash files until all other functions are complete
-        let handle = Some(thread::spawn(move || {
-            let mut did_finish = true;
-            let connection = establish_connection();
-            let mut current_file_version_count = 0;
-            //TODO: Switch the output below to use only one function for output
-            //Generics
-            for (generic_uid, file_versions) in generic_file_versions.iter_mut() {
-                let length: usize = file_versions.len();
-                for (i, file_version) in file_versions.iter_mut().enumerate() {
-                    if file_version.hash.is_none() {
-                        file_version.hash();
-                    }
-                    if file_version.fast_hash.is_none() {
-                        file_version.fast_hash();
-                    }
-                    debug!(
-                        "Hashed[{:2} of {:2}][{} of {}]: {}",
-                        current_file_version_count + 1,
-                        file_version_count,
-                        i + 1,
-                        length,
-                        pathbuf_to_string(&file_version.full_path)
-                    );
-                    file_version.update_file_version(&connection);
-                    current_file_version_count += 1;
-                }
-                //Update the generic in ram, if it has been deleted then don't worry about it
-                for live_generic in file_manager.lock().unwrap().generic_files.iter_mut() {
-                    if live_generic.generic_uid == Some(*generic_uid) {
-                        live_generic.update_hashes_from_file_versions(file_versions);
-                    }
-                }
-                if is_finished_inner.load(Ordering::Relaxed) {
-                    did_finish = false;
-                    break;
-                }
-            }
-            //Episodes
-            for (generic_uid, file_versions) in episode_file_versions.iter_mut() {
-                let length: usize = file_versions.len();
-                for (i, file_version) in file_versions.iter_mut().enumerate() {
-                    if file_version.hash.is_none() {
-                        file_version.hash();
-                    }
-                    if file_version.fast_hash.is_none() {
-                        file_version.fast_hash();
-                    }
-                    debug!(
-                        "Hashed[{:2} of {:2}][{} of {}]: {}",
-                        current_file_version_count + 1,
-                        file_version_count,
-                        i + 1,
-                        length,
-                        pathbuf_to_string(&file_version.full_path)
-                    );
-                    file_version.update_file_version(&connection);
-                    current_file_version_count += 1;
-                }
-                let mut found_generic = false;
-                for show in file_manager.lock().unwrap().shows.iter_mut() {
-                    for season in show.seasons.iter_mut() {
-                        for episode in season.episodes.iter_mut() {
-                            if episode.generic.generic_uid == Some(*generic_uid) {
-                                episode
-                                    .generic
-                                    .update_hashes_from_file_versions(file_versions);
-                                found_generic = true;
-                                break;
-                            }
-                        }
-                    }
-                }
-                if !found_generic {
-                    error!("Couldn't find Episode->Generic to update the hash of in memory");
-                    panic!();
-                }
-                if is_finished_inner.load(Ordering::Relaxed) {
-                    did_finish = false;
-                    break;
-                }
-            }
-
+        let handle = thread::spawn(move || {
+            let _hashing = RunningGuard(hashing);
+            //Reading the size and device of each file is left to this thread so it doesn't hold
+            //up the scheduler
+            let jobs: Vec<HashJob> = file_versions.into_iter().filter_map(HashJob::new).collect();
+            let did_finish = hash_file_versions(
+                jobs,
+                rehash_outdated,
+                hash_threads,
+                hash_threads_per_device,
+                file_manager,
+                hash_progress,
+                is_finished_inner.clone(),
+            );
             is_finished_inner.store(true, Ordering::Relaxed);
             if did_finish {
                 info!("Finished hashing");
             } else {
-                info!("Stopped hashing (incomplete)");
+                info!(
+                    "Stopped hashing (incomplete), it will resume the next time files are hashed"
+                );
             }
-        }))
-        .unwrap();
+        });
+
+        Some(TaskReturnAsync::new(Some(handle), is_finished))
+    }
+}
+
+///Clears the flag a background task sets while it's running once the task's thread is done with
+///it, even if the thread panics, so the task can run again
+struct RunningGuard(Arc<AtomicBool>);
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+///Struct to represent verifying files against their stored hash to find corruption. This is
+///needed so we can have an enum that contains all types of task. Only files that haven't been
+///verified within verify_interval_days are read unless all is set
+#[derive(Clone, Debug, Default)]
+pub struct VerifyHashes {
+    pub all: bool,
+}
+
+impl VerifyHashes {
+    pub fn run(&self, file_manager: Arc<Mutex<FileManager>>) -> Option<TaskReturnAsync> {
+        let (verifying, config, mut file_versions) = {
+            let file_manager_lock = file_manager.lock().unwrap();
+            let file_versions: Vec<FileVersion> = file_manager_lock
+                .generics()
+                .into_iter()
+                .flat_map(|generic| generic.file_versions.iter())
+                .filter(|file_version| !file_version.missing && file_version.hash.is_some())
+                .cloned()
+                .collect();
+            (
+                file_manager_lock.verifying.clone(),
+                file_manager_lock.config.clone(),
+                file_versions,
+            )
+        };
+        let (verify_interval_days, verify_rate) = {
+            let config = config.read().unwrap();
+            (config.verify_interval_days, config.verify_rate)
+        };
+        if !self.all {
+            let verify_interval = time::Duration::from_secs(verify_interval_days * 24 * 60 * 60);
+            file_versions.retain(|file_version| {
+                file_version.verified_at.map_or(true, |verified_at| {
+                    verified_at
+                        .elapsed()
+                        .map_or(true, |elapsed| elapsed >= verify_interval)
+                })
+            });
+        }
+        if file_versions.is_empty() {
+            debug!("No files are due to be verified");
+            return None;
+        }
+        if verifying.swap(true, Ordering::SeqCst) {
+            info!("Files are already being verified");
+            return None;
+        }
+        //Files that have never been verified go first, then the ones verified longest ago
+        file_versions.sort_by_key(|file_version| file_version.verified_at);
+
+        info!(
+            "Started verifying {} files in the background",
+            file_versions.len()
+        );
+        let is_finished = Arc::new(AtomicBool::new(false));
+        let is_finished_inner = is_finished.clone();
+        let handle = thread::spawn(move || {
+            let _verifying = RunningGuard(verifying);
+            let report = verify_file_versions(
+                file_versions,
+                verify_rate * 1_000_000,
+                file_manager,
+                &is_finished_inner,
+            );
+            report.print();
+            is_finished_inner.store(true, Ordering::Relaxed);
+            info!("Finished verifying files");
+        });
 
-        TaskReturnAsync::new(Some(handle), is_finished)
+        Some(TaskReturnAsync::new(Some(handle), is_finished))
+    }
+}
+
+///Struct to represent queueing encodes for every file that won't direct play on a device. This is
+///needed so we can have an enum that contains all types of task
+#[derive(Clone, Debug)]
+pub struct EncodeForDevice {
+    pub device: DeviceProfile,
+    pub transcode_queue: Arc<Mutex<VecDeque<Encode>>>,
+}
+
+impl EncodeForDevice {
+    pub fn new(device: DeviceProfile, transcode_queue: Arc<Mutex<VecDeque<Encode>>>) -> Self {
+        Self {
+            device,
+            transcode_queue,
+        }
+    }
+
+    pub fn run(&mut self, file_manager: Arc<Mutex<FileManager>>) {
+        info!(
+            "Started finding files that won't direct play on {}",
+            self.device.name
+        );
+        //The queue is held until the encodes are added so the same generic can't be queued twice
+        let file_manager_lock = file_manager.lock().unwrap();
+        let mut transcode_queue = self.transcode_queue.lock().unwrap();
+        let queued_generics: HashSet<i32> = transcode_queue
+            .iter()
+            .filter(|encode| encode.encode_profile == self.device.encode_profile)
+            .map(|encode| encode.generic_uid)
+            .collect();
+        let encodes = file_manager_lock.generate_encodes_for_device(&self.device, &queued_generics);
+        info!(
+            "Queued {} encodes for {} with {}",
+            encodes.len(),
+            self.device.name,
+            self.device.encode_profile
+        );
+        transcode_queue.extend(encodes);
     }
 }
 
@@ -198,6 +359,12 @@ pub enum TaskType {
     ProcessNewFiles(ProcessNewFiles),
     GenerateProfiles(GenerateProfiles),
     Hash(Hash),
+    Reconcile(Reconcile),
+    FindDuplicates(FindDuplicates),
+    PlanImport(PlanImport),
+    ReadNfo(ReadNfo),
+    VerifyHashes(VerifyHashes),
+    EncodeForDevice(EncodeForDevice),
 }
 
 ///Task struct that will later be in the database with a real id so that the queue
@@ -232,11 +399,29 @@ impl Task {
                 process_new_files.run(file_manager);
             }
             TaskType::Hash(hash) => {
-                return Some(hash.run(file_manager));
+                return hash.run(file_manager);
             }
             TaskType::GenerateProfiles(generate_profiles) => {
                 generate_profiles.run(file_manager);
             }
+            TaskType::Reconcile(reconcile) => {
+                reconcile.run(file_manager);
+            }
+            TaskType::FindDuplicates(find_duplicates) => {
+                find_duplicates.run(file_manager);
+            }
+            TaskType::PlanImport(plan_import) => {
+                plan_import.run(file_manager);
+            }
+            TaskType::ReadNfo(read_nfo) => {
+                read_nfo.run(file_manager);
+            }
+            TaskType::VerifyHashes(verify_hashes) => {
+                return verify_hashes.run(file_manager);
+            }
+            TaskType::EncodeForDevice(encode_for_device) => {
+                encode_for_device.run(file_manager);
+            }
         }
         None
     }
@@ -290,9 +475,42 @@ impl Scheduler {
         //The boolean tells the thread to stop and tells the scheduler thread that it has stopped
         //Essentially it just makes it safe to join
         let mut handles: Vec<TaskReturnAsync> = Vec::new();
+
+        //Carry on hashing where the server left off if it was stopped part way through
+        if let Some(rehash_outdated) = get_interrupted_hash_run() {
+            info!("Resuming interrupted hashing");
+            self.tasks
+                .lock()
+                .unwrap()
+                .push_back(Task::new(TaskType::Hash(Hash { rehash_outdated })));
+        }
+
+        //Files are first checked for verification when the server starts
+        let mut next_verify_check = time::Instant::now();
+
         loop {
             let mut task: Task;
 
+            if next_verify_check <= time::Instant::now()
+                && !self.input_completed.load(Ordering::Relaxed)
+            {
+                next_verify_check = time::Instant::now() + VERIFY_CHECK_INTERVAL;
+                let verify_interval_days = self
+                    .file_manager
+                    .lock()
+                    .unwrap()
+                    .config
+                    .read()
+                    .unwrap()
+                    .verify_interval_days;
+                if verify_interval_days > 0 {
+                    self.tasks
+                        .lock()
+                        .unwrap()
+                        .push_back(Task::new(TaskType::VerifyHashes(VerifyHashes::default())));
+                }
+            }
+
             //Mark the completed threads
             let mut completed_threads: Vec<usize> = Vec::new();
             for (i, handle) in handles.iter().enumerate() {
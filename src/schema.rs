@@ -1,3 +1,26 @@
+table! {
+    completed_encode (id) {
+        id -> Int4,
+        generic_uid -> Int4,
+        encode_profile -> Text,
+        source_size -> Int8,
+        output_size -> Int8,
+        finished_at -> Timestamp,
+    }
+}
+
+table! {
+    corruption (id) {
+        id -> Int4,
+        file_version_id -> Int4,
+        generic_uid -> Int4,
+        full_path -> Text,
+        expected_hash -> Varchar,
+        actual_hash -> Varchar,
+        detected_at -> Timestamp,
+    }
+}
+
 table! {
     episode (generic_uid, show_uid, season_number, episode_number) {
         generic_uid -> Int4,
@@ -5,6 +28,29 @@ table! {
         episode_title -> Text,
         season_number -> Int4,
         episode_number -> Int4,
+        release_source -> Nullable<Text>,
+        air_date -> Nullable<Text>,
+        plot -> Nullable<Text>,
+    }
+}
+
+table! {
+    extra (generic_uid) {
+        generic_uid -> Int4,
+        show_uid -> Nullable<Int4>,
+        movie_generic_uid -> Nullable<Int4>,
+    }
+}
+
+table! {
+    failed_encode (id) {
+        id -> Int4,
+        generic_uid -> Int4,
+        source_path -> Text,
+        target_path -> Text,
+        quarantine_path -> Nullable<Text>,
+        reason -> Text,
+        failed_at -> Timestamp,
     }
 }
 
@@ -22,6 +68,10 @@ table! {
         length_time -> Nullable<Float8>,
         resolution_standard -> Nullable<Int4>,
         container -> Nullable<Int4>,
+        missing -> Bool,
+        hash_version -> Int4,
+        corrupted -> Bool,
+        verified_at -> Nullable<Timestamp>,
     }
 }
 
@@ -32,10 +82,76 @@ table! {
     }
 }
 
+table! {
+    hash_run (id) {
+        id -> Int4,
+        total_files -> Int4,
+        total_bytes -> Int8,
+        hashed_files -> Int4,
+        hashed_bytes -> Int8,
+        finished -> Bool,
+        started_at -> Timestamp,
+        rehash_outdated -> Bool,
+    }
+}
+
+table! {
+    movie (generic_uid) {
+        generic_uid -> Int4,
+        movie_title -> Text,
+        movie_year -> Int4,
+    }
+}
+
+table! {
+    rejected_file (id) {
+        id -> Int4,
+        full_path -> Text,
+        reason -> Int4,
+        matched_rule -> Nullable<Text>,
+        rejected_at -> Timestamp,
+    }
+}
+
+table! {
+    sidecar (id) {
+        id -> Int4,
+        generic_uid -> Int4,
+        full_path -> Text,
+        kind -> Int4,
+        language -> Nullable<Text>,
+    }
+}
+
 table! {
     show (show_uid) {
         show_uid -> Int4,
         show_title -> Text,
+        show_year -> Nullable<Int4>,
+        plot -> Nullable<Text>,
+    }
+}
+
+table! {
+    stream (id) {
+        id -> Int4,
+        file_version_id -> Int4,
+        stream_index -> Int4,
+        stream_type -> Int4,
+        codec -> Text,
+        profile -> Nullable<Text>,
+        level -> Nullable<Text>,
+        bit_depth -> Nullable<Int4>,
+        bitrate -> Nullable<Int8>,
+        channels -> Nullable<Int4>,
+        language -> Nullable<Text>,
+        is_default -> Bool,
+        is_forced -> Bool,
+        transfer_characteristics -> Nullable<Text>,
+        colour_primaries -> Nullable<Text>,
+        matrix_coefficients -> Nullable<Text>,
+        mastering_display -> Nullable<Text>,
+        content_light_level -> Nullable<Text>,
     }
 }
 
@@ -46,14 +162,28 @@ table! {
     }
 }
 
+joinable!(corruption -> file_version (file_version_id));
 joinable!(episode -> generic (generic_uid));
 joinable!(episode -> show (show_uid));
+joinable!(extra -> show (show_uid));
 joinable!(file_version -> generic (generic_uid));
+joinable!(movie -> generic (generic_uid));
+joinable!(sidecar -> generic (generic_uid));
+joinable!(stream -> file_version (file_version_id));
 
 allow_tables_to_appear_in_same_query!(
+    completed_encode,
+    corruption,
     episode,
+    extra,
+    failed_encode,
     file_version,
     generic,
+    hash_run,
+    movie,
+    rejected_file,
     show,
+    sidecar,
+    stream,
     worker,
 );
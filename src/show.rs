@@ -3,6 +3,7 @@ use crate::{
     generic::{FileVersion, Generic},
     model::*,
 };
+use std::collections::{HashMap, HashSet};
 use tracing::error;
 
 ///Structure contains all episode specific data as well as the underlying
@@ -15,6 +16,12 @@ pub struct Episode {
     pub show_title: String,
     pub show_season: i32,
     pub show_episode: Vec<i32>,
+    pub episode_title: String,
+    //Such as HDTV, WEB-DL or BluRay
+    pub release_source: Option<String>,
+    //YYYY-MM-DD
+    pub air_date: Option<String>,
+    pub plot: Option<String>,
 }
 
 impl Episode {
@@ -24,6 +31,8 @@ impl Episode {
         show_title: String,
         show_season: i32,
         show_episode: Vec<i32>,
+        episode_title: String,
+        release_source: Option<String>,
     ) -> Self {
         Episode {
             episode_uid: None,
@@ -32,8 +41,57 @@ impl Episode {
             show_title,
             show_season,
             show_episode,
+            episode_title,
+            release_source,
+            air_date: None,
+            plot: None,
         }
     }
+
+    ///Builds Episodes from the episode rows in the database, a multi-episode file has a row
+    ///for every episode number that all share the same generic_uid, so they become one Episode.
+    ///The show title is filled in when the episode is inserted into its show
+    pub fn from_episode_models(
+        generics: &[Generic],
+        episode_models: Vec<EpisodeModel>,
+    ) -> Vec<Self> {
+        let generics: HashMap<i32, &Generic> = generics
+            .iter()
+            .map(|generic| (generic.get_generic_uid(), generic))
+            .collect();
+
+        let mut episodes: Vec<Self> = Vec::new();
+        let mut episode_indexes: HashMap<i32, usize> = HashMap::new();
+        for episode_model in episode_models {
+            if let Some(index) = episode_indexes.get(&episode_model.generic_uid) {
+                episodes[*index]
+                    .show_episode
+                    .push(episode_model.episode_number);
+                continue;
+            }
+            if let Some(generic) = generics.get(&episode_model.generic_uid) {
+                episode_indexes.insert(episode_model.generic_uid, episodes.len());
+                let mut episode = Self::new(
+                    (*generic).clone(),
+                    episode_model.show_uid,
+                    "".to_string(),
+                    episode_model.season_number,
+                    vec![episode_model.episode_number],
+                    episode_model.episode_title,
+                    episode_model.release_source,
+                );
+                episode.air_date = episode_model.air_date;
+                episode.plot = episode_model.plot;
+                episodes.push(episode);
+            }
+        }
+
+        for episode in episodes.iter_mut() {
+            episode.show_episode.sort_unstable();
+            episode.show_episode.dedup();
+        }
+        episodes
+    }
 }
 
 ///Structure to store a season containing the episodes of a show and the season number
@@ -65,6 +123,16 @@ impl Season {
         }
         false
     }
+
+    ///Number of distinct episodes in the season, a multi-episode file counts for each episode
+    ///it contains and multiple files of the same episode are only counted once
+    pub fn episode_count(&self) -> usize {
+        self.episodes
+            .iter()
+            .flat_map(|episode| episode.show_episode.iter())
+            .collect::<HashSet<&i32>>()
+            .len()
+    }
 }
 
 ///Structure to represent a show containing seasons and the name of the show
@@ -72,6 +140,9 @@ impl Season {
 pub struct Show {
     pub show_uid: i32,
     pub show_title: String,
+    //From a folder name like "Alcatraz (2012)", shows with the same title are told apart by it
+    pub show_year: Option<i32>,
+    pub plot: Option<String>,
     pub seasons: Vec<Season>,
 }
 
@@ -80,13 +151,16 @@ impl Show {
         Show {
             show_uid: uid,
             show_title,
+            show_year: None,
+            plot: None,
             seasons: Vec::new(),
         }
     }
 
     ///Add an episode to the show creating a season if none exists
-    pub fn insert_episode(&mut self, episode: Episode) {
+    pub fn insert_episode(&mut self, mut episode: Episode) {
         let season_number = episode.show_season;
+        episode.show_title = self.show_title.clone();
 
         let mut found_season: bool = false;
         for season in &mut self.seasons {
@@ -118,6 +192,13 @@ impl Show {
         false
     }
 
+    pub fn episode_count(&self) -> usize {
+        self.seasons
+            .iter()
+            .map(|season| season.episode_count())
+            .sum()
+    }
+
     pub fn get_generic_from_uid(&self, generic_uid: i32) -> Option<Generic> {
         for season in &self.seasons {
             for episode in &season.episodes {
@@ -140,6 +221,8 @@ impl Show {
         Show {
             show_uid: show_uid_temp,
             show_title,
+            show_year: show_model.show_year,
+            plot: show_model.plot,
             seasons: Vec::new(),
         }
     }
@@ -0,0 +1,118 @@
+//!Files kept next to a video that belong to it, such as external subtitles, nfo files and artwork
+use {
+    crate::{get_extension, get_file_name, get_file_stem, model::SidecarModel},
+    serde::{Deserialize, Serialize},
+    std::path::{Path, PathBuf},
+    tracing::warn,
+};
+
+///The kind of file a sidecar is, decided by its extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SidecarKind {
+    Subtitle = 1,
+    Nfo = 2,
+    Artwork = 3,
+}
+
+impl SidecarKind {
+    pub fn from_i32(input: i32) -> Option<Self> {
+        match input {
+            1 => Some(Self::Subtitle),
+            2 => Some(Self::Nfo),
+            3 => Some(Self::Artwork),
+            _ => None,
+        }
+    }
+
+    ///Returns none if the file isn't a sidecar
+    pub fn from_path(full_path: &Path) -> Option<Self> {
+        full_path.extension()?;
+        match get_extension(full_path).to_lowercase().as_str() {
+            "srt" | "ass" | "ssa" | "sub" | "idx" | "vtt" | "sup" => Some(Self::Subtitle),
+            "nfo" => Some(Self::Nfo),
+            "jpg" | "jpeg" | "png" | "tbn" | "webp" => Some(Self::Artwork),
+            _ => None,
+        }
+    }
+}
+
+///A sidecar file along with the generic that owns it
+#[derive(Clone, Debug)]
+pub struct Sidecar {
+    pub id: i32,
+    pub generic_uid: i32,
+    pub full_path: PathBuf,
+    pub kind: SidecarKind,
+    pub language: Option<String>,
+}
+
+impl Sidecar {
+    pub fn from_model(model: SidecarModel) -> Option<Self> {
+        match SidecarKind::from_i32(model.kind) {
+            Some(kind) => Some(Self {
+                id: model.id,
+                generic_uid: model.generic_uid,
+                full_path: PathBuf::from(model.full_path),
+                kind,
+                language: model.language,
+            }),
+            None => {
+                warn!(
+                    "Sidecar \"{}\" has an unknown kind: {}",
+                    model.full_path, model.kind
+                );
+                None
+            }
+        }
+    }
+
+    ///The path this sidecar should have to match the video at target_path, sidecars
+    ///that aren't named after source_path, like a folder.jpg, are shared and return none
+    pub fn get_path_for_video(&self, source_path: &Path, target_path: &Path) -> Option<PathBuf> {
+        let sidecar_file_name = get_file_name(&self.full_path);
+        let suffix = get_sidecar_suffix(&get_file_stem(source_path), &sidecar_file_name)?;
+        Some(target_path.with_file_name(format!("{}{}", get_file_stem(target_path), suffix)))
+    }
+}
+
+///The part of the sidecar's file name after the video's file stem, such as ".en.srt"
+///for "Show - S01E01.en.srt", or none if the sidecar isn't named after the video
+pub fn get_sidecar_suffix<'a>(
+    video_file_stem: &str,
+    sidecar_file_name: &'a str,
+) -> Option<&'a str> {
+    let suffix = sidecar_file_name.strip_prefix(video_file_stem)?;
+    if suffix.starts_with(['.', '-', '_', ' ']) {
+        Some(suffix)
+    } else {
+        None
+    }
+}
+
+///Subtitles are usually named like "Show - S01E01.en.forced.srt", this takes the
+///first part of the suffix after the video's name that looks like a language code
+pub fn get_sidecar_language(sidecar_suffix: &str) -> Option<String> {
+    //The extension could be mistaken for a language code
+    let sidecar_suffix = sidecar_suffix
+        .rsplit_once('.')
+        .map_or(sidecar_suffix, |(suffix, _)| suffix);
+    sidecar_suffix
+        .split(['.', '_', ' '])
+        .map(|part| part.trim_start_matches('-'))
+        .find(|part| is_language_code(part))
+        .map(|part| part.to_string())
+}
+
+///Accepts codes like "en", "eng" and "pt-BR"
+fn is_language_code(part: &str) -> bool {
+    let mut subtags = part.split('-');
+    let language = subtags.next().unwrap_or_default();
+    let region = subtags.next();
+    (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && !["sdh", "cc"].contains(&language.to_lowercase().as_str())
+        && region.map_or(true, |region| {
+            region.len() == 2 && region.chars().all(|c| c.is_ascii_alphabetic())
+        })
+        && subtags.next().is_none()
+}
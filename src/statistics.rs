@@ -0,0 +1,375 @@
+//!Totals of the library grouped by container, resolution, codec, bitrate, show and root directory,
+//!along with how much space encoding the library with a profile would save going by the sizes of
+//!past encodes with that profile. Sent to the WebUI as is or as CSV
+use {
+    crate::{
+        database::{create_completed_encode, establish_connection, get_completed_encodes},
+        encode::{Encode, EncodeProfile},
+        file_manager::FileManager,
+        generic::FileVersion,
+        get_file_stem,
+        model::{CompletedEncodeModel, NewCompletedEncode},
+        pathbuf_to_string,
+    },
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{BTreeMap, HashMap, HashSet},
+        fmt, fs,
+        path::Path,
+        sync::{Arc, Mutex},
+    },
+    tracing::warn,
+};
+
+//Upper bounds of the bitrate groups in megabits per second
+const BITRATE_GROUPS: [u64; 5] = [2, 5, 10, 20, 40];
+
+///A FileVersion along with what it's grouped by that isn't stored on it
+#[derive(Clone, Debug)]
+pub struct LibraryFile {
+    pub file_version: FileVersion,
+    //Only set for episodes
+    pub show_title: Option<String>,
+    pub root_directory: Option<String>,
+    //Bytes, none until it's read from disk or if the file couldn't be read
+    pub size: Option<u64>,
+}
+
+impl LibraryFile {
+    fn read_size(&mut self) {
+        self.size = match fs::metadata(&self.file_version.full_path) {
+            Ok(metadata) => Some(metadata.len()),
+            Err(err) => {
+                warn!(
+                    "Couldn't read the size of file: {}. Err: {}",
+                    pathbuf_to_string(&self.file_version.full_path),
+                    err
+                );
+                None
+            }
+        };
+    }
+
+    ///Megabits per second over the whole file, none if it hasn't been profiled
+    pub fn get_bitrate(&self) -> Option<f64> {
+        let size = self.size?;
+        self.file_version
+            .length_time
+            .filter(|length_time| *length_time > 0.0)
+            .map(|length_time| size as f64 * 8.0 / length_time / 1_000_000.0)
+    }
+}
+
+///Every FileVersion that isn't missing, sizes are left to be read once the FileManager is unlocked
+pub fn get_library_files(file_manager: &FileManager) -> Vec<LibraryFile> {
+    let mut show_titles: HashMap<i32, String> = HashMap::new();
+    for show in &file_manager.shows {
+        for season in &show.seasons {
+            for episode in &season.episodes {
+                show_titles.insert(episode.generic.get_generic_uid(), show.show_title.clone());
+            }
+        }
+    }
+    let config = file_manager.config.read().unwrap();
+    file_manager
+        .generics()
+        .into_iter()
+        .flat_map(|generic| generic.file_versions.iter())
+        .filter(|file_version| !file_version.missing)
+        .map(|file_version| LibraryFile {
+            show_title: show_titles.get(&file_version.generic_uid).cloned(),
+            root_directory: config
+                .tracked_directories
+                .get_root_directory_for_path(&file_version.full_path)
+                .map(|root_directory| pathbuf_to_string(&root_directory.path)),
+            file_version: file_version.clone(),
+            size: None,
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum StatisticsCategory {
+    Total,
+    Container,
+    ResolutionStandard,
+    VideoCodec,
+    Bitrate,
+    Show,
+    RootDirectory,
+}
+
+impl fmt::Display for StatisticsCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Total => write!(f, "Total"),
+            Self::Container => write!(f, "Container"),
+            Self::ResolutionStandard => write!(f, "ResolutionStandard"),
+            Self::VideoCodec => write!(f, "VideoCodec"),
+            Self::Bitrate => write!(f, "Bitrate"),
+            Self::Show => write!(f, "Show"),
+            Self::RootDirectory => write!(f, "RootDirectory"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StatisticsGroup {
+    pub category: StatisticsCategory,
+    pub name: String,
+    pub file_versions: usize,
+    //Bytes
+    pub size: u64,
+    //Seconds, only of the files that have been profiled
+    pub length_time: f64,
+}
+
+///How much space encoding every master file that hasn't already been encoded with the profile
+///would save, assuming the encodes replace their sources. Projections are none until an encode
+///with the profile has been completed
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavingsProjection {
+    pub encode_profile: EncodeProfile,
+    //Encodes with the profile the compression ratio is taken from
+    pub completed_encodes: usize,
+    //Size of the outputs divided by the size of their sources
+    pub compression_ratio: Option<f64>,
+    pub source_files: usize,
+    //Bytes
+    pub source_size: u64,
+    pub projected_size: Option<u64>,
+    pub reclaimed_size: Option<u64>,
+}
+
+impl SavingsProjection {
+    pub fn new(
+        files: &[LibraryFile],
+        encode_profile: &EncodeProfile,
+        completed_encodes: &[CompletedEncodeModel],
+    ) -> Self {
+        let (completed_source_size, completed_output_size) = completed_encodes
+            .iter()
+            .filter(|completed_encode| completed_encode.source_size > 0)
+            .fold((0, 0), |(source_size, output_size), completed_encode| {
+                (
+                    source_size + completed_encode.source_size,
+                    output_size + completed_encode.output_size,
+                )
+            });
+        let compression_ratio = if completed_source_size > 0 {
+            Some(completed_output_size as f64 / completed_source_size as f64)
+        } else {
+            None
+        };
+
+        //Encodes are named after their source with the profile added to the end
+        let suffix = format!("_{}", encode_profile);
+        let encoded_generics: HashSet<i32> = files
+            .iter()
+            .filter(|file| get_file_stem(&file.file_version.full_path).ends_with(&suffix))
+            .map(|file| file.file_version.generic_uid)
+            .collect();
+        let sources: Vec<u64> = files
+            .iter()
+            .filter(|file| {
+                file.file_version.master_file
+                    && !file.file_version.corrupted
+                    && !encoded_generics.contains(&file.file_version.generic_uid)
+            })
+            .filter_map(|file| file.size)
+            .collect();
+        let source_size: u64 = sources.iter().sum();
+        let projected_size = compression_ratio
+            .map(|compression_ratio| (source_size as f64 * compression_ratio).round() as u64);
+        Self {
+            encode_profile: encode_profile.clone(),
+            completed_encodes: completed_encodes.len(),
+            compression_ratio,
+            source_files: sources.len(),
+            source_size,
+            projected_size,
+            reclaimed_size: projected_size
+                .map(|projected_size| source_size.saturating_sub(projected_size)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LibraryStatistics {
+    //Sorted by category, then largest first
+    pub groups: Vec<StatisticsGroup>,
+    //Files whose size couldn't be read, they're left out of every group
+    pub unreadable_files: usize,
+    pub savings: Option<SavingsProjection>,
+}
+
+impl LibraryStatistics {
+    pub fn new(files: &[LibraryFile], savings: Option<SavingsProjection>) -> Self {
+        let mut groups: BTreeMap<(StatisticsCategory, String), StatisticsGroup> = BTreeMap::new();
+        let mut unreadable_files = 0;
+        for file in files {
+            let size = match file.size {
+                Some(size) => size,
+                None => {
+                    unreadable_files += 1;
+                    continue;
+                }
+            };
+            let file_version = &file.file_version;
+            let mut add = |category: StatisticsCategory, name: String| {
+                let group = groups
+                    .entry((category, name.clone()))
+                    .or_insert(StatisticsGroup {
+                        category,
+                        name,
+                        file_versions: 0,
+                        size: 0,
+                        length_time: 0.0,
+                    });
+                group.file_versions += 1;
+                group.size += size;
+                group.length_time += file_version.length_time.unwrap_or_default();
+            };
+            add(StatisticsCategory::Total, "Total".to_string());
+            add(
+                StatisticsCategory::Container,
+                or_unknown(file_version.container),
+            );
+            add(
+                StatisticsCategory::ResolutionStandard,
+                or_unknown(file_version.resolution_standard),
+            );
+            add(
+                StatisticsCategory::VideoCodec,
+                or_unknown(
+                    file_version
+                        .get_video_stream()
+                        .map(|video_stream| &video_stream.codec),
+                ),
+            );
+            add(
+                StatisticsCategory::Bitrate,
+                get_bitrate_group(file.get_bitrate()),
+            );
+            if let Some(show_title) = &file.show_title {
+                add(StatisticsCategory::Show, show_title.clone());
+            }
+            add(
+                StatisticsCategory::RootDirectory,
+                or_unknown(file.root_directory.as_ref()),
+            );
+        }
+        let mut groups: Vec<StatisticsGroup> = groups.into_values().collect();
+        groups.sort_by(|a, b| a.category.cmp(&b.category).then(b.size.cmp(&a.size)));
+        Self {
+            groups,
+            unreadable_files,
+            savings,
+        }
+    }
+
+    ///One row per group, the savings projection is added as rows in the Savings category
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("category,name,file_versions,size_bytes,length_seconds\n");
+        for group in &self.groups {
+            csv.push_str(&format!(
+                "{},{},{},{},{:.0}\n",
+                group.category,
+                escape_csv_field(&group.name),
+                group.file_versions,
+                group.size,
+                group.length_time
+            ));
+        }
+        if let Some(savings) = &self.savings {
+            let mut add_row = |name: &str, size: Option<u64>| {
+                csv.push_str(&format!(
+                    "Savings,{},{},{},\n",
+                    escape_csv_field(&format!("{} {}", savings.encode_profile, name)),
+                    savings.source_files,
+                    size.map(|size| size.to_string()).unwrap_or_default()
+                ));
+            };
+            add_row("source", Some(savings.source_size));
+            add_row("projected", savings.projected_size);
+            add_row("reclaimed", savings.reclaimed_size);
+        }
+        csv
+    }
+}
+
+fn or_unknown<T: ToString>(value: Option<T>) -> String {
+    value.map_or_else(|| "Unknown".to_string(), |value| value.to_string())
+}
+
+fn get_bitrate_group(bitrate: Option<f64>) -> String {
+    let bitrate = match bitrate {
+        Some(bitrate) => bitrate,
+        None => return "Unknown".to_string(),
+    };
+    let mut lower = 0;
+    for upper in BITRATE_GROUPS {
+        if bitrate < upper as f64 {
+            return format!("{}-{} Mbps", lower, upper);
+        }
+        lower = upper;
+    }
+    format!("{}+ Mbps", lower)
+}
+
+///Fields with commas, quotes or new lines are quoted
+fn escape_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+///Reads the size of every file once the FileManager is unlocked, the savings projection is only
+///made if a profile is given
+pub fn generate_statistics(
+    file_manager: Arc<Mutex<FileManager>>,
+    encode_profile: Option<&EncodeProfile>,
+) -> LibraryStatistics {
+    let mut files = get_library_files(&file_manager.lock().unwrap());
+    for file in files.iter_mut() {
+        file.read_size();
+    }
+    let savings = encode_profile.map(|encode_profile| {
+        SavingsProjection::new(
+            &files,
+            encode_profile,
+            &get_completed_encodes(&encode_profile.to_string()),
+        )
+    });
+    LibraryStatistics::new(&files, savings)
+}
+
+///Records the size of an encode and its source once it's been added to the library
+pub fn record_completed_encode(encode: &Encode) {
+    let get_size = |path: &Path| match fs::metadata(path) {
+        Ok(metadata) => Some(metadata.len() as i64),
+        Err(err) => {
+            warn!(
+                "Couldn't read the size of encoded file, it won't be used for projections: {}. Err: {}",
+                pathbuf_to_string(path),
+                err
+            );
+            None
+        }
+    };
+    if let (Some(source_size), Some(output_size)) =
+        (get_size(&encode.source_path), get_size(&encode.target_path))
+    {
+        create_completed_encode(
+            &establish_connection(),
+            NewCompletedEncode {
+                generic_uid: encode.generic_uid,
+                encode_profile: encode.encode_profile.to_string(),
+                source_size,
+                output_size,
+            },
+        );
+    }
+}
@@ -0,0 +1,430 @@
+//!Every video, audio and subtitle stream in a FileVersion, read by the MediaProber when the file
+//!is profiled so the library can be filtered by codec and track language
+use {
+    crate::{
+        model::StreamModel,
+        profile::{AudioCodec, DynamicRange, VideoCodec},
+    },
+    serde::{Deserialize, Serialize},
+    tracing::warn,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum StreamType {
+    Video = 1,
+    Audio = 2,
+    Subtitle = 3,
+}
+
+impl StreamType {
+    pub fn from_i32(input: i32) -> Option<Self> {
+        match input {
+            1 => Some(Self::Video),
+            2 => Some(Self::Audio),
+            3 => Some(Self::Subtitle),
+            _ => None,
+        }
+    }
+
+    ///Takes the codec_type from ffprobe or the track @type from mediainfo, returns none for
+    ///streams that aren't stored such as data and attachments
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "video" => Some(Self::Video),
+            "audio" => Some(Self::Audio),
+            "subtitle" | "text" => Some(Self::Subtitle),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Stream {
+    //Position of the stream in the file
+    pub stream_index: i32,
+    pub stream_type: StreamType,
+    //As ffmpeg names it, such as hevc or aac
+    pub codec: String,
+    pub profile: Option<String>,
+    //Such as 4.1
+    pub level: Option<String>,
+    pub bit_depth: Option<i32>,
+    //Bits per second
+    pub bitrate: Option<i64>,
+    pub channels: Option<i32>,
+    //As tagged in the file, ffprobe gives ISO 639-2 codes such as eng and mediainfo gives
+    //ISO 639-1 codes such as en
+    pub language: Option<String>,
+    pub is_default: bool,
+    pub is_forced: bool,
+    //Colour values are only read for video streams and use ffmpeg's names, such as smpte2084,
+    //bt2020 and bt2020nc
+    pub transfer_characteristics: Option<String>,
+    pub colour_primaries: Option<String>,
+    pub matrix_coefficients: Option<String>,
+    //HDR10 mastering display metadata in the form x265's master-display takes, such as
+    //G(13250,34500)B(7500,3000)R(34000,16000)WP(15635,16450)L(10000000,50)
+    pub mastering_display: Option<String>,
+    //MaxCLL and MaxFALL in the form x265's max-cll takes, such as 1000,400
+    pub content_light_level: Option<String>,
+}
+
+impl Stream {
+    pub fn from_model(model: StreamModel) -> Option<Self> {
+        match StreamType::from_i32(model.stream_type) {
+            Some(stream_type) => Some(Self {
+                stream_index: model.stream_index,
+                stream_type,
+                codec: model.codec,
+                profile: model.profile,
+                level: model.level,
+                bit_depth: model.bit_depth,
+                bitrate: model.bitrate,
+                channels: model.channels,
+                language: model.language,
+                is_default: model.is_default,
+                is_forced: model.is_forced,
+                transfer_characteristics: model.transfer_characteristics,
+                colour_primaries: model.colour_primaries,
+                matrix_coefficients: model.matrix_coefficients,
+                mastering_display: model.mastering_display,
+                content_light_level: model.content_light_level,
+            }),
+            None => {
+                warn!(
+                    "Stream {} of file version {} has an unknown type: {}",
+                    model.stream_index, model.file_version_id, model.stream_type
+                );
+                None
+            }
+        }
+    }
+
+    pub fn get_video_codec(&self) -> Option<VideoCodec> {
+        if self.stream_type != StreamType::Video {
+            return None;
+        }
+        match self.codec.as_str() {
+            "h264" => Some(VideoCodec::H264),
+            "hevc" => Some(VideoCodec::H265),
+            "vp8" => Some(VideoCodec::VP8),
+            "vp9" => Some(VideoCodec::VP9),
+            _ => None,
+        }
+    }
+
+    pub fn get_audio_codec(&self) -> Option<AudioCodec> {
+        if self.stream_type != StreamType::Audio {
+            return None;
+        }
+        match self.codec.as_str() {
+            "flac" => Some(AudioCodec::FLAC),
+            "aac" => match &self.profile {
+                Some(profile) if profile.contains("HE") => Some(AudioCodec::HEAAC),
+                _ => Some(AudioCodec::LCAAC),
+            },
+            "mp3" => Some(AudioCodec::MP3),
+            "opus" => Some(AudioCodec::Opus),
+            "vorbis" => Some(AudioCodec::Vorbis),
+            codec if codec.starts_with("pcm") => Some(AudioCodec::WAVLPCM),
+            _ => None,
+        }
+    }
+
+    ///Sources with a PQ transfer are treated as HDR10 even without mastering display metadata
+    pub fn get_dynamic_range(&self) -> Option<DynamicRange> {
+        if self.stream_type != StreamType::Video {
+            return None;
+        }
+        match self.transfer_characteristics.as_deref() {
+            Some("smpte2084") => Some(DynamicRange::HDR10),
+            Some("arib-std-b67") => Some(DynamicRange::HLG),
+            _ => Some(DynamicRange::SDR),
+        }
+    }
+}
+
+///Codec names from mediainfo are changed to the names ffmpeg uses so both probers store the same
+///codec for the same stream
+pub fn normalise_mediainfo_codec(format: &str) -> String {
+    match format {
+        "AVC" => "h264",
+        "HEVC" => "hevc",
+        "MPEG-4 Visual" => "mpeg4",
+        "MPEG Video" => "mpeg2video",
+        "E-AC-3" => "eac3",
+        "AC-3" => "ac3",
+        "MLP FBA" => "truehd",
+        "MPEG Audio" => "mp3",
+        "UTF-8" => "subrip",
+        "PGS" => "hdmv_pgs_subtitle",
+        "VobSub" => "dvd_subtitle",
+        "Timed Text" => "mov_text",
+        format => return format.to_lowercase(),
+    }
+    .to_string()
+}
+
+///Languages are stored as ISO 639-2/B codes, the codes Matroska uses and ffprobe reports, so
+///mediainfo's two letter codes and the terminology variants some files are tagged with, such as
+///deu instead of ger, are changed to them. Region subtags such as en-US are dropped and
+///undetermined languages are none
+pub fn normalise_language(language: &str) -> Option<String> {
+    let language = language
+        .split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+    if language.is_empty() || language == "und" {
+        return None;
+    }
+    ISO_639_1_CODES
+        .iter()
+        .chain(ISO_639_2_TERMINOLOGY_CODES.iter())
+        .find(|(code, _)| *code == language)
+        .map(|(_, bibliographic_code)| bibliographic_code.to_string())
+        .or(Some(language))
+}
+
+//(ISO 639-1, ISO 639-2/B)
+const ISO_639_1_CODES: [(&str, &str); 184] = [
+    ("aa", "aar"),
+    ("ab", "abk"),
+    ("ae", "ave"),
+    ("af", "afr"),
+    ("ak", "aka"),
+    ("am", "amh"),
+    ("an", "arg"),
+    ("ar", "ara"),
+    ("as", "asm"),
+    ("av", "ava"),
+    ("ay", "aym"),
+    ("az", "aze"),
+    ("ba", "bak"),
+    ("be", "bel"),
+    ("bg", "bul"),
+    ("bh", "bih"),
+    ("bi", "bis"),
+    ("bm", "bam"),
+    ("bn", "ben"),
+    ("bo", "tib"),
+    ("br", "bre"),
+    ("bs", "bos"),
+    ("ca", "cat"),
+    ("ce", "che"),
+    ("ch", "cha"),
+    ("co", "cos"),
+    ("cr", "cre"),
+    ("cs", "cze"),
+    ("cu", "chu"),
+    ("cv", "chv"),
+    ("cy", "wel"),
+    ("da", "dan"),
+    ("de", "ger"),
+    ("dv", "div"),
+    ("dz", "dzo"),
+    ("ee", "ewe"),
+    ("el", "gre"),
+    ("en", "eng"),
+    ("eo", "epo"),
+    ("es", "spa"),
+    ("et", "est"),
+    ("eu", "baq"),
+    ("fa", "per"),
+    ("ff", "ful"),
+    ("fi", "fin"),
+    ("fj", "fij"),
+    ("fo", "fao"),
+    ("fr", "fre"),
+    ("fy", "fry"),
+    ("ga", "gle"),
+    ("gd", "gla"),
+    ("gl", "glg"),
+    ("gn", "grn"),
+    ("gu", "guj"),
+    ("gv", "glv"),
+    ("ha", "hau"),
+    ("he", "heb"),
+    ("hi", "hin"),
+    ("ho", "hmo"),
+    ("hr", "hrv"),
+    ("ht", "hat"),
+    ("hu", "hun"),
+    ("hy", "arm"),
+    ("hz", "her"),
+    ("ia", "ina"),
+    ("id", "ind"),
+    ("ie", "ile"),
+    ("ig", "ibo"),
+    ("ii", "iii"),
+    ("ik", "ipk"),
+    ("io", "ido"),
+    ("is", "ice"),
+    ("it", "ita"),
+    ("iu", "iku"),
+    ("ja", "jpn"),
+    ("jv", "jav"),
+    ("ka", "geo"),
+    ("kg", "kon"),
+    ("ki", "kik"),
+    ("kj", "kua"),
+    ("kk", "kaz"),
+    ("kl", "kal"),
+    ("km", "khm"),
+    ("kn", "kan"),
+    ("ko", "kor"),
+    ("kr", "kau"),
+    ("ks", "kas"),
+    ("ku", "kur"),
+    ("kv", "kom"),
+    ("kw", "cor"),
+    ("ky", "kir"),
+    ("la", "lat"),
+    ("lb", "ltz"),
+    ("lg", "lug"),
+    ("li", "lim"),
+    ("ln", "lin"),
+    ("lo", "lao"),
+    ("lt", "lit"),
+    ("lu", "lub"),
+    ("lv", "lav"),
+    ("mg", "mlg"),
+    ("mh", "mah"),
+    ("mi", "mao"),
+    ("mk", "mac"),
+    ("ml", "mal"),
+    ("mn", "mon"),
+    ("mr", "mar"),
+    ("ms", "may"),
+    ("mt", "mlt"),
+    ("my", "bur"),
+    ("na", "nau"),
+    ("nb", "nob"),
+    ("nd", "nde"),
+    ("ne", "nep"),
+    ("ng", "ndo"),
+    ("nl", "dut"),
+    ("nn", "nno"),
+    ("no", "nor"),
+    ("nr", "nbl"),
+    ("nv", "nav"),
+    ("ny", "nya"),
+    ("oc", "oci"),
+    ("oj", "oji"),
+    ("om", "orm"),
+    ("or", "ori"),
+    ("os", "oss"),
+    ("pa", "pan"),
+    ("pi", "pli"),
+    ("pl", "pol"),
+    ("ps", "pus"),
+    ("pt", "por"),
+    ("qu", "que"),
+    ("rm", "roh"),
+    ("rn", "run"),
+    ("ro", "rum"),
+    ("ru", "rus"),
+    ("rw", "kin"),
+    ("sa", "san"),
+    ("sc", "srd"),
+    ("sd", "snd"),
+    ("se", "sme"),
+    ("sg", "sag"),
+    ("si", "sin"),
+    ("sk", "slo"),
+    ("sl", "slv"),
+    ("sm", "smo"),
+    ("sn", "sna"),
+    ("so", "som"),
+    ("sq", "alb"),
+    ("sr", "srp"),
+    ("ss", "ssw"),
+    ("st", "sot"),
+    ("su", "sun"),
+    ("sv", "swe"),
+    ("sw", "swa"),
+    ("ta", "tam"),
+    ("te", "tel"),
+    ("tg", "tgk"),
+    ("th", "tha"),
+    ("ti", "tir"),
+    ("tk", "tuk"),
+    ("tl", "tgl"),
+    ("tn", "tsn"),
+    ("to", "ton"),
+    ("tr", "tur"),
+    ("ts", "tso"),
+    ("tt", "tat"),
+    ("tw", "twi"),
+    ("ty", "tah"),
+    ("ug", "uig"),
+    ("uk", "ukr"),
+    ("ur", "urd"),
+    ("uz", "uzb"),
+    ("ve", "ven"),
+    ("vi", "vie"),
+    ("vo", "vol"),
+    ("wa", "wln"),
+    ("wo", "wol"),
+    ("xh", "xho"),
+    ("yi", "yid"),
+    ("yo", "yor"),
+    ("za", "zha"),
+    ("zh", "chi"),
+    ("zu", "zul"),
+];
+
+//(ISO 639-2/T, ISO 639-2/B) for the languages where the two differ
+const ISO_639_2_TERMINOLOGY_CODES: [(&str, &str); 20] = [
+    ("sqi", "alb"),
+    ("hye", "arm"),
+    ("eus", "baq"),
+    ("mya", "bur"),
+    ("zho", "chi"),
+    ("ces", "cze"),
+    ("nld", "dut"),
+    ("fra", "fre"),
+    ("kat", "geo"),
+    ("deu", "ger"),
+    ("ell", "gre"),
+    ("isl", "ice"),
+    ("mkd", "mac"),
+    ("mri", "mao"),
+    ("msa", "may"),
+    ("fas", "per"),
+    ("ron", "rum"),
+    ("slk", "slo"),
+    ("bod", "tib"),
+    ("cym", "wel"),
+];
+
+///Picks out FileVersions by their streams, every field that is set has to match the same stream
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct StreamFilter {
+    pub stream_type: Option<StreamType>,
+    pub codec: Option<String>,
+    pub language: Option<String>,
+}
+
+impl StreamFilter {
+    ///Codecs and languages are compared case-insensitively, languages are normalised first so
+    ///en matches streams stored as eng
+    pub fn matches(&self, stream: &Stream) -> bool {
+        self.stream_type
+            .map_or(true, |stream_type| stream.stream_type == stream_type)
+            && self
+                .codec
+                .as_ref()
+                .map_or(true, |codec| stream.codec.eq_ignore_ascii_case(codec))
+            && self.language.as_ref().map_or(true, |language| {
+                stream.language.as_ref().is_some_and(|stream_language| {
+                    normalise_language(stream_language) == normalise_language(language)
+                })
+            })
+    }
+
+    pub fn matches_any(&self, streams: &[Stream]) -> bool {
+        streams.iter().any(|stream| self.matches(stream))
+    }
+}
@@ -3,12 +3,43 @@ mod tests {
     use {
         crate::{
             config::ServerConfig,
-            encode::{Encode, EncodeProfile},
-            generic::FileVersion,
+            designation::Designation,
+            device::{DeviceProfile, PlaybackIssue, VideoSupport},
+            duplicate::{DuplicateFileVersion, DuplicateKind, DuplicateReport},
+            encode::{Encode, EncodeProfile, EncodeString},
+            file_manager::{
+                find_device_encode_source, match_missing_files, rebase_path, Crawl, ImportPlanner,
+                LibraryType, Reason, RootDirectory, TrackedDirectories,
+            },
+            generic::{
+                fast_hash_reader, fast_hash_with_version, hash_reader, hash_with_version,
+                FileVersion, Generic, CURRENT_HASH_VERSION, LEGACY_HASH_VERSION,
+            },
             get_extension, get_file_name, get_file_stem, get_parent_directory,
             get_show_title_from_pathbuf,
-            model::FileVersionModel,
+            hashing::{HashJob, HashProgress, HashQueue},
+            ignore::{check_ignore_rules, IgnoreRules, IGNORE_FILE_NAME},
+            import_plan::{ImportPlan, PlannedFile, PlannedImport},
+            integrity::{verify_file_version, RateLimitedReader},
+            model::{CompletedEncodeModel, EpisodeModel, FileVersionModel},
+            movie::Movie,
+            nfo::{find_nfo_episode, parse_episode_nfo, parse_show_nfo, NfoShow},
+            parser::{
+                parse_episode, parse_extra, parse_movie, parse_release_source, parse_show_title,
+                strip_release_tags, EpisodeParseRule, ParsedEpisode, ParsedMovie,
+            },
+            path_mapping::{PathMapper, PathMapping, WorkerPathMappings},
             pathbuf_to_string, pathbuf_with_suffix,
+            prober::{parse_ffprobe_json, parse_mediainfo_json, ProbeError},
+            profile::{AudioCodec, Container, DynamicRange, VideoCodec},
+            show::{Episode, Show},
+            sidecar::{get_sidecar_language, get_sidecar_suffix, Sidecar, SidecarKind},
+            statistics::{LibraryFile, LibraryStatistics, SavingsProjection, StatisticsCategory},
+            stream::{normalise_language, Stream, StreamFilter, StreamType},
+            validation::{
+                check_probed_output, get_expected_audio_streams, ProbedMedia, ValidationError,
+            },
+            watcher::PendingFile,
         },
         std::{
             process::Child,
@@ -23,14 +54,12 @@ mod tests {
 
     //TODO: Generate an instance of a worker, which can communicate with a test instance of the server
 
-    //Tests the generation and activation of an Encode, will somehow test a remote worker encoding it,
-    //but it shouldn't be that hard, it will just involve creating a dummy instance of the entire program :)
-    #[test]
-    fn test_encode() {
-        let file_version_model: FileVersionModel = FileVersionModel {
-            id: 0,
-            generic_uid: 0,
-            full_path: r".\unit_test_files\test_video.mp4".to_string(),
+    ///A FileVersionModel that hasn't been hashed or profiled, tests set the fields they need
+    fn file_version_model(id: i32, generic_uid: i32, full_path: &str) -> FileVersionModel {
+        FileVersionModel {
+            id,
+            generic_uid,
+            full_path: full_path.to_string(),
             master_file: true,
             file_hash: None,
             fast_file_hash: None,
@@ -40,8 +69,33 @@ mod tests {
             length_time: None,
             resolution_standard: None,
             container: None,
-        };
-        let file_version: FileVersion = FileVersion::from_model(file_version_model);
+            missing: false,
+            hash_version: CURRENT_HASH_VERSION,
+            corrupted: false,
+            verified_at: None,
+        }
+    }
+
+    ///A path in the temp directory that isn't shared with other tests or test runs
+    fn unique_temp_path(name: &str) -> std::path::PathBuf {
+        static COUNTER: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+        std::env::temp_dir().join(format!(
+            "tlm_{}_{}_{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+            name
+        ))
+    }
+
+    //Tests the generation and activation of an Encode, will somehow test a remote worker encoding it,
+    //but it shouldn't be that hard, it will just involve creating a dummy instance of the entire program :)
+    #[test]
+    fn test_encode() {
+        let file_version: FileVersion = FileVersion::from_model(file_version_model(
+            0,
+            0,
+            r".\unit_test_files\test_video.mp4",
+        ));
         let encode_profile: EncodeProfile = EncodeProfile::H265_TV_1080p;
         let server_config: Arc<RwLock<ServerConfig>> =
             Arc::new(RwLock::new(ServerConfig::default()));
@@ -66,6 +120,77 @@ mod tests {
         encode.delete_file_cache();
     }
 
+    #[test]
+    fn test_pending_file_settles() {
+        let settle_time = std::time::Duration::from_millis(50);
+        let mut pending_file = PendingFile::new(1024);
+        assert!(!pending_file.is_settled(1024, settle_time));
+        std::thread::sleep(settle_time);
+        assert!(!pending_file.is_settled(2048, settle_time));
+        std::thread::sleep(settle_time);
+        assert!(pending_file.is_settled(2048, settle_time));
+    }
+
+    #[test]
+    fn test_rebase_path() {
+        let from = std::path::Path::new("/media/tv/Show");
+        let to = std::path::Path::new("/media/tv/Show (2005)");
+        assert_eq!(
+            rebase_path(&from.join("Season 1/Show S01E01.mkv"), from, to),
+            Some(to.join("Season 1/Show S01E01.mkv"))
+        );
+        //Only whole directory names are matched
+        assert_eq!(
+            rebase_path(
+                std::path::Path::new("/media/tv/Show 2/S01E01.mkv"),
+                from,
+                to
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_match_missing_files() {
+        let directory = unique_temp_path("match_missing_files");
+        std::fs::create_dir_all(directory.join("Season 1")).unwrap();
+        let previous_path = directory.join("Show - S01E01.mkv");
+        let new_path = directory.join("Season 1/Show - S01E01.mkv");
+        let deleted_path = directory.join("Show - S01E02.mkv");
+        let added_path = directory.join("Season 1/Show - S01E03.mkv");
+        let still_there_path = directory.join("Show - S01E04.mkv");
+        for (full_path, data) in [
+            (&previous_path, "first episode"),
+            (&deleted_path, "second episode"),
+            (&added_path, "third episode"),
+            (&still_there_path, "fourth episode"),
+        ] {
+            std::fs::write(full_path, data).unwrap();
+        }
+        let hashed_file_version = |id: i32, full_path: &std::path::Path| {
+            FileVersion::from_model(FileVersionModel {
+                file_hash: Some(hash_with_version(full_path, CURRENT_HASH_VERSION).unwrap()),
+                fast_file_hash: Some(
+                    fast_hash_with_version(full_path, CURRENT_HASH_VERSION).unwrap(),
+                ),
+                ..file_version_model(id, id, &pathbuf_to_string(full_path))
+            })
+        };
+        let file_versions = vec![
+            hashed_file_version(1, &previous_path),
+            hashed_file_version(2, &deleted_path),
+            hashed_file_version(4, &still_there_path),
+        ];
+        std::fs::rename(&previous_path, &new_path).unwrap();
+        std::fs::remove_file(&deleted_path).unwrap();
+
+        let report = match_missing_files(&file_versions, &[added_path.clone(), new_path.clone()]);
+        std::fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(report.relinked, vec![(previous_path, new_path)]);
+        assert_eq!(report.orphaned, vec![deleted_path]);
+        assert_eq!(report.added, vec![added_path]);
+    }
+
     #[test]
     fn test_get_show_title_from_pathbuf() {
         assert_eq!(
@@ -94,6 +219,1660 @@ mod tests {
         );
     }
 
+    fn parsed_episode(
+        rule_name: &str,
+        season: i32,
+        episodes: Vec<i32>,
+        date: Option<&str>,
+        title: Option<&str>,
+    ) -> Option<ParsedEpisode> {
+        Some(ParsedEpisode {
+            rule_name: rule_name.to_string(),
+            season,
+            episodes,
+            date: date.map(|date| date.to_string()),
+            title: title.map(|title| title.to_string()),
+            release_source: None,
+        })
+    }
+
+    //Duplicate.rs
+    #[test]
+    fn test_find_duplicates() {
+        let generic = |generic_uid: i32, file_versions: Vec<(i32, &str, Option<&str>)>| {
+            let mut generic = Generic::default();
+            generic.generic_uid = Some(generic_uid);
+            for (id, full_path, file_hash) in file_versions {
+                generic
+                    .file_versions
+                    .push(FileVersion::from_model(FileVersionModel {
+                        file_hash: file_hash.map(|file_hash| file_hash.to_string()),
+                        ..file_version_model(id, generic_uid, full_path)
+                    }));
+            }
+            generic
+        };
+
+        //Generics 1 and 2 are identical copies, 3 and 4 are the same movie at different resolutions
+        let generic_files = vec![
+            generic(1, vec![(1, "/media/home/video.mp4", Some("a"))]),
+            generic(2, vec![(2, "/media/backup/video.mp4", Some("a"))]),
+        ];
+        let movies = vec![
+            Movie::new(
+                generic(
+                    3,
+                    vec![(3, "/movies/Movie (2019)/Movie (2019) 1080p.mkv", Some("b"))],
+                ),
+                "Movie".to_string(),
+                2019,
+            ),
+            Movie::new(
+                generic(
+                    4,
+                    vec![(4, "/movies/Movie (2019)/Movie (2019) 4k.mkv", Some("c"))],
+                ),
+                "Movie".to_string(),
+                2019,
+            ),
+        ];
+        //Generic 5 is S01E01-E02 so it isn't a duplicate of 6 and 7, which are both S01E01.
+        //Generic 6 has been encoded, the encode isn't a duplicate
+        let mut show = Show::new(1, "Show".to_string());
+        let mut encoded_generic = generic(
+            6,
+            vec![
+                (6, "/tv/Show/Season 1/Show - S01E01.mkv", None),
+                (7, "/tv/Show/Season 1/Show - S01E01_H265.mp4", None),
+            ],
+        );
+        encoded_generic.file_versions[1].master_file = false;
+        for (generic, episodes) in [
+            (
+                generic(
+                    5,
+                    vec![(5, "/tv/Show/Season 1/Show - S01E01-E02.mkv", None)],
+                ),
+                vec![1, 2],
+            ),
+            (encoded_generic, vec![1]),
+            (
+                generic(
+                    7,
+                    vec![(8, "/tv/Show/Season 1/Show - S01E01 720p.mkv", None)],
+                ),
+                vec![1],
+            ),
+        ] {
+            show.insert_episode(Episode::new(
+                generic,
+                1,
+                "Show".to_string(),
+                1,
+                episodes,
+                "".to_string(),
+                None,
+            ));
+        }
+        let shows = vec![show];
+
+        let mut generics: Vec<&Generic> = generic_files.iter().collect();
+        generics.extend(movies.iter().map(|movie| &movie.generic));
+        for season in &shows[0].seasons {
+            generics.extend(season.episodes.iter().map(|episode| &episode.generic));
+        }
+
+        let mut report = DuplicateReport::find(&generics, &shows, &movies);
+        assert_eq!(report.groups.len(), 3);
+        assert_eq!(report.groups[0].kind, DuplicateKind::Identical);
+        assert!(report.groups[0].contains(2, 2));
+        assert_eq!(report.groups[1].kind, DuplicateKind::Episode);
+        assert_eq!(
+            report.groups[1]
+                .file_versions
+                .iter()
+                .map(|file_version| (file_version.generic_uid, file_version.id))
+                .collect::<Vec<_>>(),
+            vec![(6, 6), (7, 8)]
+        );
+        assert_eq!(report.groups[2].kind, DuplicateKind::Movie);
+        assert_eq!(report.groups[2].file_versions.len(), 2);
+
+        //Keeping one copy leaves a single file in the group so it is no longer a duplicate
+        report.remove_file_versions(&[(2, 2)]);
+        assert_eq!(report.groups.len(), 2);
+        assert_eq!(report.groups[0].group_id, 1);
+
+        //Files that changed or are gone since the report was made aren't deleted
+        let full_path = unique_temp_path("find_duplicates.mkv");
+        std::fs::write(&full_path, "episode").unwrap();
+        let duplicate =
+            DuplicateFileVersion::from_file_version(&FileVersion::from_model(FileVersionModel {
+                file_hash: Some(hash_with_version(&full_path, CURRENT_HASH_VERSION).unwrap()),
+                ..file_version_model(1, 1, &pathbuf_to_string(&full_path))
+            }));
+        assert!(duplicate.is_unchanged());
+        std::fs::write(&full_path, "another episode").unwrap();
+        assert!(!duplicate.is_unchanged());
+        std::fs::remove_file(&full_path).unwrap();
+        assert!(!duplicate.is_unchanged());
+    }
+
+    //File_manager.rs
+    #[test]
+    fn test_root_directory_settings() {
+        let tracked_directories: TrackedDirectories = toml::from_str(
+            r#"
+            root_directories = ["/media/home_videos"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            tracked_directories
+                .get_library_type(&std::path::PathBuf::from("/media/home_videos/birthday.mp4")),
+            LibraryType::Mixed
+        );
+        //Symlinks are followed unless a root directory turns it off
+        assert!(tracked_directories.get_root_directories()[0].follow_symlinks);
+
+        let tracked_directories: TrackedDirectories = toml::from_str(
+            r#"
+            [[root_directories]]
+            path = "/media/tv"
+            library_type = "tv"
+            encode_profile = "H265_TV_1080p"
+
+            [[root_directories]]
+            path = "/media/tv/anime"
+            library_type = "tv"
+            allowed_extensions = ["mkv"]
+            follow_symlinks = false
+            same_file_system = true
+
+            [[root_directories]]
+            path = "/media/movies"
+            library_type = "movies"
+            "#,
+        )
+        .unwrap();
+        let episode_path =
+            std::path::PathBuf::from("/media/tv/anime/One Piece/Season 1/One Piece - S01E01.mkv");
+        let root_directory = tracked_directories
+            .get_root_directory_for_path(&episode_path)
+            .unwrap();
+        assert_eq!(
+            root_directory.path,
+            std::path::PathBuf::from("/media/tv/anime")
+        );
+        assert_eq!(
+            root_directory.allowed_extensions,
+            Some(vec!["mkv".to_string()])
+        );
+        assert!(!root_directory.follow_symlinks && root_directory.same_file_system);
+        assert!(!root_directory.deduplicate_hardlinks);
+        assert_eq!(
+            tracked_directories.get_show_title(&episode_path),
+            "One Piece"
+        );
+        assert_eq!(
+            tracked_directories
+                .get_show_title(&std::path::PathBuf::from("/media/tv/Alcatraz/S01E02.mp4")),
+            "Alcatraz"
+        );
+        assert_eq!(
+            tracked_directories
+                .get_encode_profile(&std::path::PathBuf::from("/media/tv/Alcatraz/S01E02.mp4"))
+                .to_string(),
+            EncodeProfile::H265_TV_1080p.to_string()
+        );
+        assert_eq!(
+            tracked_directories.get_library_type(&std::path::PathBuf::from(
+                "/media/movies/Blade Runner (1982).mkv"
+            )),
+            LibraryType::Movies
+        );
+
+        //Written back out to the config file as an array of tables
+        assert!(toml::to_string(&tracked_directories).is_ok());
+    }
+
+    //Generic.rs
+    #[test]
+    fn test_hash_versions() {
+        use std::{hash::Hasher, io::Cursor};
+
+        //Not a multiple of the read buffer so the last read is short
+        let data: Vec<u8> = (0..200_003).map(|i| (i % 251) as u8).collect();
+        let mut hasher = seahash::SeaHasher::new();
+        hasher.write(&data);
+        assert_eq!(
+            hash_reader(Cursor::new(&data)).unwrap(),
+            hasher.finish().to_string()
+        );
+
+        //Read errors are returned instead of panicking
+        struct FailingReader;
+        impl std::io::Read for FailingReader {
+            fn read(&mut self, _buffer: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("disk error"))
+            }
+        }
+        assert!(hash_reader(FailingReader).is_err());
+        let missing_path = unique_temp_path("missing.mkv");
+        assert!(hash_with_version(&missing_path, CURRENT_HASH_VERSION).is_err());
+        assert!(fast_hash_with_version(&missing_path, LEGACY_HASH_VERSION).is_err());
+
+        //Large enough to be sampled rather than hashed whole
+        let size = 13 * 1024 * 1024;
+        let original: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+        let fast_hash =
+            |data: &Vec<u8>| fast_hash_reader(Cursor::new(data), data.len() as u64).unwrap();
+        let mut changed_tail = original.clone();
+        changed_tail[size - 1] ^= 1;
+        assert_ne!(fast_hash(&original), fast_hash(&changed_tail));
+        let mut changed_middle = original.clone();
+        changed_middle[size / 2] ^= 1;
+        assert_ne!(fast_hash(&original), fast_hash(&changed_middle));
+        //Bytes between the samples aren't read
+        let mut changed_unsampled = original.clone();
+        changed_unsampled[4 * 1024 * 1024 + 100_000] ^= 1;
+        assert_eq!(fast_hash(&original), fast_hash(&changed_unsampled));
+
+        let mut file_version = FileVersion::from_model(FileVersionModel {
+            file_hash: Some("1".to_string()),
+            fast_file_hash: Some("2".to_string()),
+            hash_version: LEGACY_HASH_VERSION,
+            ..file_version_model(1, 1, "/media/video.mp4")
+        });
+        assert!(file_version.has_outdated_hash());
+        file_version.upgrade_hash_version();
+        assert!(!file_version.has_outdated_hash());
+        assert_eq!(file_version.hash_version, CURRENT_HASH_VERSION);
+        assert_eq!(file_version.hash, None);
+        assert_eq!(file_version.fast_hash, None);
+    }
+
+    //Hashing.rs
+    #[test]
+    fn test_hash_progress() {
+        let mut hash_progress = HashProgress::default();
+        //Resumed with 1 of 4 files already hashed
+        hash_progress.start(4, 4_000, 1, 1_000);
+        assert!(hash_progress.is_running);
+        assert_eq!(hash_progress.eta_seconds, None);
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        hash_progress.add_hashed(1_000);
+        assert_eq!(hash_progress.hashed_files, 2);
+        assert_eq!(hash_progress.hashed_bytes, 2_000);
+        //Only bytes hashed since resuming count towards the throughput
+        assert!(hash_progress.bytes_per_second > 0);
+        assert!(hash_progress.bytes_per_second <= 100_000);
+        assert_eq!(
+            hash_progress.eta_seconds,
+            Some(2_000 / hash_progress.bytes_per_second)
+        );
+    }
+
+    #[test]
+    fn test_hash_queue() {
+        use std::sync::atomic::AtomicBool;
+
+        let job = |id: i32, device: u64| HashJob {
+            file_version: FileVersion::from_model(file_version_model(
+                id,
+                id,
+                &format!("/mnt/{}/{}.mkv", device, id),
+            )),
+            size: 1_000,
+            device,
+        };
+        let stop = AtomicBool::new(false);
+        let queue = HashQueue::new(vec![job(1, 1), job(2, 1), job(3, 1), job(4, 2)], 2);
+        let next_id = || queue.next_job(&stop).unwrap().file_version.id;
+        assert_eq!(next_id(), 1);
+        assert_eq!(next_id(), 2);
+        //Device 1 is at its limit so its third file waits behind the other device's
+        assert_eq!(next_id(), 4);
+        queue.finish_job(1);
+        assert_eq!(next_id(), 3);
+        assert!(queue.next_job(&stop).is_none());
+
+        //Waiting for a free device stops when the run is stopped
+        let queue = HashQueue::new(vec![job(1, 1), job(2, 1)], 1);
+        assert_eq!(queue.next_job(&stop).unwrap().file_version.id, 1);
+        let stop = AtomicBool::new(true);
+        assert!(queue.next_job(&stop).is_none());
+    }
+
+    //Integrity.rs
+    #[test]
+    fn test_verify_file_version() {
+        use std::{
+            io::{Cursor, Read},
+            sync::atomic::{AtomicBool, Ordering},
+            time::Instant,
+        };
+
+        let data: Vec<u8> = (0..100_000).map(|i| (i % 251) as u8).collect();
+        let stop = AtomicBool::new(false);
+        //Reading 100KB at 1MB/s takes at least 100ms
+        let started_at = Instant::now();
+        let mut read_data = Vec::new();
+        RateLimitedReader::new(Cursor::new(&data), 1_000_000, &stop)
+            .read_to_end(&mut read_data)
+            .unwrap();
+        assert_eq!(read_data, data);
+        assert!(started_at.elapsed() >= std::time::Duration::from_millis(100));
+
+        let full_path = unique_temp_path("verify_file_version.mkv");
+        std::fs::write(&full_path, &data).unwrap();
+        let file_version = FileVersion::from_model(FileVersionModel {
+            file_hash: Some(hash_reader(Cursor::new(&data)).unwrap()),
+            ..file_version_model(1, 1, &pathbuf_to_string(&full_path))
+        });
+        assert_eq!(verify_file_version(&file_version, 0, &stop), Some(Ok(())));
+
+        //A single flipped bit is found
+        let mut rotted_data = data.clone();
+        rotted_data[50_000] ^= 1;
+        std::fs::write(&full_path, &rotted_data).unwrap();
+        assert_eq!(
+            verify_file_version(&file_version, 0, &stop),
+            Some(Err(hash_reader(Cursor::new(&rotted_data)).unwrap()))
+        );
+
+        //A file that can't be read is skipped rather than recorded as a mismatch, a directory
+        //can be opened but not read
+        let unreadable_path = unique_temp_path("verify_file_version_unreadable");
+        std::fs::create_dir(&unreadable_path).unwrap();
+        let unreadable_file_version = FileVersion::from_model(FileVersionModel {
+            file_hash: Some(hash_reader(Cursor::new(&data)).unwrap()),
+            ..file_version_model(2, 2, &pathbuf_to_string(&unreadable_path))
+        });
+        assert_eq!(
+            verify_file_version(&unreadable_file_version, 0, &stop),
+            None
+        );
+        std::fs::remove_dir(&unreadable_path).unwrap();
+
+        //A stopped verification doesn't count as a mismatch
+        stop.store(true, Ordering::Relaxed);
+        assert_eq!(verify_file_version(&file_version, 0, &stop), None);
+        std::fs::remove_file(&full_path).unwrap();
+    }
+
+    //Ignore.rs
+    #[test]
+    fn test_ignore_rules() {
+        let root = std::path::Path::new("/mnt/tvshows");
+        let ignore_rules = IgnoreRules::new(
+            &[
+                ".recycle_bin".to_string(),
+                "/@eaDir/".to_string(),
+                "*.partial".to_string(),
+                "Alcatraz/**/extras".to_string(),
+                "!keep.partial".to_string(),
+            ],
+            &[r"(?i)sample\.mkv$".to_string()],
+        );
+        let is_ignored = |full_path: &str| {
+            check_ignore_rules([(root, &ignore_rules)], std::path::Path::new(full_path))
+        };
+
+        assert_eq!(
+            is_ignored("/mnt/tvshows/.recycle_bin/Alcatraz - S01E01.mkv"),
+            Some(".recycle_bin".to_string())
+        );
+        assert_eq!(
+            is_ignored("/mnt/tvshows/my.recycle_binge/Alcatraz - S01E01.mkv"),
+            None
+        );
+        assert_eq!(
+            is_ignored("/mnt/tvshows/@eaDir/Alcatraz - S01E01.mkv"),
+            Some("/@eaDir/".to_string())
+        );
+        assert_eq!(
+            is_ignored("/mnt/tvshows/Alcatraz/@eaDir/Alcatraz - S01E01.mkv"),
+            None
+        );
+        assert_eq!(
+            is_ignored("/mnt/tvshows/Alcatraz/Season 1/Alcatraz - S01E01.mkv.partial"),
+            Some("*.partial".to_string())
+        );
+        assert_eq!(is_ignored("/mnt/tvshows/Alcatraz/keep.partial"), None);
+        assert_eq!(
+            is_ignored("/mnt/tvshows/Alcatraz/Season 1/extras/Making of.mkv"),
+            Some("Alcatraz/**/extras".to_string())
+        );
+        assert_eq!(
+            is_ignored("/mnt/tvshows/Alcatraz/Season 1/Sample.mkv"),
+            Some(r"(?i)sample\.mkv$".to_string())
+        );
+
+        let directory = std::env::temp_dir().join("tlm_test_ignore_rules");
+        std::fs::create_dir_all(&directory).unwrap();
+        let ignore_file_path = directory.join(IGNORE_FILE_NAME);
+        std::fs::write(&ignore_file_path, "#Comment\n\n*.nfo\n").unwrap();
+        let ignore_file_rules = IgnoreRules::from_ignore_file(&ignore_file_path).unwrap();
+        std::fs::remove_dir_all(&directory).unwrap();
+        assert_eq!(ignore_file_rules.rules.len(), 1);
+        assert_eq!(
+            check_ignore_rules(
+                [(directory.as_path(), &ignore_file_rules)],
+                &directory.join("Season 1/Alcatraz.nfo")
+            ),
+            Some(format!("{}: *.nfo", pathbuf_to_string(&ignore_file_path)))
+        );
+    }
+
+    //Import_plan.rs
+    #[test]
+    fn test_import_plan() {
+        let plan = ImportPlan {
+            accepted: vec![
+                PlannedFile {
+                    full_path: "/tv/Show/Season 1/Show - S01E01.mkv".to_string(),
+                    import: PlannedImport::Episode {
+                        show_title: "Show".to_string(),
+                        season: 1,
+                        episodes: vec![1],
+                        episode_title: None,
+                        release_source: None,
+                        rule_name: "season_episode".to_string(),
+                    },
+                },
+                PlannedFile {
+                    full_path: "/tv/Show/Season 1/Behind the scenes.mkv".to_string(),
+                    import: PlannedImport::Unmatched,
+                },
+            ],
+            new_shows: vec!["Show".to_string()],
+            ..ImportPlan::default()
+        };
+        assert_eq!(
+            plan.get_unmatched_files(),
+            vec!["/tv/Show/Season 1/Behind the scenes.mkv"]
+        );
+
+        //The designation is a field of each file so the WebUI doesn't need to unwrap variants
+        let json = serde_json::to_value(&plan).unwrap();
+        assert_eq!(json["accepted"][0]["import"]["designation"], "Episode");
+        assert_eq!(json["accepted"][0]["import"]["show_title"], "Show");
+        assert_eq!(json["accepted"][1]["import"]["designation"], "Unmatched");
+    }
+
+    #[test]
+    fn test_import_planner() {
+        let root = unique_temp_path("import_planner");
+        let files = [
+            "Show/Season 1/Show - S01E01.mkv",
+            "Show/Season 1/Show - S01E01.en.srt",
+            "Show/Season 1/Show - S01E02.mkv",
+            "Show/notes.txt",
+            "Existing Show/Season 2/Existing Show - S02E01.mkv",
+        ];
+        for file in files {
+            let full_path = root.join(file);
+            std::fs::create_dir_all(full_path.parent().unwrap()).unwrap();
+            std::fs::write(full_path, file).unwrap();
+        }
+        let mut config = ServerConfig::default();
+        config.tracked_directories = TrackedDirectories::new_empty();
+        config
+            .tracked_directories
+            .add_root_directory(RootDirectory::new(root.clone(), LibraryType::Tv));
+
+        let plan = ImportPlanner::new(
+            config,
+            [root.join("Show/Season 1/Show - S01E02.mkv")]
+                .into_iter()
+                .collect(),
+            Vec::new(),
+            vec![("Existing Show".to_string(), None)],
+            Vec::new(),
+        )
+        .plan();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let get_import = |file: &str| {
+            plan.accepted
+                .iter()
+                .find(|planned_file| planned_file.full_path == pathbuf_to_string(&root.join(file)))
+                .map(|planned_file| planned_file.import.clone())
+        };
+        assert_eq!(plan.accepted.len(), 2);
+        assert_eq!(
+            get_import("Show/Season 1/Show - S01E01.mkv"),
+            Some(PlannedImport::Episode {
+                show_title: "Show".to_string(),
+                season: 1,
+                episodes: vec![1],
+                episode_title: None,
+                release_source: None,
+                rule_name: "season_episode".to_string(),
+            })
+        );
+        assert!(matches!(
+            get_import("Existing Show/Season 2/Existing Show - S02E01.mkv"),
+            Some(PlannedImport::Episode { season: 2, .. })
+        ));
+        //Only shows that don't exist yet would be created
+        assert_eq!(plan.new_shows, vec!["Show".to_string()]);
+        assert_eq!(
+            plan.sidecars,
+            vec![pathbuf_to_string(
+                &root.join("Show/Season 1/Show - S01E01.en.srt")
+            )]
+        );
+        assert_eq!(plan.rejected.len(), 1);
+        assert_eq!(plan.rejected[0].reason, Reason::ExtensionDisallowed);
+        assert_eq!(plan.already_tracked_count, 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_crawl_links() {
+        use std::os::unix::fs::symlink;
+
+        let directory = unique_temp_path("crawl_links");
+        let outside = directory.join("outside");
+        let skipped_root = directory.join("skipped");
+        let followed_root = directory.join("followed");
+        let hardlink_root = directory.join("hardlinks");
+        for path in [
+            &outside,
+            &skipped_root,
+            &followed_root.join("dir"),
+            &hardlink_root,
+        ] {
+            std::fs::create_dir_all(path).unwrap();
+        }
+        std::fs::write(outside.join("outside.mkv"), "outside").unwrap();
+        std::fs::write(skipped_root.join("video.mkv"), "video").unwrap();
+        symlink(outside.join("outside.mkv"), skipped_root.join("link.mkv")).unwrap();
+        symlink(&outside, skipped_root.join("linked_dir")).unwrap();
+        std::fs::write(followed_root.join("dir/video.mkv"), "video").unwrap();
+        symlink(followed_root.join("dir"), followed_root.join("dir/loop")).unwrap();
+        symlink(&outside, followed_root.join("linked_dir")).unwrap();
+        std::fs::write(hardlink_root.join("a.mkv"), "hardlinked").unwrap();
+        std::fs::hard_link(hardlink_root.join("a.mkv"), hardlink_root.join("b.mkv")).unwrap();
+
+        let mut config = ServerConfig::default();
+        config.tracked_directories = TrackedDirectories::new_empty();
+        let mut skipped = RootDirectory::new(skipped_root.clone(), LibraryType::Generic);
+        skipped.follow_symlinks = false;
+        let mut hardlinks = RootDirectory::new(hardlink_root.clone(), LibraryType::Generic);
+        hardlinks.deduplicate_hardlinks = true;
+        for root_directory in [
+            skipped,
+            RootDirectory::new(followed_root.clone(), LibraryType::Generic),
+            hardlinks,
+        ] {
+            config
+                .tracked_directories
+                .add_root_directory(root_directory);
+        }
+
+        //b.mkv is already in the library so it's the path that is kept
+        let plan = ImportPlanner::new(
+            config,
+            [hardlink_root.join("b.mkv")].into_iter().collect(),
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+        )
+        .plan();
+
+        //Directories moved into a root directory while it's watched are walked the same way
+        let mut hardlinks = RootDirectory::new(hardlink_root.clone(), LibraryType::Generic);
+        hardlinks.deduplicate_hardlinks = true;
+        let mut crawl = Crawl::default();
+        crawl.walk(
+            &RootDirectory::new(followed_root.clone(), LibraryType::Generic),
+            &followed_root.join("dir"),
+        );
+        crawl.walk(&hardlinks, &hardlink_root);
+        crawl.deduplicate_hardlinks(&std::collections::HashSet::new());
+        std::fs::remove_dir_all(&directory).unwrap();
+        crawl.files.sort();
+        assert_eq!(
+            crawl.files,
+            vec![
+                followed_root.join("dir/video.mkv"),
+                hardlink_root.join("a.mkv")
+            ]
+        );
+        assert_eq!(
+            crawl.skipped,
+            vec![
+                (
+                    followed_root.join("dir/loop"),
+                    Reason::SymlinkLoop,
+                    Some(pathbuf_to_string(&followed_root.join("dir"))),
+                ),
+                (
+                    hardlink_root.join("b.mkv"),
+                    Reason::Hardlink,
+                    Some(pathbuf_to_string(&hardlink_root.join("a.mkv"))),
+                ),
+            ]
+        );
+
+        let mut accepted: Vec<String> = plan
+            .accepted
+            .iter()
+            .map(|planned_file| planned_file.full_path.clone())
+            .collect();
+        accepted.sort();
+        assert_eq!(
+            accepted,
+            vec![
+                pathbuf_to_string(&followed_root.join("dir/video.mkv")),
+                pathbuf_to_string(&followed_root.join("linked_dir/outside.mkv")),
+                pathbuf_to_string(&skipped_root.join("video.mkv")),
+            ]
+        );
+        let mut rejected: Vec<(String, Reason, Option<String>)> = plan
+            .rejected
+            .iter()
+            .map(|rejection| {
+                (
+                    rejection.full_path.clone(),
+                    rejection.reason,
+                    rejection.matched_rule.clone(),
+                )
+            })
+            .collect();
+        rejected.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            rejected,
+            vec![
+                (
+                    pathbuf_to_string(&followed_root.join("dir/loop")),
+                    Reason::SymlinkLoop,
+                    Some(pathbuf_to_string(&followed_root.join("dir"))),
+                ),
+                (
+                    pathbuf_to_string(&hardlink_root.join("a.mkv")),
+                    Reason::Hardlink,
+                    Some(pathbuf_to_string(&hardlink_root.join("b.mkv"))),
+                ),
+                (
+                    pathbuf_to_string(&skipped_root.join("link.mkv")),
+                    Reason::Symlink,
+                    None,
+                ),
+                (
+                    pathbuf_to_string(&skipped_root.join("linked_dir")),
+                    Reason::Symlink,
+                    None,
+                ),
+            ]
+        );
+        assert_eq!(plan.already_tracked_count, 1);
+    }
+
+    //Nfo.rs
+    #[test]
+    fn test_parse_nfo() {
+        let nfo_path = std::path::PathBuf::from("/mnt/tvshows/Alcatraz/tvshow.nfo");
+        let show_nfo = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes" ?>
+<tvshow>
+    <title>Alcatraz</title>
+    <premiered>2012-01-16</premiered>
+    <plot>Inmates who vanished in 1963 reappear in the present day.</plot>
+</tvshow>
+https://www.thetvdb.com/?tab=series&id=248814"#;
+        assert_eq!(
+            parse_show_nfo(show_nfo, &nfo_path),
+            Some(NfoShow {
+                title: Some("Alcatraz".to_string()),
+                year: Some(2012),
+                plot: Some("Inmates who vanished in 1963 reappear in the present day.".to_string()),
+            })
+        );
+        assert_eq!(parse_show_nfo("<tvshow><title>", &nfo_path), None);
+
+        let episode_nfo = r#"<?xml version="1.0" encoding="UTF-8"?>
+<episodedetails>
+    <title>Ernest Cobb</title>
+    <season>1</season>
+    <episode>2</episode>
+    <aired>2012-01-16</aired>
+</episodedetails>
+<episodedetails>
+    <title>Kit Nelson</title>
+    <season>1</season>
+    <episode>3</episode>
+    <aired>2012-01-23</aired>
+    <plot> </plot>
+</episodedetails>"#;
+        let nfo_episodes = parse_episode_nfo(episode_nfo, &nfo_path);
+        assert_eq!(nfo_episodes.len(), 2);
+        let nfo_episode = find_nfo_episode(&nfo_episodes, 1, 3).unwrap();
+        assert_eq!(nfo_episode.title, Some("Kit Nelson".to_string()));
+        assert_eq!(nfo_episode.aired, Some("2012-01-23".to_string()));
+        assert_eq!(nfo_episode.plot, None);
+        //Never falls back to another episode's details
+        assert_eq!(find_nfo_episode(&nfo_episodes, 1, 9), None);
+        assert_eq!(find_nfo_episode(&nfo_episodes, 2, 3), None);
+        assert_eq!(find_nfo_episode(&nfo_episodes[..1], 1, 3), None);
+
+        //A single entry without an episode number is for the file's only episode
+        let nfo_episodes = parse_episode_nfo(
+            "<episodedetails><title>Pilot</title><season>1</season></episodedetails>",
+            &nfo_path,
+        );
+        assert_eq!(
+            find_nfo_episode(&nfo_episodes, 1, 1).unwrap().title,
+            Some("Pilot".to_string())
+        );
+        assert_eq!(find_nfo_episode(&nfo_episodes, 2, 1), None);
+        let nfo_episodes = parse_episode_nfo(
+            "<episodedetails><title>Pilot</title></episodedetails>",
+            &nfo_path,
+        );
+        assert!(find_nfo_episode(&nfo_episodes, 3, 1).is_some());
+    }
+
+    //Parser.rs
+    #[test]
+    fn test_parse_episode_season_episode() {
+        let rules = EpisodeParseRule::default_rules();
+        assert_eq!(
+            parse_episode(&rules, "Alcatraz - S01E02 - Ernest Cobb HDTV-720p"),
+            Some(ParsedEpisode {
+                release_source: Some("HDTV".to_string()),
+                ..parsed_episode("season_episode", 1, vec![2], None, Some("Ernest Cobb")).unwrap()
+            })
+        );
+        assert_eq!(
+            parse_episode(&rules, "Show.Name.s02e10.720p.WEB"),
+            Some(ParsedEpisode {
+                release_source: Some("WEB".to_string()),
+                ..parsed_episode("season_episode", 2, vec![10], None, None).unwrap()
+            })
+        );
+        assert_eq!(
+            parse_episode(&rules, "Show - S01E01-E03"),
+            parsed_episode("season_episode", 1, vec![1, 2, 3], None, None)
+        );
+        assert_eq!(
+            parse_episode(&rules, "Show - S01E01E02"),
+            parsed_episode("season_episode", 1, vec![1, 2], None, None)
+        );
+        assert_eq!(parse_episode(&rules, "Show - S01E"), None);
+    }
+
+    #[test]
+    fn test_parse_episode_cross() {
+        let rules = EpisodeParseRule::default_rules();
+        assert_eq!(
+            parse_episode(&rules, "Show - 1x02 - Title"),
+            parsed_episode("cross", 1, vec![2], None, Some("Title"))
+        );
+        assert_eq!(
+            parse_episode(&rules, "Show 3x04-05"),
+            parsed_episode("cross", 3, vec![4, 5], None, None)
+        );
+        assert_eq!(parse_episode(&rules, "Home video 1920x1080"), None);
+    }
+
+    #[test]
+    fn test_parse_episode_season_episode_words() {
+        let rules = EpisodeParseRule::default_rules();
+        assert_eq!(
+            parse_episode(&rules, "Show Season 1 Episode 2"),
+            parsed_episode("season_episode_words", 1, vec![2], None, None)
+        );
+        assert_eq!(
+            parse_episode(&rules, "Show.Season.10.Episode.12 - Title"),
+            parsed_episode("season_episode_words", 10, vec![12], None, Some("Title"))
+        );
+    }
+
+    #[test]
+    fn test_parse_episode_daily() {
+        let rules = EpisodeParseRule::default_rules();
+        assert_eq!(
+            parse_episode(&rules, "The Daily Show - 2012-03-05 - Guest Name"),
+            parsed_episode(
+                "daily",
+                2012,
+                vec![305],
+                Some("2012-03-05"),
+                Some("Guest Name")
+            )
+        );
+        assert_eq!(
+            parse_episode(&rules, "The.Daily.Show.2012.11.20"),
+            parsed_episode("daily", 2012, vec![1120], Some("2012-11-20"), None)
+        );
+    }
+
+    #[test]
+    fn test_parse_episode_absolute() {
+        let rules = EpisodeParseRule::default_rules();
+        assert_eq!(
+            parse_episode(&rules, "[SubsPlease] One Piece - 1012 (1080p) [ABCD1234]"),
+            parsed_episode("absolute", 1, vec![1012], None, None)
+        );
+        assert_eq!(
+            parse_episode(&rules, "Show - 07v2"),
+            parsed_episode("absolute", 1, vec![7], None, None)
+        );
+        assert_eq!(
+            parse_episode(&rules, "Show - 123 [720p]"),
+            parsed_episode("absolute", 1, vec![123], None, None)
+        );
+        assert_eq!(parse_episode(&rules, "Holiday video"), None);
+        //Titles ending in a number aren't episodes without a release group
+        assert_eq!(parse_episode(&rules, "Blade Runner - 2049"), None);
+        assert_eq!(parse_episode(&rules, "Apollo - 13 (1995)"), None);
+    }
+
+    #[test]
+    fn test_parse_release_tags() {
+        assert_eq!(
+            parse_release_source("Show - S01E01 - Title 1080p WEBDL x264"),
+            Some("WEB-DL".to_string())
+        );
+        assert_eq!(
+            parse_release_source("Show.S01E01.Title.Blu-Ray.720p"),
+            Some("BluRay".to_string())
+        );
+        assert_eq!(
+            parse_release_source("Show - S01E01 - Charlotte's Web"),
+            None
+        );
+        assert_eq!(strip_release_tags("Ernest Cobb HDTV-720p"), "Ernest Cobb");
+        assert_eq!(strip_release_tags("Pilot [1080p]"), "Pilot");
+        assert_eq!(strip_release_tags("720p.WEB"), "");
+        assert_eq!(
+            parse_show_title("Alcatraz (2012)"),
+            ("Alcatraz".to_string(), Some(2012))
+        );
+        assert_eq!(parse_show_title("Alcatraz"), ("Alcatraz".to_string(), None));
+    }
+
+    #[test]
+    fn test_parse_movie() {
+        let parsed_movie = |title: &str, year: i32| {
+            Some(ParsedMovie {
+                title: title.to_string(),
+                year,
+            })
+        };
+        assert_eq!(
+            parse_movie(&std::path::PathBuf::from("/movies/Blade Runner (1982).mkv")),
+            parsed_movie("Blade Runner", 1982)
+        );
+        assert_eq!(
+            parse_movie(&std::path::PathBuf::from(
+                "/movies/Blade Runner 2049 (2017)/movie-1080p.mkv"
+            )),
+            parsed_movie("Blade Runner 2049", 2017)
+        );
+        assert_eq!(
+            parse_movie(&std::path::PathBuf::from("/movies/1917 (2019) [1080p].mp4")),
+            parsed_movie("1917", 2019)
+        );
+        assert_eq!(
+            parse_movie(&std::path::PathBuf::from("/movies/Home Video 2019.mp4")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_extra() {
+        let path = |path: &str| std::path::PathBuf::from(path);
+        assert_eq!(
+            parse_extra(&path("/tv/Show/Specials/Show - S00E01.mkv"), &path("/tv")),
+            Some((Designation::Special, path("/tv/Show/Show - S00E01.mkv")))
+        );
+        assert_eq!(
+            parse_extra(&path("/tv/Show/Season 00/Show - S00E02.mkv"), &path("/tv")),
+            Some((Designation::Special, path("/tv/Show/Show - S00E02.mkv")))
+        );
+        assert_eq!(
+            parse_extra(
+                &path("/movies/Movie (2019)/Featurettes/Making Of.mkv"),
+                &path("/movies")
+            ),
+            Some((
+                Designation::Extra,
+                path("/movies/Movie (2019)/Making Of.mkv")
+            ))
+        );
+        assert_eq!(
+            parse_extra(
+                &path("/movies/Movie (2019)/Movie (2019)-trailer.mkv"),
+                &path("/movies")
+            ),
+            Some((
+                Designation::Trailer,
+                path("/movies/Movie (2019)/Movie (2019).mkv")
+            ))
+        );
+        assert_eq!(
+            parse_extra(&path("/tv/Show/Season 1/Show - S01E01.mkv"), &path("/tv")),
+            None
+        );
+        //Only folders between the file and the show or movie folder count
+        assert_eq!(
+            parse_extra(
+                &path("/mnt/extras/tv/Show/Season 1/Show - S01E01.mkv"),
+                &path("/mnt/extras/tv")
+            ),
+            None
+        );
+        assert_eq!(
+            parse_extra(
+                &path("/tv/Specials/Season 1/Specials - S01E01.mkv"),
+                &path("/tv")
+            ),
+            None
+        );
+        assert_eq!(
+            parse_extra(
+                &path("/movies/Extras (2005)/Extras (2005).mkv"),
+                &path("/movies")
+            ),
+            None
+        );
+    }
+
+    //Prober.rs
+    #[test]
+    fn test_probers() {
+        let full_path = std::path::Path::new("/media/Show/Season 1/Show - S01E01.mkv");
+        //The audio stream comes first and the frame rate is unknown
+        let profile = parse_ffprobe_json(
+            r#"{
+                "streams": [
+                    {"index": 0, "codec_type": "audio", "codec_name": "aac"},
+                    {"index": 1, "codec_type": "video", "codec_name": "h264", "width": 1920,
+                        "height": 1080, "avg_frame_rate": "0/0", "r_frame_rate": "0/0"}
+                ],
+                "format": {"duration": "1425.120000"}
+            }"#,
+            full_path,
+        )
+        .unwrap();
+        assert_eq!(profile.width, Some(1920));
+        assert_eq!(profile.height, Some(1080));
+        assert_eq!(profile.framerate, None);
+        assert_eq!(profile.length_time, Some(1425.12));
+        assert_eq!(profile.container.unwrap().to_string(), "mkv");
+
+        let profile = parse_mediainfo_json(
+            r#"{"media": {"track": [
+                {"@type": "General", "Duration": "1425.120", "FileExtension": "mp4"},
+                {"@type": "Audio", "Format": "AAC"},
+                {"@type": "Video", "Width": "1280", "Height": "720", "FrameRate": "23.976"}
+            ]}}"#,
+            full_path,
+        )
+        .unwrap();
+        assert_eq!(profile.width, Some(1280));
+        assert_eq!(profile.framerate, Some(23.976));
+        assert_eq!(profile.container.unwrap().to_string(), "mp4");
+
+        assert_eq!(
+            parse_mediainfo_json(
+                r#"{"media": {"track": [{"@type": "General"}, {"@type": "Audio"}]}}"#,
+                full_path
+            )
+            .unwrap_err(),
+            ProbeError::NoVideoStream
+        );
+        assert_eq!(
+            parse_mediainfo_json(
+                r#"{"media": {"track": [{"@type": "Video", "Width": "wide"}]}}"#,
+                full_path
+            )
+            .unwrap_err(),
+            ProbeError::InvalidField {
+                field: "Width".to_string(),
+                value: "wide".to_string()
+            }
+        );
+    }
+
+    //Show.rs
+    #[test]
+    fn test_multi_episode_file() {
+        let episode_model = |generic_uid: i32, episode_number: i32| EpisodeModel {
+            generic_uid,
+            show_uid: 1,
+            episode_title: "".to_string(),
+            season_number: 1,
+            episode_number,
+            release_source: None,
+            air_date: None,
+            plot: None,
+        };
+        let mut generics: Vec<Generic> = Vec::new();
+        for generic_uid in 1..=3 {
+            let mut generic = Generic::default();
+            generic.generic_uid = Some(generic_uid);
+            generics.push(generic);
+        }
+
+        //Generic 1 is S01E01-E03, generic 2 is another version of S01E02 and generic 3 is S01E04
+        let episodes = Episode::from_episode_models(
+            &generics,
+            vec![
+                episode_model(1, 3),
+                episode_model(2, 2),
+                episode_model(1, 1),
+                episode_model(3, 4),
+                episode_model(1, 2),
+            ],
+        );
+        assert_eq!(episodes.len(), 3);
+        assert_eq!(episodes[0].show_episode, vec![1, 2, 3]);
+
+        let mut show = Show::new(1, "Show".to_string());
+        for episode in episodes {
+            show.insert_episode(episode);
+        }
+        assert_eq!(show.episode_count(), 4);
+    }
+
+    //Sidecar.rs
+    #[test]
+    fn test_sidecars() {
+        let path = std::path::PathBuf::from;
+        assert_eq!(
+            SidecarKind::from_path(&path("/tv/Show/Season 1/Show - S01E01.en.srt")),
+            Some(SidecarKind::Subtitle)
+        );
+        assert_eq!(
+            SidecarKind::from_path(&path("/movies/Movie (2019)/movie.nfo")),
+            Some(SidecarKind::Nfo)
+        );
+        assert_eq!(
+            SidecarKind::from_path(&path("/movies/Movie (2019)/folder.jpg")),
+            Some(SidecarKind::Artwork)
+        );
+        assert_eq!(
+            SidecarKind::from_path(&path("/movies/Movie (2019)/Movie (2019).mkv")),
+            None
+        );
+
+        assert_eq!(
+            get_sidecar_suffix("Show - S01E01", "Show - S01E01.en.forced.srt"),
+            Some(".en.forced.srt")
+        );
+        assert_eq!(
+            get_sidecar_suffix("Show - S01E01", "Show - S01E010.srt"),
+            None
+        );
+        assert_eq!(
+            get_sidecar_language(".en.forced.srt"),
+            Some("en".to_string())
+        );
+        assert_eq!(
+            get_sidecar_language(".pt-BR.srt"),
+            Some("pt-BR".to_string())
+        );
+        assert_eq!(get_sidecar_language(".sdh.srt"), None);
+        assert_eq!(get_sidecar_language(".srt"), None);
+
+        let sidecar = Sidecar {
+            id: 1,
+            generic_uid: 1,
+            full_path: path("/tv/Show/Season 1/Show - S01E01.en.srt"),
+            kind: SidecarKind::Subtitle,
+            language: Some("en".to_string()),
+        };
+        assert_eq!(
+            sidecar.get_path_for_video(
+                &path("/tv/Show/Season 1/Show - S01E01.mkv"),
+                &path("/tv/Show/Season 1/Show - S01E01_H265.mp4")
+            ),
+            Some(path("/tv/Show/Season 1/Show - S01E01_H265.en.srt"))
+        );
+        assert_eq!(
+            sidecar.get_path_for_video(
+                &path("/tv/Show/Season 1/Show - S01E02.mkv"),
+                &path("/tv/Show/Season 1/Show - S01E02_H265.mp4")
+            ),
+            None
+        );
+    }
+
+    //Stream.rs
+    #[test]
+    fn test_streams() {
+        let full_path = std::path::Path::new("/media/Movie (2020)/Movie (2020).mkv");
+        let ffprobe_streams = parse_ffprobe_json(
+            r#"{
+                "streams": [
+                    {"index": 0, "codec_type": "video", "codec_name": "hevc", "profile": "Main 10",
+                        "level": 153, "pix_fmt": "yuv420p10le", "width": 3840, "height": 2160,
+                        "disposition": {"default": 1, "forced": 0}},
+                    {"index": 1, "codec_type": "audio", "codec_name": "eac3", "channels": 6,
+                        "disposition": {"default": 1, "forced": 0},
+                        "tags": {"language": "jpn", "BPS": "640000"}},
+                    {"index": 2, "codec_type": "subtitle", "codec_name": "subrip",
+                        "disposition": {"default": 0, "forced": 1}, "tags": {"language": "eng"}},
+                    {"index": 3, "codec_type": "attachment", "codec_name": "ttf"}
+                ],
+                "format": {"duration": "6000.0"}
+            }"#,
+            full_path,
+        )
+        .unwrap()
+        .streams;
+        assert_eq!(
+            ffprobe_streams,
+            vec![
+                Stream {
+                    stream_index: 0,
+                    stream_type: StreamType::Video,
+                    codec: "hevc".to_string(),
+                    profile: Some("Main 10".to_string()),
+                    level: Some("5.1".to_string()),
+                    bit_depth: Some(10),
+                    bitrate: None,
+                    channels: None,
+                    language: None,
+                    is_default: true,
+                    is_forced: false,
+                    transfer_characteristics: None,
+                    colour_primaries: None,
+                    matrix_coefficients: None,
+                    mastering_display: None,
+                    content_light_level: None,
+                },
+                Stream {
+                    stream_index: 1,
+                    stream_type: StreamType::Audio,
+                    codec: "eac3".to_string(),
+                    profile: None,
+                    level: None,
+                    bit_depth: None,
+                    bitrate: Some(640_000),
+                    channels: Some(6),
+                    language: Some("jpn".to_string()),
+                    is_default: true,
+                    is_forced: false,
+                    transfer_characteristics: None,
+                    colour_primaries: None,
+                    matrix_coefficients: None,
+                    mastering_display: None,
+                    content_light_level: None,
+                },
+                Stream {
+                    stream_index: 2,
+                    stream_type: StreamType::Subtitle,
+                    codec: "subrip".to_string(),
+                    profile: None,
+                    level: None,
+                    bit_depth: None,
+                    bitrate: None,
+                    channels: None,
+                    language: Some("eng".to_string()),
+                    is_default: false,
+                    is_forced: true,
+                    transfer_characteristics: None,
+                    colour_primaries: None,
+                    matrix_coefficients: None,
+                    mastering_display: None,
+                    content_light_level: None,
+                },
+            ]
+        );
+
+        //The same file read by mediainfo gets the same codec names and level
+        let mediainfo_streams = parse_mediainfo_json(
+            r#"{"media": {"track": [
+                {"@type": "General", "Duration": "6000.000"},
+                {"@type": "Video", "StreamOrder": "0", "Format": "HEVC", "Format_Profile": "Main 10",
+                    "Format_Level": "5.1", "BitDepth": "10", "Width": "3840", "Height": "2160",
+                    "Default": "Yes", "Forced": "No"},
+                {"@type": "Audio", "StreamOrder": "1", "Format": "E-AC-3", "Channels": "6",
+                    "BitRate": "640000", "Language": "ja", "Default": "Yes", "Forced": "No"},
+                {"@type": "Text", "StreamOrder": "2", "Format": "UTF-8", "Language": "en",
+                    "Default": "No", "Forced": "Yes"}
+            ]}}"#,
+            full_path,
+        )
+        .unwrap()
+        .streams;
+        assert_eq!(mediainfo_streams.len(), 3);
+        assert_eq!(mediainfo_streams[0].level, ffprobe_streams[0].level);
+        for (mediainfo_stream, ffprobe_stream) in mediainfo_streams.iter().zip(&ffprobe_streams) {
+            assert_eq!(mediainfo_stream.stream_type, ffprobe_stream.stream_type);
+            assert_eq!(mediainfo_stream.codec, ffprobe_stream.codec);
+            assert_eq!(mediainfo_stream.language, ffprobe_stream.language);
+        }
+        assert_eq!(normalise_language("en-US"), Some("eng".to_string()));
+        assert_eq!(normalise_language("deu"), Some("ger".to_string()));
+        assert_eq!(normalise_language("ger"), Some("ger".to_string()));
+        assert_eq!(normalise_language("und"), None);
+
+        let japanese_audio = StreamFilter {
+            stream_type: Some(StreamType::Audio),
+            codec: None,
+            language: Some("JPN".to_string()),
+        };
+        assert!(japanese_audio.matches_any(&ffprobe_streams));
+        //The English stream is a subtitle
+        let english_audio = StreamFilter {
+            language: Some("eng".to_string()),
+            ..japanese_audio
+        };
+        assert!(!english_audio.matches_any(&ffprobe_streams));
+        //Two letter codes match the three letter codes streams are stored with
+        let japanese = StreamFilter {
+            language: Some("ja".to_string()),
+            ..StreamFilter::default()
+        };
+        assert!(japanese.matches_any(&ffprobe_streams));
+        let hevc = StreamFilter {
+            codec: Some("hevc".to_string()),
+            ..StreamFilter::default()
+        };
+        assert!(hevc.matches_any(&ffprobe_streams));
+    }
+
+    #[test]
+    fn test_hdr() {
+        let full_path = std::path::Path::new("/media/Movie (2020)/Movie (2020).mkv");
+        let ffprobe_stream = parse_ffprobe_json(
+            r#"{"streams": [
+                {"index": 0, "codec_type": "video", "codec_name": "hevc", "width": 3840,
+                    "color_transfer": "smpte2084", "color_primaries": "bt2020",
+                    "color_space": "bt2020nc", "side_data_list": [
+                        {"side_data_type": "Mastering display metadata",
+                            "red_x": "34000/50000", "red_y": "16000/50000",
+                            "green_x": "13250/50000", "green_y": "34500/50000",
+                            "blue_x": "7500/50000", "blue_y": "3000/50000",
+                            "white_point_x": "15635/50000", "white_point_y": "16450/50000",
+                            "min_luminance": "50/10000", "max_luminance": "10000000/10000"},
+                        {"side_data_type": "Content light level metadata",
+                            "max_content": 1000, "max_average": 400}
+                    ]}
+            ]}"#,
+            full_path,
+        )
+        .unwrap()
+        .streams
+        .remove(0);
+        let mastering_display =
+            "G(13250,34500)B(7500,3000)R(34000,16000)WP(15635,16450)L(10000000,50)";
+        assert_eq!(
+            ffprobe_stream.mastering_display.as_deref(),
+            Some(mastering_display)
+        );
+        assert_eq!(
+            ffprobe_stream.content_light_level.as_deref(),
+            Some("1000,400")
+        );
+        assert_eq!(
+            ffprobe_stream.get_dynamic_range(),
+            Some(DynamicRange::HDR10)
+        );
+
+        //mediainfo gives the same colour data in its own names
+        let mediainfo_stream = parse_mediainfo_json(
+            r#"{"media": {"track": [
+                {"@type": "Video", "Format": "HEVC", "Width": "3840",
+                    "transfer_characteristics": "PQ", "colour_primaries": "BT.2020",
+                    "matrix_coefficients": "BT.2020 non-constant",
+                    "MasteringDisplay_ColorPrimaries": "Display P3",
+                    "MasteringDisplay_Luminance": "min: 0.0050 cd/m2, max: 1000 cd/m2",
+                    "MaxCLL": "1000 cd/m2", "MaxFALL": "400 cd/m2"}
+            ]}}"#,
+            full_path,
+        )
+        .unwrap()
+        .streams
+        .remove(0);
+        assert_eq!(mediainfo_stream, ffprobe_stream);
+
+        let mut file_version =
+            FileVersion::from_model(file_version_model(0, 0, &pathbuf_to_string(full_path)));
+        file_version.streams = vec![ffprobe_stream];
+        fn get_arguments(file_version: &FileVersion, encode_profile: &EncodeProfile) -> String {
+            let mut encode_string =
+                EncodeString::generate_deactivated(file_version, encode_profile);
+            encode_string.activate(std::env::temp_dir());
+            encode_string.get_encode_string().join(" ")
+        }
+        let kept = get_arguments(&file_version, &EncodeProfile::H265_TV_4K_HDR);
+        assert!(kept.contains("-profile:v main10"));
+        assert!(kept.contains("-pix_fmt yuv420p10le"));
+        assert!(kept.contains(&format!("master-display={}", mastering_display)));
+        assert!(kept.contains("max-cll=1000,400"));
+        assert!(!kept.contains("tonemap"));
+        let tone_mapped = get_arguments(&file_version, &EncodeProfile::H265_TV_1080p);
+        assert!(tone_mapped.contains("tonemap=tonemap=hable"));
+        assert!(tone_mapped.contains("-color_trc bt709"));
+        assert!(!tone_mapped.contains("master-display"));
+        //H.264 profiles encode with x264, tone mapping and scaling share the one filter chain
+        let h264 = get_arguments(&file_version, &EncodeProfile::H264_TV_1080p);
+        assert!(h264.contains("-c:v libx264"));
+        assert!(h264.contains("-profile:v high -level:v 4.1"));
+        assert!(!h264.contains("x265"));
+        assert!(h264.contains(",format=yuv420p,scale='min(1920,iw)':-2"));
+        assert_eq!(h264.matches("-vf").count(), 1);
+
+        //SDR sources are encoded the same whichever profile is used
+        file_version.streams[0].transfer_characteristics = Some("bt709".to_string());
+        assert_eq!(
+            get_arguments(&file_version, &EncodeProfile::H265_HDR),
+            get_arguments(&file_version, &EncodeProfile::H265)
+        );
+    }
+
+    //Device.rs
+    #[test]
+    fn test_direct_play() {
+        let full_path = std::path::Path::new("/media/Movie (2020)/Movie (2020).mkv");
+        let profile = parse_ffprobe_json(
+            r#"{
+                "streams": [
+                    {"index": 0, "codec_type": "video", "codec_name": "hevc", "level": 153,
+                        "pix_fmt": "yuv420p10le", "width": 3840, "height": 2160,
+                        "avg_frame_rate": "24000/1001", "color_transfer": "smpte2084"},
+                    {"index": 1, "codec_type": "audio", "codec_name": "truehd",
+                        "disposition": {"default": 0}},
+                    {"index": 2, "codec_type": "audio", "codec_name": "aac", "profile": "LC",
+                        "disposition": {"default": 1}}
+                ],
+                "format": {"duration": "6000.0"}
+            }"#,
+            full_path,
+        )
+        .unwrap();
+        let mut file_version = FileVersion::from_model(FileVersionModel {
+            width: profile.width,
+            height: profile.height,
+            framerate: profile.framerate,
+            length_time: profile.length_time,
+            ..file_version_model(0, 0, &pathbuf_to_string(full_path))
+        });
+        let server_config = ServerConfig::default();
+        let get_device = |name: &str| server_config.get_device(name).unwrap();
+        assert_eq!(
+            get_device("chromecast_ultra").check_direct_play(&file_version),
+            vec![PlaybackIssue::NotProfiled]
+        );
+
+        file_version.resolution_standard = profile.resolution_standard;
+        file_version.container = profile.container;
+        //Video streams stored before colour data was read are removed so the file is profiled again
+        file_version.streams = profile.streams[1..].to_vec();
+        assert!(file_version.profile_is_none());
+        file_version.streams = profile.streams;
+        assert!(!file_version.profile_is_none());
+        //The default audio stream is the one checked
+        assert!(get_device("chromecast_ultra").can_direct_play(&file_version));
+        assert!(get_device("chromecast_google_tv").can_direct_play(&file_version));
+        assert_eq!(
+            get_device("chromecast_gen2").check_direct_play(&file_version),
+            vec![
+                PlaybackIssue::VideoCodec("hevc".to_string()),
+                PlaybackIssue::DynamicRange(DynamicRange::HDR10)
+            ]
+        );
+        assert_eq!(
+            get_device("browser").check_direct_play(&file_version)[0],
+            PlaybackIssue::Container(Some(Container::MKV))
+        );
+
+        //Only generics with no version that plays, or was already encoded for the device, are
+        //encoded for it
+        let mut generic = Generic::default();
+        generic.file_versions.push(file_version.clone());
+        let chromecast_gen2 = get_device("chromecast_gen2");
+        assert_eq!(
+            find_device_encode_source(&generic, &chromecast_gen2)
+                .map(|file_version| file_version.full_path.clone()),
+            Some(full_path.to_path_buf())
+        );
+        assert!(find_device_encode_source(&generic, &get_device("chromecast_ultra")).is_none());
+        let mut encoded_file_version = file_version.clone();
+        encoded_file_version.full_path =
+            pathbuf_with_suffix(full_path, "_H264_TV_1080p".to_string());
+        generic.file_versions.push(encoded_file_version);
+        assert!(find_device_encode_source(&generic, &chromecast_gen2).is_none());
+        //A version that hasn't been profiled might already play
+        generic.file_versions[1] = FileVersion::from_model(file_version_model(
+            1,
+            0,
+            "/media/Movie (2020)/Movie (2020) - Copy.mp4",
+        ));
+        assert!(find_device_encode_source(&generic, &chromecast_gen2).is_none());
+
+        //Devices in the config replace built in devices with the same name
+        let mut server_config = ServerConfig::default();
+        server_config.devices = vec![DeviceProfile {
+            name: "chromecast_ultra".to_string(),
+            containers: vec![Container::MKV],
+            video_codecs: vec![VideoSupport {
+                codec: VideoCodec::H265,
+                max_level: Some(5.1),
+                max_bit_depth: Some(10),
+                max_width: Some(1920),
+                max_height: None,
+                max_framerate: None,
+            }],
+            audio_codecs: vec![AudioCodec::LCAAC],
+            dynamic_ranges: vec![DynamicRange::SDR, DynamicRange::HDR10],
+            encode_profile: EncodeProfile::H265_TV_1080p,
+        }];
+        let devices = server_config.get_devices();
+        assert_eq!(
+            devices
+                .iter()
+                .filter(|device| device.name == "chromecast_ultra")
+                .count(),
+            1
+        );
+        assert_eq!(
+            server_config
+                .get_device("chromecast_ultra")
+                .unwrap()
+                .check_direct_play(&file_version),
+            vec![PlaybackIssue::Resolution(3840, 2160)]
+        );
+        assert!(server_config.get_device("toaster").is_none());
+    }
+
+    //Statistics.rs
+    #[test]
+    fn test_statistics() {
+        let library_file = |generic_uid: i32,
+                            full_path: &str,
+                            master_file: bool,
+                            show_title: Option<&str>,
+                            size: Option<u64>| {
+            let mut file_version = FileVersion::from_model(FileVersionModel {
+                master_file,
+                length_time: Some(4000.0),
+                ..file_version_model(0, generic_uid, full_path)
+            });
+            file_version.container = Some(Container::MKV);
+            LibraryFile {
+                file_version,
+                show_title: show_title.map(str::to_string),
+                root_directory: Some(full_path.split('/').take(2).collect::<Vec<_>>().join("/")),
+                size,
+            }
+        };
+        let files = vec![
+            library_file(
+                1,
+                "/tv/Law & Order, SVU/Season 1/Law & Order, SVU - S01E01.mkv",
+                true,
+                Some("Law & Order, SVU"),
+                Some(1_000_000_000),
+            ),
+            //Already encoded, so it isn't counted towards the savings
+            library_file(
+                1,
+                "/tv/Law & Order, SVU/Season 1/Law & Order, SVU - S01E01_H265.mp4",
+                false,
+                Some("Law & Order, SVU"),
+                Some(300_000_000),
+            ),
+            library_file(
+                2,
+                "/movies/Movie (2020).mkv",
+                true,
+                None,
+                Some(4_000_000_000),
+            ),
+            library_file(3, "/movies/Unreadable (2020).mkv", true, None, None),
+        ];
+        let completed_encode = CompletedEncodeModel {
+            id: 0,
+            generic_uid: 1,
+            encode_profile: "H265".to_string(),
+            source_size: 1_000_000_000,
+            output_size: 250_000_000,
+            finished_at: std::time::SystemTime::now(),
+        };
+        let savings = SavingsProjection::new(&files, &EncodeProfile::H265, &[completed_encode]);
+        assert_eq!(savings.compression_ratio, Some(0.25));
+        assert_eq!(savings.source_files, 1);
+        assert_eq!(savings.projected_size, Some(1_000_000_000));
+        assert_eq!(savings.reclaimed_size, Some(3_000_000_000));
+        //There's nothing to project from without a completed encode
+        let no_history = SavingsProjection::new(&files, &EncodeProfile::H265_TV_1080p, &[]);
+        assert_eq!(no_history.source_files, 2);
+        assert_eq!(no_history.reclaimed_size, None);
+
+        let statistics = LibraryStatistics::new(&files, Some(savings));
+        assert_eq!(statistics.unreadable_files, 1);
+        let get_group = |category: StatisticsCategory, name: &str| {
+            statistics
+                .groups
+                .iter()
+                .find(|group| group.category == category && group.name == name)
+                .cloned()
+        };
+        let total = get_group(StatisticsCategory::Total, "Total").unwrap();
+        assert_eq!((total.file_versions, total.size), (3, 5_300_000_000));
+        assert_eq!(
+            get_group(StatisticsCategory::Show, "Law & Order, SVU")
+                .unwrap()
+                .size,
+            1_300_000_000
+        );
+        assert_eq!(
+            get_group(StatisticsCategory::RootDirectory, "/movies")
+                .unwrap()
+                .file_versions,
+            1
+        );
+        //The movie is 8 Mbps and the episodes are 2 and 0.6 Mbps
+        assert!(get_group(StatisticsCategory::Bitrate, "5-10 Mbps").is_some());
+        assert!(get_group(StatisticsCategory::Bitrate, "2-5 Mbps").is_some());
+        assert!(get_group(StatisticsCategory::Bitrate, "0-2 Mbps").is_some());
+        assert!(get_group(StatisticsCategory::VideoCodec, "Unknown").is_some());
+
+        let csv = statistics.to_csv();
+        assert!(csv.starts_with("category,name,file_versions,size_bytes,length_seconds\n"));
+        assert!(csv.contains("\nShow,\"Law & Order, SVU\",2,1300000000,8000\n"));
+        assert!(csv.contains("\nSavings,H265 reclaimed,1,3000000000,\n"));
+    }
+
+    //Validation.rs
+    #[test]
+    fn test_validate_probed_output() {
+        let probed = ProbedMedia::from_ffprobe_json(
+            r#"{
+                "streams": [
+                    {"index": 0, "codec_type": "video", "codec_name": "hevc"},
+                    {"index": 1, "codec_type": "audio", "codec_name": "aac"},
+                    {"index": 2, "codec_type": "video", "codec_name": "mjpeg",
+                        "disposition": {"attached_pic": 1}}
+                ],
+                "format": {"duration": "1425.120000"}
+            }"#,
+        )
+        .unwrap();
+        //Cover art doesn't count as a video stream
+        assert_eq!(
+            probed,
+            ProbedMedia {
+                duration: Some(1425.12),
+                video_streams: 1,
+                audio_streams: 1,
+            }
+        );
+        assert!(ProbedMedia::from_ffprobe_json("not json").is_none());
+
+        //Only one audio stream is kept from a source with several
+        let expected_audio_streams = Some(get_expected_audio_streams(3));
+        assert_eq!(
+            check_probed_output(&probed, Some(1426.0), expected_audio_streams, 2.0),
+            Ok(())
+        );
+        //A truncated encode
+        assert_eq!(
+            check_probed_output(&probed, Some(1500.0), expected_audio_streams, 2.0),
+            Err(ValidationError::DurationMismatch {
+                expected: 1500.0,
+                actual: Some(1425.12)
+            })
+        );
+        assert_eq!(
+            check_probed_output(&probed, None, Some(get_expected_audio_streams(0)), 2.0),
+            Err(ValidationError::AudioTrackMismatch {
+                expected: 0,
+                actual: 1
+            })
+        );
+        let audio_only = ProbedMedia {
+            video_streams: 0,
+            ..probed
+        };
+        assert_eq!(
+            check_probed_output(&audio_only, None, None, 2.0),
+            Err(ValidationError::NoVideoStream)
+        );
+    }
+
+    //Path_mapping.rs
+    #[test]
+    fn test_path_mapper() {
+        let global_mappings = vec![
+            PathMapping::new("/mnt/media", r"\\nas\media"),
+            PathMapping::new("/mnt/media/tv", r"\\nas\tv"),
+        ];
+        let worker_path_mappings = vec![WorkerPathMappings {
+            worker_uid: 2,
+            path_mappings: vec![PathMapping::new("/mnt/media/", "/srv/media/")],
+        }];
+
+        let path_mapper = PathMapper::new(&global_mappings, &worker_path_mappings, Some(1));
+        let server_path =
+            std::path::PathBuf::from("/mnt/media/tv/Alcatraz/Season 1/Alcatraz - S01E02.mkv");
+        let worker_path = path_mapper.to_worker_path(&server_path);
+        assert_eq!(
+            worker_path,
+            std::path::PathBuf::from(r"\\nas\tv\Alcatraz\Season 1\Alcatraz - S01E02.mkv")
+        );
+        assert_eq!(path_mapper.to_server_path(&worker_path), server_path);
+        assert_eq!(
+            path_mapper.to_worker_path(&std::path::PathBuf::from("/mnt/media2/movie.mkv")),
+            std::path::PathBuf::from("/mnt/media2/movie.mkv")
+        );
+
+        let path_mapper = PathMapper::new(&global_mappings, &worker_path_mappings, Some(2));
+        assert_eq!(
+            path_mapper.to_worker_path(&server_path),
+            std::path::PathBuf::from("/srv/media/tv/Alcatraz/Season 1/Alcatraz - S01E02.mkv")
+        );
+    }
+
     //Lib.rs
     #[test]
     fn test_os_string_to_string() {
@@ -170,4 +1949,4 @@ mod tests {
             std::path::PathBuf::from(r"/mnt/tvshows/Alcatraz/Season 1/")
         )
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,271 @@
+//!Checks the output of an encode before it's accepted into the library: its length has to be
+//!close to its source's, its video has to decode and it has to keep the audio it was expected to.
+//!Outputs that fail are moved to a quarantine directory and recorded as failed encodes
+use {
+    crate::{
+        config::ServerConfig,
+        database::{create_failed_encode, establish_connection},
+        encode::Encode,
+        ensure_path_exists, get_file_name,
+        model::{FailedEncodeModel, NewFailedEncode},
+        pathbuf_to_string,
+        prober::run_ffprobe,
+    },
+    serde::{Deserialize, Serialize},
+    serde_json::Value,
+    std::{
+        fmt, fs,
+        path::{Path, PathBuf},
+        process::Command,
+        sync::{Arc, RwLock},
+        time::SystemTime,
+    },
+    tracing::{error, warn},
+};
+
+///Directory inside the global temp directory that rejected encodes are moved to
+pub const QUARANTINE_DIRECTORY_NAME: &str = "quarantine";
+
+///The length and streams of a file as reported by ffprobe
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProbedMedia {
+    //Seconds
+    pub duration: Option<f64>,
+    pub video_streams: usize,
+    pub audio_streams: usize,
+}
+
+impl ProbedMedia {
+    ///None if ffprobe can't read the file
+    pub fn from_file(full_path: &Path) -> Option<Self> {
+        Self::from_ffprobe_json(&run_ffprobe(full_path).ok()?)
+    }
+
+    pub fn from_ffprobe_json(json: &str) -> Option<Self> {
+        let value: Value = serde_json::from_str(json).ok()?;
+        let streams = value["streams"].as_array()?;
+        let count_streams = |codec_type: &str| {
+            streams
+                .iter()
+                .filter(|stream| stream["codec_type"] == codec_type)
+                //Cover art is reported as a video stream
+                .filter(|stream| stream["disposition"]["attached_pic"] != 1)
+                .count()
+        };
+        Some(Self {
+            //ffprobe reports the duration as a string
+            duration: value["format"]["duration"]
+                .as_str()
+                .and_then(|duration| duration.parse::<f64>().ok()),
+            video_streams: count_streams("video"),
+            audio_streams: count_streams("audio"),
+        })
+    }
+}
+
+///Why an encode's output wasn't accepted into the library
+#[derive(Clone, Debug, PartialEq)]
+pub enum ValidationError {
+    MissingOutput,
+    ProbeFailed,
+    NoVideoStream,
+    UndecodableVideo(String),
+    //Seconds
+    DurationMismatch { expected: f64, actual: Option<f64> },
+    AudioTrackMismatch { expected: usize, actual: usize },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingOutput => write!(f, "The output file doesn't exist"),
+            Self::ProbeFailed => write!(f, "The output couldn't be read by ffprobe"),
+            Self::NoVideoStream => write!(f, "The output has no video stream"),
+            Self::UndecodableVideo(err) => write!(f, "The video failed to decode: {}", err),
+            Self::DurationMismatch { expected, actual } => match actual {
+                Some(actual) => write!(
+                    f,
+                    "The output is {:.2}s long but its source is {:.2}s",
+                    actual, expected
+                ),
+                None => write!(
+                    f,
+                    "The output has no duration, its source is {:.2}s",
+                    expected
+                ),
+            },
+            Self::AudioTrackMismatch { expected, actual } => write!(
+                f,
+                "The output has {} audio tracks but {} were expected",
+                actual, expected
+            ),
+        }
+    }
+}
+
+///Encode strings don't map streams, so ffmpeg keeps the one audio stream it picks by default
+pub fn get_expected_audio_streams(source_audio_streams: usize) -> usize {
+    source_audio_streams.min(1)
+}
+
+///Checks the probed output against what its source says it should have, any expectation that's
+///none isn't checked. duration_tolerance is in seconds
+pub fn check_probed_output(
+    output: &ProbedMedia,
+    expected_duration: Option<f64>,
+    expected_audio_streams: Option<usize>,
+    duration_tolerance: f64,
+) -> Result<(), ValidationError> {
+    if output.video_streams == 0 {
+        return Err(ValidationError::NoVideoStream);
+    }
+    if let Some(expected) = expected_duration {
+        if output.duration.map_or(true, |actual| {
+            (actual - expected).abs() > duration_tolerance
+        }) {
+            return Err(ValidationError::DurationMismatch {
+                expected,
+                actual: output.duration,
+            });
+        }
+    }
+    if let Some(expected) = expected_audio_streams {
+        if output.audio_streams != expected {
+            return Err(ValidationError::AudioTrackMismatch {
+                expected,
+                actual: output.audio_streams,
+            });
+        }
+    }
+    Ok(())
+}
+
+///Decodes the whole of the first video stream, stopping at the first error
+fn check_video_decodes(full_path: &Path) -> Result<(), ValidationError> {
+    let output = Command::new("ffmpeg")
+        .args([
+            "-v",
+            "error",
+            "-xerror",
+            "-i",
+            &pathbuf_to_string(full_path),
+            "-map",
+            "0:v:0",
+            "-f",
+            "null",
+            "-",
+        ])
+        .output()
+        .unwrap_or_else(|err| {
+            error!("Failed to execute process for ffmpeg. Err: {}", err);
+            panic!();
+        });
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(ValidationError::UndecodableVideo(
+            stderr.lines().last().unwrap_or_default().trim().to_string(),
+        ))
+    }
+}
+
+///Validates the output of an encode that has been moved to the server's temp directory.
+///source_length_time is the length stored for the source FileVersion, the source is probed for
+///its length if it doesn't have one
+pub fn validate_encode_output(
+    encode: &Encode,
+    source_length_time: Option<f64>,
+    duration_tolerance: f64,
+) -> Result<(), ValidationError> {
+    if !encode.temp_target_path.exists() {
+        return Err(ValidationError::MissingOutput);
+    }
+    let output =
+        ProbedMedia::from_file(&encode.temp_target_path).ok_or(ValidationError::ProbeFailed)?;
+    let source = ProbedMedia::from_file(&encode.source_path);
+    if source.is_none() {
+        warn!(
+            "Couldn't probe encode source, its audio tracks won't be checked: {}",
+            pathbuf_to_string(&encode.source_path)
+        );
+    }
+    check_probed_output(
+        &output,
+        source_length_time.or_else(|| source.as_ref().and_then(|source| source.duration)),
+        source
+            .as_ref()
+            .map(|source| get_expected_audio_streams(source.audio_streams)),
+        duration_tolerance,
+    )?;
+    //Decoding is left until last as it's by far the slowest check
+    check_video_decodes(&encode.temp_target_path)
+}
+
+///An encode whose output was rejected
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FailedEncode {
+    pub id: i32,
+    pub generic_uid: i32,
+    pub source_path: PathBuf,
+    pub target_path: PathBuf,
+    //None if the output never made it to the server
+    pub quarantine_path: Option<PathBuf>,
+    pub reason: String,
+    pub failed_at: SystemTime,
+}
+
+impl FailedEncode {
+    pub fn from_model(model: FailedEncodeModel) -> Self {
+        Self {
+            id: model.id,
+            generic_uid: model.generic_uid,
+            source_path: PathBuf::from(model.source_path),
+            target_path: PathBuf::from(model.target_path),
+            quarantine_path: model.quarantine_path.map(PathBuf::from),
+            reason: model.reason,
+            failed_at: model.failed_at,
+        }
+    }
+}
+
+///Moves the encode's output to the quarantine directory, if it made it to the server, and records
+///the encode as failed instead of adding it to the library
+pub fn reject_encode(encode: &Encode, reason: &str, server_config: &Arc<RwLock<ServerConfig>>) {
+    error!(
+        "Rejected encode of {}: {}",
+        pathbuf_to_string(&encode.source_path),
+        reason
+    );
+    let quarantine_path = if encode.temp_target_path.exists() {
+        let quarantine_directory = server_config
+            .read()
+            .unwrap()
+            .tracked_directories
+            .get_global_temp_directory()
+            .join(QUARANTINE_DIRECTORY_NAME);
+        ensure_path_exists(&quarantine_directory);
+        let quarantine_path = quarantine_directory.join(get_file_name(&encode.temp_target_path));
+        //The quarantine directory is inside the temp directory, so this never crosses file systems
+        if let Err(err) = fs::rename(&encode.temp_target_path, &quarantine_path) {
+            error!(
+                "Failed to move rejected encode to quarantine. IO output: {}",
+                err
+            );
+            panic!();
+        }
+        Some(quarantine_path)
+    } else {
+        None
+    };
+    create_failed_encode(
+        &establish_connection(),
+        NewFailedEncode {
+            generic_uid: encode.generic_uid,
+            source_path: pathbuf_to_string(&encode.source_path),
+            target_path: pathbuf_to_string(&encode.target_path),
+            quarantine_path: quarantine_path.as_deref().map(pathbuf_to_string),
+            reason: reason.to_string(),
+        },
+    );
+}
@@ -0,0 +1,315 @@
+//!Watches the tracked root directories for changes so that new media can be imported
+//!as it arrives instead of requiring a full crawl of every root directory
+use {
+    crate::{
+        config::ServerConfig,
+        file_manager::{rebase_path, Crawl, FileManager},
+        pathbuf_to_string,
+        scheduler::{GenerateProfiles, ImportFiles, ProcessNewFiles, Task, TaskType},
+    },
+    notify::{
+        event::{ModifyKind, RenameMode},
+        Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher,
+    },
+    std::{
+        collections::{HashMap, HashSet, VecDeque},
+        fs,
+        path::{Path, PathBuf},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            mpsc::{channel, Receiver, RecvTimeoutError},
+            Arc, Mutex, RwLock,
+        },
+        thread::{self, JoinHandle},
+        time::{Duration, Instant},
+    },
+    tracing::{debug, error, info, warn},
+};
+
+///A file that has been created or modified but may still be being written to
+#[derive(Clone, Debug)]
+pub struct PendingFile {
+    size: u64,
+    last_changed: Instant,
+}
+
+impl PendingFile {
+    pub fn new(size: u64) -> Self {
+        Self {
+            size,
+            last_changed: Instant::now(),
+        }
+    }
+
+    ///Records the current size of the file, returns true once the size
+    ///hasn't changed for at least the settle time
+    pub fn is_settled(&mut self, size: u64, settle_time: Duration) -> bool {
+        if size != self.size {
+            self.size = size;
+            self.last_changed = Instant::now();
+            return false;
+        }
+        self.last_changed.elapsed() >= settle_time
+    }
+}
+
+///Feeds file system events from the tracked root directories into the FileManager
+///and schedules tasks for files once they have finished being written
+pub struct FileWatcher {
+    config: Arc<RwLock<ServerConfig>>,
+    file_manager: Arc<Mutex<FileManager>>,
+    tasks: Arc<Mutex<VecDeque<Task>>>,
+    pending_files: HashMap<PathBuf, PendingFile>,
+}
+
+impl FileWatcher {
+    pub fn new(
+        config: Arc<RwLock<ServerConfig>>,
+        file_manager: Arc<Mutex<FileManager>>,
+        tasks: Arc<Mutex<VecDeque<Task>>>,
+    ) -> Self {
+        Self {
+            config,
+            file_manager,
+            tasks,
+            pending_files: HashMap::new(),
+        }
+    }
+
+    ///Starts watching in it's own thread until told to stop
+    pub fn start_watching(mut self, stop_watching: Arc<AtomicBool>) -> JoinHandle<()> {
+        thread::spawn(move || {
+            let (tx, rx) = channel();
+            let mut watcher: RecommendedWatcher = match notify::recommended_watcher(tx) {
+                Ok(watcher) => watcher,
+                Err(err) => {
+                    error!("Failed to create file system watcher. Err: {}", err);
+                    return;
+                }
+            };
+
+            let root_directories = self
+                .config
+                .read()
+                .unwrap()
+                .tracked_directories
+                .get_root_directories()
+                .clone();
+            for root_directory in &root_directories {
+                match watcher.watch(&root_directory.path, RecursiveMode::Recursive) {
+                    Ok(_) => info!("Watching: {}", pathbuf_to_string(&root_directory.path)),
+                    Err(err) => warn!(
+                        "Failed to watch: {}. Err: {}",
+                        pathbuf_to_string(&root_directory.path),
+                        err
+                    ),
+                }
+            }
+
+            self.watch(rx, stop_watching);
+        })
+    }
+
+    fn watch(&mut self, rx: Receiver<notify::Result<Event>>, stop_watching: Arc<AtomicBool>) {
+        let wait_time = Duration::from_secs(1);
+        while !stop_watching.load(Ordering::Relaxed) {
+            match rx.recv_timeout(wait_time) {
+                Ok(Ok(event)) => self.handle_event(event),
+                Ok(Err(err)) => error!("File system watcher error. Err: {}", err),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    error!("File system watcher stopped unexpectedly");
+                    break;
+                }
+            }
+            self.import_settled_files();
+        }
+    }
+
+    fn handle_event(&mut self, event: Event) {
+        if event.need_rescan() {
+            warn!("File system watcher missed events, scheduling a full import");
+            let mut tasks = self.tasks.lock().unwrap();
+            tasks.push_back(Task::new(TaskType::ImportFiles(ImportFiles::default())));
+            tasks.push_back(Task::new(TaskType::ProcessNewFiles(
+                ProcessNewFiles::default(),
+            )));
+            tasks.push_back(Task::new(TaskType::GenerateProfiles(
+                GenerateProfiles::default(),
+            )));
+            return;
+        }
+
+        match event.kind {
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+                self.rename(&event.paths[0], &event.paths[1]);
+            }
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) | EventKind::Remove(_) => {
+                for path in &event.paths {
+                    self.remove(path);
+                }
+            }
+            //Directories are only walked when they're new to the root directory
+            EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_)) => {
+                for path in event.paths {
+                    self.mark_pending_directory(path);
+                }
+            }
+            EventKind::Modify(_) => {
+                for path in event.paths {
+                    self.mark_pending(path);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    ///Tracks a file until it has finished being written to
+    fn mark_pending(&mut self, full_path: PathBuf) {
+        if !full_path.is_file() {
+            return;
+        }
+        if self
+            .file_manager
+            .lock()
+            .unwrap()
+            .existing_files_hashset
+            .contains(&full_path)
+        {
+            return;
+        }
+        let size = fs::metadata(&full_path).map_or(0, |metadata| metadata.len());
+        match self.pending_files.get_mut(&full_path) {
+            Some(pending_file) => {
+                pending_file.is_settled(size, Duration::ZERO);
+            }
+            None => {
+                debug!(
+                    "Waiting for file to settle: {}",
+                    pathbuf_to_string(&full_path)
+                );
+                self.pending_files.insert(full_path, PendingFile::new(size));
+            }
+        }
+    }
+
+    ///Tracks every file in a directory that was created or moved into a root directory, the
+    ///directory is walked with the same options as a crawl of its root directory
+    fn mark_pending_directory(&mut self, full_path: PathBuf) {
+        if !full_path.is_dir() {
+            self.mark_pending(full_path);
+            return;
+        }
+        let root_directory = match self
+            .config
+            .read()
+            .unwrap()
+            .tracked_directories
+            .get_root_directory_for_path(&full_path)
+        {
+            Some(root_directory) => root_directory.clone(),
+            None => return,
+        };
+        let mut crawl = Crawl::default();
+        crawl.walk(&root_directory, &full_path);
+        {
+            let mut file_manager_lock = self.file_manager.lock().unwrap();
+            crawl.deduplicate_hardlinks(&file_manager_lock.existing_files_hashset);
+            file_manager_lock.reject_crawled_files(crawl.skipped);
+        }
+        for full_path in crawl.files {
+            self.mark_pending(full_path);
+        }
+    }
+
+    fn rename(&mut self, from: &Path, to: &Path) {
+        if to.is_dir() {
+            self.rename_directory(from, to);
+            return;
+        }
+        if let Some(pending_file) = self.pending_files.remove(from) {
+            self.pending_files.insert(to.to_path_buf(), pending_file);
+            return;
+        }
+        if !self.file_manager.lock().unwrap().rename_file(from, to) {
+            self.mark_pending(to.to_path_buf());
+        }
+    }
+
+    ///Relinks everything under the directory's old path, then picks up any files in it
+    ///that weren't tracked yet
+    fn rename_directory(&mut self, from: &Path, to: &Path) {
+        self.pending_files = self
+            .pending_files
+            .drain()
+            .map(|(full_path, pending_file)| {
+                (
+                    rebase_path(&full_path, from, to).unwrap_or(full_path),
+                    pending_file,
+                )
+            })
+            .collect();
+        self.file_manager.lock().unwrap().rename_directory(from, to);
+        self.mark_pending_directory(to.to_path_buf());
+    }
+
+    fn remove(&mut self, full_path: &Path) {
+        if self.pending_files.remove(full_path).is_some() {
+            return;
+        }
+        //The path is gone so whether it was a directory is only known by what was inside it
+        self.pending_files
+            .retain(|pending_path, _| !pending_path.starts_with(full_path));
+        let mut file_manager_lock = self.file_manager.lock().unwrap();
+        file_manager_lock.remove_file(full_path);
+        file_manager_lock.remove_directory(full_path);
+    }
+
+    ///Imports every pending file whose size has stopped changing and schedules
+    ///processing and profiling for only those files
+    fn import_settled_files(&mut self) {
+        if self.pending_files.is_empty() {
+            return;
+        }
+        let settle_time = Duration::from_secs(self.config.read().unwrap().watcher_settle_time);
+        let mut settled_files: Vec<PathBuf> = Vec::new();
+        self.pending_files.retain(|full_path, pending_file| {
+            match fs::metadata(full_path) {
+                Ok(metadata) => {
+                    if pending_file.is_settled(metadata.len(), settle_time) {
+                        settled_files.push(full_path.clone());
+                        return false;
+                    }
+                    true
+                }
+                //The file is gone before it finished being written
+                Err(_) => false,
+            }
+        });
+        if settled_files.is_empty() {
+            return;
+        }
+
+        let mut imported_files: HashSet<PathBuf> = HashSet::new();
+        {
+            let mut file_manager_lock = self.file_manager.lock().unwrap();
+            for full_path in settled_files {
+                if file_manager_lock.import_file(full_path.clone()) {
+                    info!("Found new file: {}", pathbuf_to_string(&full_path));
+                    imported_files.insert(full_path);
+                }
+            }
+        }
+        if imported_files.is_empty() {
+            return;
+        }
+
+        let mut tasks = self.tasks.lock().unwrap();
+        tasks.push_back(Task::new(TaskType::ProcessNewFiles(
+            ProcessNewFiles::default(),
+        )));
+        tasks.push_back(Task::new(TaskType::GenerateProfiles(
+            GenerateProfiles::new(imported_files),
+        )));
+    }
+}
@@ -4,6 +4,8 @@ use {
     crate::{
         encode::{Encode, EncodeProfile},
         model::WorkerModel,
+        path_mapping::PathMapper,
+        pathbuf_to_string,
         worker_manager::AddEncodeMode,
         MessageSource,
     },
@@ -11,6 +13,7 @@ use {
     serde::{Deserialize, Serialize},
     std::{
         collections::VecDeque,
+        fmt,
         net::SocketAddr,
         path::PathBuf,
         str::FromStr,
@@ -21,6 +24,25 @@ use {
     tracing::error,
 };
 
+///Why an encode couldn't be sent to a worker
+#[derive(Clone, Debug, PartialEq)]
+pub enum QueueError {
+    //The server can't read the source, such as while the share it's on is offline
+    SourceUnavailable(PathBuf),
+}
+
+impl fmt::Display for QueueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SourceUnavailable(source_path) => write!(
+                f,
+                "The source file isn't accessible from the server: {}",
+                pathbuf_to_string(source_path)
+            ),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Worker {
     pub uid: Option<i32>,
@@ -86,26 +108,30 @@ impl Worker {
         //TODO: Have the worker send a message to the server if it can't access the file
     }
 
-    pub fn add_to_queue(&mut self, encode: Encode) {
+    ///Returns why the encode couldn't be sent, it's left for the caller to queue again
+    pub fn add_to_queue(
+        &mut self,
+        encode: Encode,
+        path_mapper: &PathMapper,
+    ) -> Result<(), QueueError> {
         //share credentials will have to be handled on the worker side
         if !encode.source_path.exists() {
-            error!(
-                "source_path is not accessible from the server: {:?}",
-                encode.source_path
-            );
-            //TODO: mark this Encode Task as failed because "file not found", change it to a
-            //      state where it can be stored, then manually repaired before being restarted,
-            panic!();
+            return Err(QueueError::SourceUnavailable(encode.source_path));
         }
 
         //Adds the encode to the workers queue server-side, this should mirror the client-side queue
+        //It keeps the server's paths so it can be put back in the WorkerManager's queue
         self.transcode_queue
             .write()
             .unwrap()
             .push_back(encode.clone());
 
-        //Sends the encode to the worker
-        self.send_message_to_worker(WorkerMessage::Encode(encode, AddEncodeMode::Back));
+        //Sends the encode to the worker with paths as the worker sees them
+        self.send_message_to_worker(WorkerMessage::Encode(
+            path_mapper.encode_to_worker(encode),
+            AddEncodeMode::Back,
+        ));
+        Ok(())
     }
 
     pub fn check_if_active(&mut self) {
@@ -127,6 +153,8 @@ pub enum WorkerMessage {
     EncodeFinished(i32, i32, PathBuf),
     MoveStarted(i32, i32, PathBuf, PathBuf),
     MoveFinished(i32, i32, Encode),
+    //The reason ffmpeg failed, the output is sent to the server's temp directory if there is any
+    EncodeFailed(i32, i32, Encode, String),
 
     //WebUI
     EncodeGeneric(i32, i32, AddEncodeMode, EncodeProfile),
@@ -1,25 +1,32 @@
 use {
     crate::{
+        config::ServerConfig,
         database::get_all_workers,
         database::{create_worker, establish_connection},
         encode::Encode,
         model::NewWorker,
+        path_mapping::PathMapper,
         worker::{Worker, WorkerMessage},
     },
     futures_channel::mpsc::UnboundedSender,
     serde::{Deserialize, Serialize},
     std::{
-        collections::VecDeque,
+        collections::{HashMap, VecDeque},
         net::SocketAddr,
         path::PathBuf,
         process::Child,
         sync::{Arc, Mutex, RwLock},
-        time::Instant,
+        time::{Duration, Instant},
     },
     tokio_tungstenite::tungstenite::Message,
     tracing::{debug, error, info, warn},
 };
 
+//How long an encode whose source couldn't be read waits before it's sent again, the wait doubles
+//each time it still can't be read
+const SOURCE_RETRY_DELAY: Duration = Duration::from_secs(60);
+const MAX_SOURCE_RETRY_DELAY: Duration = Duration::from_secs(60 * 60);
+
 pub enum WorkerAction {
     ClearCurrentTranscode(i32),
 }
@@ -29,6 +36,32 @@ pub struct WorkerManager {
     closed_workers: VecDeque<Worker>,
     transcode_queue: Arc<Mutex<VecDeque<Encode>>>,
     timeout_threshold: u64,
+    server_config: Arc<RwLock<ServerConfig>>,
+    //Keyed by the encode's source path
+    unavailable_sources: HashMap<PathBuf, UnavailableSource>,
+}
+
+///A source that couldn't be read the last time its encode was sent, such as while a NAS is offline
+struct UnavailableSource {
+    retry_at: Instant,
+    delay: Duration,
+}
+
+impl UnavailableSource {
+    ///Waits twice as long as it did the previous time the source couldn't be read
+    fn new(previous: Option<&Self>, now: Instant) -> Self {
+        let delay = previous.map_or(SOURCE_RETRY_DELAY, |previous| {
+            (previous.delay * 2).min(MAX_SOURCE_RETRY_DELAY)
+        });
+        Self {
+            retry_at: now + delay,
+            delay,
+        }
+    }
+
+    fn is_due(&self, now: Instant) -> bool {
+        self.retry_at <= now
+    }
 }
 
 impl WorkerManager {
@@ -36,15 +69,25 @@ impl WorkerManager {
         workers: Arc<Mutex<VecDeque<Worker>>>,
         transcode_queue: Arc<Mutex<VecDeque<Encode>>>,
         timeout_threshold: u64,
+        server_config: Arc<RwLock<ServerConfig>>,
     ) -> Self {
         Self {
             workers,
             closed_workers: get_all_workers(),
             transcode_queue,
             timeout_threshold,
+            server_config,
+            unavailable_sources: HashMap::new(),
         }
     }
 
+    pub fn get_path_mapper(&self, worker_uid: i32) -> PathMapper {
+        self.server_config
+            .read()
+            .unwrap()
+            .get_path_mapper(Some(worker_uid))
+    }
+
     pub fn perform_on_worker(
         &mut self,
         worker_uid: Option<i32>,
@@ -180,15 +223,51 @@ impl WorkerManager {
         );
     }
 
-    ///Uses Round-robin fill method
+    ///Uses Round-robin fill method. Encodes whose source can't be read are left in the queue
+    ///and skipped until they're due to be tried again, so they're sent once the source is back
     pub fn fill_transcode_queues(&mut self) {
         for worker in self.workers.lock().unwrap().iter_mut() {
             if worker.spaces_in_queue() < 1 {
                 continue;
             }
-            match self.transcode_queue.lock().unwrap().pop_front() {
-                Some(encode) => worker.add_to_queue(encode),
-                None => break,
+            let path_mapper = self
+                .server_config
+                .read()
+                .unwrap()
+                .get_path_mapper(worker.uid);
+            let mut transcode_queue = self.transcode_queue.lock().unwrap();
+            loop {
+                let now = Instant::now();
+                let index = match transcode_queue.iter().position(|encode| {
+                    self.unavailable_sources
+                        .get(&encode.source_path)
+                        .map_or(true, |unavailable_source| unavailable_source.is_due(now))
+                }) {
+                    Some(index) => index,
+                    None => return,
+                };
+                let encode = transcode_queue.remove(index).unwrap();
+                match worker.add_to_queue(encode.clone(), &path_mapper) {
+                    Ok(()) => {
+                        self.unavailable_sources.remove(&encode.source_path);
+                        break;
+                    }
+                    Err(err) => {
+                        let unavailable_source = UnavailableSource::new(
+                            self.unavailable_sources.get(&encode.source_path),
+                            now,
+                        );
+                        warn!(
+                            "Couldn't send encode to worker: {:?}, trying again in {}s. Err: {}",
+                            worker.uid,
+                            unavailable_source.delay.as_secs(),
+                            err
+                        );
+                        self.unavailable_sources
+                            .insert(encode.source_path.clone(), unavailable_source);
+                        transcode_queue.insert(index, encode);
+                    }
+                }
             }
         }
     }
@@ -308,12 +387,11 @@ impl WorkerTranscodeQueue {
                     .take()
                     .unwrap()
                     .wait_with_output();
-                let ok: bool = output.is_ok();
-                let _ = output.unwrap_or_else(|err| {
+                let output = output.unwrap_or_else(|err| {
                     error!("Failed to execute ffmpeg process. Err: {}", err);
                     panic!();
                 });
-                if ok {
+                if output.status.success() {
                     let encode: Encode;
                     //Guarantees the lock drops
                     {
@@ -368,9 +446,31 @@ impl WorkerTranscodeQueue {
 
                     self.clear_current_transcode();
                 } else {
-                    //TODO: Handle failure
-                    error!("Encode failed");
-                    panic!();
+                    let encode: Encode;
+                    //Guarantees the lock drops
+                    {
+                        encode = self.current_transcode.read().unwrap().clone().unwrap();
+                    }
+                    let reason = format!("ffmpeg exited with {}", output.status);
+                    error!("Encode failed, {}", reason);
+
+                    //Whatever ffmpeg wrote is sent to the server so it can be quarantined
+                    if PathBuf::from(encode.encode_string.get_target_path()).exists() {
+                        encode.transfer_encode_to_server_temp();
+                    }
+                    let _ = tx.start_send(
+                        WorkerMessage::EncodeFailed(
+                            worker_uid.read().unwrap().unwrap(),
+                            encode.generic_uid,
+                            encode.clone(),
+                            reason,
+                        )
+                        .to_message(),
+                    );
+
+                    encode.delete_file_cache();
+
+                    self.clear_current_transcode();
                 }
             }
         }
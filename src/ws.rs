@@ -1,7 +1,12 @@
 //!Module for handing web socket connections that will be used with
 //!both the cli and web ui controller to communicate in both directions as necessary
 
-use crate::ws_functions::{request_all_file_versions, encode_file, request_all_shows};
+use crate::ws_functions::{
+    encode_file, keep_duplicate, request_all_file_versions, request_all_movies, request_all_shows,
+    request_corruptions, request_direct_play, request_duplicates, request_failed_encodes,
+    request_filtered_file_versions, request_hash_progress, request_import_plan,
+    request_rejected_files, request_statistics, request_statistics_csv,
+};
 use {
     crate::{
         config::{ServerConfig, WorkerConfig},
@@ -16,8 +21,10 @@ use {
         worker::WorkerMessage,
         worker_manager::{WorkerManager, WorkerTranscodeQueue},
         ws_functions::{
-            encode_finished, encode_generic, encode_started, generate_profiles, hash_files,
-            import_files, initialise, move_finished, move_started, process_files,
+            encode_failed, encode_finished, encode_for_device, encode_generic, encode_started,
+            find_duplicates, generate_profiles, hash_files, import_files, initialise,
+            migrate_hashes, move_finished, move_started, plan_import, process_files, read_nfo,
+            reconcile_files, verify_files,
         },
         MessageSource, PeerMap, RequestType, WebUIMessage,
     },
@@ -93,10 +100,49 @@ async fn handle_web_connection(
                                         RequestType::AllShows => {
                                             request_all_shows(tx.clone(), file_manager.clone());
                                         },
+                                        RequestType::AllMovies => {
+                                            request_all_movies(tx.clone(), file_manager.clone());
+                                        },
+                                        RequestType::RejectedFiles(reason) => {
+                                            request_rejected_files(tx.clone(), file_manager.clone(), reason);
+                                        },
+                                        RequestType::Duplicates => {
+                                            request_duplicates(tx.clone(), file_manager.clone());
+                                        },
+                                        RequestType::ImportPlan => {
+                                            request_import_plan(tx.clone(), file_manager.clone());
+                                        },
+                                        RequestType::HashProgress => {
+                                            request_hash_progress(tx.clone(), file_manager.clone());
+                                        },
+                                        RequestType::Corruptions => {
+                                            request_corruptions(tx.clone());
+                                        },
+                                        RequestType::FailedEncodes => {
+                                            request_failed_encodes(tx.clone());
+                                        },
+                                        RequestType::FilteredFileVersions(stream_filter) => {
+                                            request_filtered_file_versions(tx.clone(), file_manager.clone(), stream_filter);
+                                        },
+                                        RequestType::DirectPlay => {
+                                            request_direct_play(tx.clone(), file_manager.clone());
+                                        },
+                                        RequestType::Statistics(encode_profile) => {
+                                            request_statistics(tx.clone(), file_manager.clone(), encode_profile);
+                                        },
+                                        RequestType::StatisticsCsv(encode_profile) => {
+                                            request_statistics_csv(tx.clone(), file_manager.clone(), encode_profile);
+                                        },
                                     };
                                 }
                                 WebUIMessage::Encode(generic_uid, id) => {
-                                    encode_file(file_manager.clone(), worker_manager_transcode_queue.clone(), &EncodeProfile::H264_TV_1080p, generic_uid, id);
+                                    encode_file(file_manager.clone(), worker_manager_transcode_queue.clone(), None, generic_uid, id);
+                                },
+                                WebUIMessage::KeepDuplicate(group_id, generic_uid, id) => {
+                                    keep_duplicate(tx.clone(), file_manager.clone(), group_id, generic_uid, id);
+                                },
+                                WebUIMessage::EncodeForDevice(device_name) => {
+                                    encode_for_device(tasks.clone(), server_config.clone(), worker_manager_transcode_queue.clone(), &device_name);
                                 },
                                 _ => {
                                     warn!("Server received a message it doesn't know how to handle");
@@ -138,6 +184,9 @@ async fn handle_web_connection(
                                 WorkerMessage::MoveFinished(_, _, _) => {
                                     move_finished(worker_message, worker_manager.clone(), file_manager.clone());
                                 }
+                                WorkerMessage::EncodeFailed(_, _, _, _) => {
+                                    encode_failed(worker_message, worker_manager.clone(), file_manager.clone());
+                                }
                                 _ => {
                                     warn!("Server received a message it doesn't know how to handle, ignoring");
                                 }
@@ -156,9 +205,18 @@ async fn handle_web_connection(
                 match message {
                     //Tasks
                     "hash" => hash_files(tasks.clone()),
+                    "migrate_hashes" => migrate_hashes(tasks.clone()),
                     "import" => import_files(tasks.clone()),
                     "process" => process_files(tasks.clone()),
                     "generate_profiles" => generate_profiles(tasks.clone()),
+                    "find_duplicates" => find_duplicates(tasks.clone()),
+                    "plan_import" => plan_import(tasks.clone()),
+                    "read_nfo" => read_nfo(tasks.clone()),
+                    "verify" => verify_files(tasks.clone()),
+                    "reconcile" => {
+                        reconcile_files(tasks.clone());
+                        process_files(tasks.clone());
+                    }
                     "bulk" => {
                         //TODO: Implement a way of making one task wait for another before it can run
                         //    : this will require tasks to be logged in the DB and knowledge of the uid for the await
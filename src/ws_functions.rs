@@ -3,17 +3,28 @@ use std::time::Instant;
 use tokio_tungstenite::tungstenite::Message;
 use tracing::{debug, warn};
 
-use crate::{WebUIMessage, WebUIFileVersion, WebUIShow};
+use crate::{
+    file_manager::Reason, WebUIFileVersion, WebUIMessage, WebUIMovie, WebUIRejectedFile, WebUIShow,
+};
 
 use {
     crate::{
         config::ServerConfig,
         copy,
+        database::{get_all_corruptions, get_all_failed_encodes},
+        device::DirectPlay,
         encode::{Encode, EncodeProfile},
         file_manager::FileManager,
         generic::FileVersion,
+        integrity::Corruption,
         pathbuf_to_string, remove_file,
-        scheduler::{GenerateProfiles, Hash, ImportFiles, ProcessNewFiles, Task, TaskType},
+        scheduler::{
+            EncodeForDevice, FindDuplicates, GenerateProfiles, Hash, ImportFiles, PlanImport,
+            ProcessNewFiles, ReadNfo, Reconcile, Task, TaskType, VerifyHashes,
+        },
+        statistics::{generate_statistics, record_completed_encode},
+        stream::StreamFilter,
+        validation::{reject_encode, validate_encode_output, FailedEncode},
         worker::WorkerMessage,
         worker_manager::{AddEncodeMode, WorkerManager},
         PeerMap, Tx,
@@ -21,7 +32,7 @@ use {
     std::{
         collections::VecDeque,
         net::SocketAddr,
-        sync::{Arc, Mutex, RwLock},
+        sync::{atomic::Ordering, Arc, Mutex, RwLock},
     },
     tracing::{error, info},
 };
@@ -49,6 +60,24 @@ pub fn hash_files(tasks: Arc<Mutex<VecDeque<Task>>>) {
         .push_back(Task::new(TaskType::Hash(Hash::default())))
 }
 
+///Rehashes files whose hashes were made with an older hash algorithm
+pub fn migrate_hashes(tasks: Arc<Mutex<VecDeque<Task>>>) {
+    tasks
+        .lock()
+        .unwrap()
+        .push_back(Task::new(TaskType::Hash(Hash::rehash_outdated())))
+}
+
+///Verifies every hashed file now, not only the ones that are due
+pub fn verify_files(tasks: Arc<Mutex<VecDeque<Task>>>) {
+    tasks
+        .lock()
+        .unwrap()
+        .push_back(Task::new(TaskType::VerifyHashes(VerifyHashes {
+            all: true,
+        })))
+}
+
 pub fn generate_profiles(tasks: Arc<Mutex<VecDeque<Task>>>) {
     tasks
         .lock()
@@ -58,6 +87,55 @@ pub fn generate_profiles(tasks: Arc<Mutex<VecDeque<Task>>>) {
         )));
 }
 
+pub fn reconcile_files(tasks: Arc<Mutex<VecDeque<Task>>>) {
+    tasks
+        .lock()
+        .unwrap()
+        .push_back(Task::new(TaskType::Reconcile(Reconcile::default())));
+}
+
+pub fn find_duplicates(tasks: Arc<Mutex<VecDeque<Task>>>) {
+    tasks
+        .lock()
+        .unwrap()
+        .push_back(Task::new(TaskType::FindDuplicates(
+            FindDuplicates::default(),
+        )));
+}
+
+pub fn plan_import(tasks: Arc<Mutex<VecDeque<Task>>>) {
+    tasks
+        .lock()
+        .unwrap()
+        .push_back(Task::new(TaskType::PlanImport(PlanImport::default())));
+}
+
+pub fn read_nfo(tasks: Arc<Mutex<VecDeque<Task>>>) {
+    tasks
+        .lock()
+        .unwrap()
+        .push_back(Task::new(TaskType::ReadNfo(ReadNfo::default())));
+}
+
+///Queues encodes for every file with no version that will direct play on the device
+pub fn encode_for_device(
+    tasks: Arc<Mutex<VecDeque<Task>>>,
+    server_config: Arc<RwLock<ServerConfig>>,
+    worker_manager_transcode_queue: Arc<Mutex<VecDeque<Encode>>>,
+    device_name: &str,
+) {
+    match server_config.read().unwrap().get_device(device_name) {
+        Some(device) => tasks
+            .lock()
+            .unwrap()
+            .push_back(Task::new(TaskType::EncodeForDevice(EncodeForDevice::new(
+                device,
+                worker_manager_transcode_queue,
+            )))),
+        None => warn!("{} is not a known device", device_name),
+    }
+}
+
 pub fn test(mut tx: Tx) {
     let _ = tx.start_send(Message::text("Fuck you".to_string()));
     info!("Telling WebUI \"Fuck You\"");
@@ -86,6 +164,16 @@ pub fn request_all_file_versions(
                 }
             }
         }
+        for movie in file_manager_lock.movies.iter() {
+            for file_version in movie.generic.file_versions.iter() {
+                file_versions.push(WebUIFileVersion::from_file_version(file_version));
+            }
+        }
+        for extra in file_manager_lock.extras.iter() {
+            for file_version in extra.generic.file_versions.iter() {
+                file_versions.push(WebUIFileVersion::from_file_version(file_version));
+            }
+        }
     }
     debug!("Sending {} file versions", file_versions.len());
     let _ = tx.start_send(WebUIMessage::FileVersions(file_versions).to_message());
@@ -95,6 +183,64 @@ pub fn request_all_file_versions(
     );
 }
 
+pub fn request_filtered_file_versions(
+    mut tx: Tx,
+    file_manager: Arc<Mutex<FileManager>>,
+    stream_filter: StreamFilter,
+) {
+    let file_versions: Vec<WebUIFileVersion> = file_manager
+        .lock()
+        .unwrap()
+        .generics()
+        .into_iter()
+        .flat_map(|generic| generic.file_versions.iter())
+        .filter(|file_version| stream_filter.matches_any(&file_version.streams))
+        .map(WebUIFileVersion::from_file_version)
+        .collect();
+    debug!("Sending {} filtered file versions", file_versions.len());
+    let _ = tx.start_send(WebUIMessage::FileVersions(file_versions).to_message());
+}
+
+pub fn request_direct_play(mut tx: Tx, file_manager: Arc<Mutex<FileManager>>) {
+    let file_manager_lock = file_manager.lock().unwrap();
+    let devices = file_manager_lock.config.read().unwrap().get_devices();
+    let direct_play: Vec<DirectPlay> = file_manager_lock
+        .generics()
+        .into_iter()
+        .flat_map(|generic| generic.file_versions.iter())
+        .map(|file_version| DirectPlay::new(file_version, &devices))
+        .collect();
+    debug!(
+        "Sending direct play for {} file versions",
+        direct_play.len()
+    );
+    let _ = tx.start_send(WebUIMessage::DirectPlay(direct_play).to_message());
+}
+
+pub fn request_statistics(
+    mut tx: Tx,
+    file_manager: Arc<Mutex<FileManager>>,
+    encode_profile: Option<EncodeProfile>,
+) {
+    //Reading the size of every file in the library can take a while on network storage
+    tokio::task::spawn_blocking(move || {
+        let statistics = generate_statistics(file_manager, encode_profile.as_ref());
+        debug!("Sending {} statistics groups", statistics.groups.len());
+        let _ = tx.start_send(WebUIMessage::Statistics(statistics).to_message());
+    });
+}
+
+pub fn request_statistics_csv(
+    mut tx: Tx,
+    file_manager: Arc<Mutex<FileManager>>,
+    encode_profile: Option<EncodeProfile>,
+) {
+    tokio::task::spawn_blocking(move || {
+        let csv = generate_statistics(file_manager, encode_profile.as_ref()).to_csv();
+        let _ = tx.start_send(WebUIMessage::StatisticsCsv(csv).to_message());
+    });
+}
+
 pub fn request_all_shows(
     mut tx: Tx,
     file_manager: Arc<Mutex<FileManager>>,
@@ -116,6 +262,114 @@ pub fn request_all_shows(
     );
 }
 
+pub fn request_all_movies(mut tx: Tx, file_manager: Arc<Mutex<FileManager>>) {
+    let start_time = Instant::now();
+    let mut movies: Vec<WebUIMovie>;
+    {
+        let file_manager_lock = file_manager.lock().unwrap();
+        movies = Vec::with_capacity(file_manager_lock.movies.len());
+        for movie in file_manager_lock.movies.iter() {
+            movies.push(WebUIMovie::from_movie(movie));
+        }
+    }
+    debug!("Sending {} movies", movies.len());
+    let _ = tx.start_send(WebUIMessage::Movies(movies).to_message());
+    debug!(
+        "Movies: It took {}ms to handle data request.",
+        start_time.elapsed().as_millis()
+    );
+}
+
+pub fn request_rejected_files(
+    mut tx: Tx,
+    file_manager: Arc<Mutex<FileManager>>,
+    reason: Option<Reason>,
+) {
+    let start_time = Instant::now();
+    let rejected_files: Vec<WebUIRejectedFile> = file_manager
+        .lock()
+        .unwrap()
+        .get_rejected_files(reason)
+        .into_iter()
+        .map(WebUIRejectedFile::from_path_buf_reason)
+        .collect();
+    debug!("Sending {} rejected files", rejected_files.len());
+    let _ = tx.start_send(WebUIMessage::RejectedFiles(rejected_files).to_message());
+    debug!(
+        "RejectedFiles: It took {}ms to handle data request.",
+        start_time.elapsed().as_millis()
+    );
+}
+
+pub fn request_duplicates(mut tx: Tx, file_manager: Arc<Mutex<FileManager>>) {
+    let duplicate_groups = file_manager.lock().unwrap().duplicate_report.groups.clone();
+    debug!("Sending {} duplicate groups", duplicate_groups.len());
+    let _ = tx.start_send(WebUIMessage::Duplicates(duplicate_groups).to_message());
+}
+
+///Deletes every other copy in the duplicate group and sends back what is left of the report
+pub fn keep_duplicate(
+    tx: Tx,
+    file_manager: Arc<Mutex<FileManager>>,
+    group_id: usize,
+    generic_uid: i32,
+    id: i32,
+) {
+    if !file_manager
+        .lock()
+        .unwrap()
+        .keep_duplicate(group_id, generic_uid, id)
+    {
+        warn!(
+            "No duplicate group: {} containing file: {}, {}",
+            group_id, generic_uid, id
+        );
+    }
+    request_duplicates(tx, file_manager);
+}
+
+pub fn request_import_plan(mut tx: Tx, file_manager: Arc<Mutex<FileManager>>) {
+    let import_plan = file_manager.lock().unwrap().import_plan.clone();
+    debug!(
+        "Sending import plan with {} accepted files",
+        import_plan.accepted.len()
+    );
+    let _ = tx.start_send(WebUIMessage::ImportPlan(import_plan).to_message());
+}
+
+///Only the progress's own lock is held while it's read, so this isn't held up by hashing
+pub fn request_hash_progress(mut tx: Tx, file_manager: Arc<Mutex<FileManager>>) {
+    let (hash_progress, hashing) = {
+        let file_manager_lock = file_manager.lock().unwrap();
+        (
+            file_manager_lock.hash_progress.clone(),
+            file_manager_lock.hashing.clone(),
+        )
+    };
+    let mut hash_progress = hash_progress.read().unwrap().clone();
+    //A run that panicked never marked its progress as stopped
+    hash_progress.is_running = hashing.load(Ordering::SeqCst);
+    let _ = tx.start_send(WebUIMessage::HashProgress(hash_progress).to_message());
+}
+
+pub fn request_corruptions(mut tx: Tx) {
+    let corruptions: Vec<Corruption> = get_all_corruptions()
+        .into_iter()
+        .map(Corruption::from_model)
+        .collect();
+    debug!("Sending {} corruptions", corruptions.len());
+    let _ = tx.start_send(WebUIMessage::Corruptions(corruptions).to_message());
+}
+
+pub fn request_failed_encodes(mut tx: Tx) {
+    let failed_encodes: Vec<FailedEncode> = get_all_failed_encodes()
+        .into_iter()
+        .map(FailedEncode::from_model)
+        .collect();
+    debug!("Sending {} failed encodes", failed_encodes.len());
+    let _ = tx.start_send(WebUIMessage::FailedEncodes(failed_encodes).to_message());
+}
+
 //WorkerMessage functions
 pub fn initialise(
     initialise_message: WorkerMessage,
@@ -241,33 +495,92 @@ pub fn move_finished(
     file_manager: Arc<Mutex<FileManager>>,
 ) {
     if let WorkerMessage::MoveFinished(worker_uid, generic_uid, encode) = move_finished_message {
-        if let Err(err) = copy(&encode.temp_target_path, &encode.target_path) {
-            error!(
-                "Failed to copy file from server temp to media library. IO output: {}",
-                err
-            );
-            panic!();
-        }
-        if let Err(err) = remove_file(&encode.temp_target_path) {
-            error!("Failed to remove file from server temp. IO output: {}", err);
-            panic!();
-        }
+        //The worker sends back the paths as it sees them
+        let encode = worker_manager
+            .lock()
+            .unwrap()
+            .get_path_mapper(worker_uid)
+            .encode_to_server(encode);
         //TODO: Make this whole process persistent
         worker_manager
             .lock()
             .unwrap()
             .clear_current_transcode_from_worker(worker_uid, generic_uid);
-        if !file_manager
+        //Validating decodes the whole output and copying it into the library can take minutes, so
+        //it's done off of the websocket handler's thread
+        tokio::task::spawn_blocking(move || {
+            add_encode_to_library(generic_uid, encode, file_manager)
+        });
+    } else {
+        panic!();
+    }
+}
+
+///Checks a finished encode, then moves it from the server temp directory into the library
+fn add_encode_to_library(generic_uid: i32, encode: Encode, file_manager: Arc<Mutex<FileManager>>) {
+    let (server_config, source_length_time) = {
+        let file_manager_lock = file_manager.lock().unwrap();
+        let source_length_time = file_manager_lock
+            .generics()
+            .into_iter()
+            .filter(|generic| generic.get_generic_uid() == generic_uid)
+            .flat_map(|generic| generic.file_versions.iter())
+            .find(|file_version| file_version.full_path == encode.source_path)
+            .and_then(|file_version| file_version.length_time);
+        (file_manager_lock.config.clone(), source_length_time)
+    };
+    let duration_tolerance = server_config.read().unwrap().encode_duration_tolerance;
+    if let Err(err) = validate_encode_output(&encode, source_length_time, duration_tolerance) {
+        reject_encode(&encode, &err.to_string(), &server_config);
+        return;
+    }
+    if let Err(err) = copy(&encode.temp_target_path, &encode.target_path) {
+        error!(
+            "Failed to copy file from server temp to media library. IO output: {}",
+            err
+        );
+        panic!();
+    }
+    if let Err(err) = remove_file(&encode.temp_target_path) {
+        error!("Failed to remove file from server temp. IO output: {}", err);
+        panic!();
+    }
+    if !file_manager
+        .lock()
+        .unwrap()
+        .insert_file_version(&FileVersion::new(generic_uid, &encode.target_path, false))
+    {
+        error!("This should've found a generic to insert it into, this shouldn't have happened.");
+        panic!();
+    }
+    file_manager.lock().unwrap().copy_sidecars_for_encode(
+        generic_uid,
+        &encode.source_path,
+        &encode.target_path,
+    );
+    record_completed_encode(&encode);
+    //TODO: Make an enum of actions that could be performed on a Worker, like clear_current_transcode
+}
+
+pub fn encode_failed(
+    encode_failed_message: WorkerMessage,
+    worker_manager: Arc<Mutex<WorkerManager>>,
+    file_manager: Arc<Mutex<FileManager>>,
+) {
+    if let WorkerMessage::EncodeFailed(worker_uid, generic_uid, encode, reason) =
+        encode_failed_message
+    {
+        let encode = worker_manager
             .lock()
             .unwrap()
-            .insert_file_version(&FileVersion::new(generic_uid, &encode.target_path, false))
-        {
-            error!(
-                "This should've found a generic to insert it into, this shouldn't have happened."
-            );
-            panic!();
-        }
-        //TODO: Make an enum of actions that could be performed on a Worker, like clear_current_transcode
+            .get_path_mapper(worker_uid)
+            .encode_to_server(encode);
+        worker_manager
+            .lock()
+            .unwrap()
+            .clear_current_transcode_from_worker(worker_uid, generic_uid);
+        let server_config = file_manager.lock().unwrap().config.clone();
+        reject_encode(&encode, &reason, &server_config);
     } else {
         panic!();
     }
@@ -276,7 +589,7 @@ pub fn move_finished(
 pub fn encode_file(
     file_manager: Arc<Mutex<FileManager>>,
     worker_mananger_transcode_queue: Arc<Mutex<VecDeque<Encode>>>,
-    encode_profile: &EncodeProfile,
+    encode_profile: Option<&EncodeProfile>,
     generic_uid: i32,
     id: i32,
 ) {